@@ -4,6 +4,12 @@ async fn main() {
     println!("HELLO WORLD!");
 }
 
+mod tags {
+    pub const TAG1: &str = "tag1";
+}
+
+testify::register_tags!([tags::TAG1, "tag2"]);
+
 #[testify::test()]
 fn test_example() {
     panic!("HELLO WORLD!");
@@ -12,15 +18,23 @@ fn test_example() {
 #[testify::test(
     name = "Hello world!",
     case = "success",
-    tags = ["tag1", "tag2"],
+    suite = "integration",
+    tags = [tags::TAG1, "tag2"],
 )]
 fn test_hello_world_success() -> Result<(), String> {
-    Ok(())
+    if !INTEGRATION_READY.load(std::sync::atomic::Ordering::Relaxed) {
+        Err("The setup of the `integration` suite didn't run.".to_string())
+    } else if TAG1_READY.load(std::sync::atomic::Ordering::Relaxed) {
+        Ok(())
+    } else {
+        Err("The setup of `tag1` didn't run.".to_string())
+    }
 }
 
 #[testify::test(
     name = "Hello world!",
     case = "failure",
+    suite = "integration",
     tags = ["tag1", "tag2"],
     should_fail
 )]
@@ -28,8 +42,1206 @@ fn test_hello_world_failure() -> Result<(), String> {
     Err(String::from("This didn't work!"))
 }
 
+// The lowest priority there is, which the attribute has to parse without overflowing.
+#[testify::test(name = "Lowest priority", priority = -2147483648)]
+fn test_lowest_priority() {}
+
+#[testify::test(name = "Runtime threads", priority = 10)]
+fn test_runtime_threads() -> Result<(), String> {
+    // An invalid value falls back to the default, same as an unset one.
+    let expected = std::env::var(testify::RT_THREADS_ENV_VAR_NAME)
+        .ok()
+        .and_then(|threads| threads.parse().ok())
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, usize::from));
+
+    let workers = testify::ASYNC_RT.metrics().num_workers();
+
+    if workers == expected {
+        Ok(())
+    } else {
+        Err(format!("Expected {expected} runtime workers, but there are {workers}."))
+    }
+}
+
+#[testify::test(name = "Time bounds", min_time = "10ms", max_time = "1s")]
+fn test_time_bounds() {
+    std::thread::sleep(std::time::Duration::from_millis(20));
+}
+
+#[derive(Debug)]
+struct ExampleError {
+    #[allow(dead_code)]
+    code: u32,
+}
+
+#[testify::test(
+    name = "Debug error",
+    description = "Checks that errors without a message are shown with their `Debug` output.",
+    should_fail
+)]
+fn test_debug_error() -> Result<(), ExampleError> {
+    Err(ExampleError { code: 42 })
+}
+
+#[testify::test(name = "Assert matches", case = "match")]
+fn test_assert_matches() {
+    testify::assert_matches!("42".parse::<u32>(), Ok(n) if n > 0);
+    testify::macros::assert_matches!(Some(1), Some(_), "it should be `Some`");
+}
+
+#[testify::test(name = "Assert matches", case = "mismatch", should_panic)]
+fn test_assert_matches_mismatch() {
+    testify::assert_matches!("nope".parse::<u32>(), Ok(_));
+}
+
+#[testify::test(name = "Nested cases", case = "Admin > Weak password", should_fail)]
+fn test_nested_admin_weak() -> bool {
+    false
+}
+
+#[testify::test(name = "Nested cases", case = "Admin > Strong password")]
+fn test_nested_admin_strong() {}
+
+#[testify::test(name = "Nested cases", case = "Guest")]
+fn test_nested_guest() {}
+
+#[testify::test(name = "Nested termination", case = "Result<Option<bool>>")]
+fn test_nested_result_option_bool() -> Result<Option<bool>, String> {
+    Ok(Some(true))
+}
+
+#[testify::test(name = "Nested termination", case = "Option<Result<()>>")]
+fn test_nested_option_result() -> Option<Result<(), String>> {
+    Some(Ok(()))
+}
+
+#[testify::test(name = "Nested termination", case = "Result<(ExitCode, bool)>")]
+fn test_nested_result_tuple() -> Result<(std::process::ExitCode, bool), String> {
+    Ok((std::process::ExitCode::SUCCESS, true))
+}
+
+#[testify::test(name = "Nested termination", case = "false inside Ok", should_fail)]
+fn test_nested_false() -> Result<Option<bool>, String> {
+    Ok(Some(false))
+}
+
+#[testify::test(name = "Nested termination", case = "failure in tuple", should_fail)]
+fn test_nested_tuple_failure() -> Result<(std::process::ExitCode, bool), String> {
+    Ok((std::process::ExitCode::FAILURE, true))
+}
+
+#[testify::test(name = "IO error message")]
+fn test_io_error_message() -> Result<(), String> {
+    use testify::TestTermination;
+
+    let result: std::io::Result<()> = std::fs::read("does-not-exist.txt").map(|_| ());
+    let message = result.failure_message().unwrap_or_default();
+
+    if message.starts_with("NotFound: ") && message.contains("os error") {
+        Ok(())
+    } else {
+        Err(format!("Unexpected message: {message}"))
+    }
+}
+
+#[testify::test(name = "IO error", should_fail)]
+fn test_io_error() -> std::io::Result<()> {
+    Err(std::io::ErrorKind::NotFound.into())
+}
+
+#[testify::test(name = "Unit error", should_fail = "`Err(())` has no message")]
+fn test_unit_error() -> Result<(), ()> {
+    Err(())
+}
+
+#[testify::test(name = "Artifacts")]
+fn test_artifacts() -> std::io::Result<()> {
+    let path = testify::artifact_dir().join("output.txt");
+    std::fs::write(&path, "Hello, world!")?;
+
+    assert_eq!(std::fs::read_to_string(path)?, "Hello, world!");
+    Ok(())
+}
+
+#[testify::test(name = "Artifact names", case = "a b")]
+fn test_artifact_names_space() -> bool {
+    testify::artifact_dir().ends_with("Artifact%20names/a%20b")
+}
+
+#[testify::test(name = "Artifact names", case = "a_b")]
+fn test_artifact_names_underscore() -> bool {
+    testify::artifact_dir().ends_with("Artifact%20names/a_b")
+}
+
+/// Writes `owner` to a file in the test's artifact directory, and returns whether the directory
+/// wasn't already claimed by another test.
+fn claim_artifact_dir(owner: &str) -> std::io::Result<bool> {
+    let path = testify::artifact_dir().join("owner.txt");
+
+    match std::fs::read_to_string(&path) {
+        Ok(previous) => Ok(previous == owner),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            std::fs::write(path, owner)?;
+            Ok(true)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+// A suite and name that would share a directory with a suite-less name and case, if tests without
+// a suite didn't have a suite level of their own.
+#[testify::test(suite = "Collision", name = "Artifact")]
+fn test_artifact_suite() -> std::io::Result<bool> {
+    claim_artifact_dir("suite")
+}
+
+#[testify::test(name = "Collision", case = "Artifact")]
+fn test_artifact_case() -> std::io::Result<bool> {
+    claim_artifact_dir("case")
+}
+
+static HOOK_CALLS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+#[testify::test(name = "Panic hooks")]
+fn test_panic_hooks() -> bool {
+    // The hooks aren't called with the registry locked, so a hook can use it.
+    let hook = testify::chain_panic_hook(Box::new(|_| {
+        let _ = testify::PANIC_HOOKS.lock().unwrap().len();
+        HOOK_CALLS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }));
+
+    let before = HOOK_CALLS.load(std::sync::atomic::Ordering::Relaxed);
+    let _ = std::panic::catch_unwind(|| panic!("Calling the panic hooks."));
+    let called = HOOK_CALLS.load(std::sync::atomic::Ordering::Relaxed) > before;
+
+    // Removing the hook only succeeds once, since it's not chained anymore afterwards.
+    called && testify::unchain_panic_hook(&hook) && !testify::unchain_panic_hook(&hook)
+}
+
+#[testify::test(name = "Isolated panic hooks")]
+fn test_isolated_panic_hooks() -> Result<(), String> {
+    // The same test, in a process of its own, where the chained hook has to fire too.
+    let config = testify::runner::TestifyConfig {
+        isolate: true,
+        ..Default::default()
+    };
+    let (report, contents) = run_nested(
+        testify::Runner::new()
+            .with_tests(registered_tests("Panic hooks"))
+            .with_config(config),
+    );
+
+    if report.successes() == 1 {
+        Ok(())
+    } else {
+        Err(format!("Unexpected output:\n{contents}"))
+    }
+}
+
+#[derive(Default)]
+struct Counter {
+    count: u32,
+}
+
+#[testify::tests]
+impl Counter {
+    #[testify::test(name = "Counter starts at zero")]
+    fn starts_at_zero(&self) -> Option<()> {
+        (self.count == 0).then_some(())
+    }
+
+    #[testify::test(name = "Counter increments")]
+    async fn increments(&mut self) {
+        self.count += 1;
+        assert_eq!(self.count, 1);
+    }
+
+    #[testify::test(name = "Counter is default", should_panic)]
+    fn is_default() {
+        assert_ne!(Counter::default().count, 0);
+    }
+
+    // `std`'s `#[test]` can't be used on methods, so it's taken for testify's.
+    #[test(name = "Counter adds")]
+    fn adds(&mut self) -> bool {
+        self.count += 2;
+        self.count == 2
+    }
+
+    #[std::prelude::rust_2024::test]
+    fn doubles(&mut self) -> bool {
+        self.count = 1;
+        self.count * 2 == 2
+    }
+}
+
+static TAG1_READY: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+#[testify::setup(tags = [tags::TAG1])]
+fn setup_tag1() {
+    TAG1_READY.store(true, std::sync::atomic::Ordering::Relaxed);
+}
+
+#[testify::cleanup(tags = [tags::TAG1])]
+async fn cleanup_tag1() {
+    TAG1_READY.store(false, std::sync::atomic::Ordering::Relaxed);
+}
+
+static INTEGRATION_READY: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+#[testify::setup(suite = "integration")]
+fn setup_integration() {
+    INTEGRATION_READY.store(true, std::sync::atomic::Ordering::Relaxed);
+}
+
+#[testify::cleanup(suite = "integration")]
+fn cleanup_integration() {
+    INTEGRATION_READY.store(false, std::sync::atomic::Ordering::Relaxed);
+}
+
+static SERVICE_RUNNING: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+#[testify::setup]
+async fn setup() {
+    for n in [1, 2] {
+        testify::register_test!(format!("Generated test {n}"), move || assert!(n > 0));
+    }
+
+    testify::spawn_service(async {
+        SERVICE_RUNNING.store(true, std::sync::atomic::Ordering::Relaxed);
+        std::future::pending::<()>().await;
+    });
+}
+
+#[testify::test(name = "Background service")]
+async fn test_background_service() -> Result<(), testify::WaitTimeout> {
+    testify::wait_until_async(
+        || async { SERVICE_RUNNING.load(std::sync::atomic::Ordering::Relaxed) },
+        std::time::Duration::from_secs(1),
+    )
+    .await
+}
+
+#[testify::test(name = "Wait timeout")]
+fn test_wait_timeout() -> Result<(), String> {
+    let result = testify::wait_until_every(
+        || false,
+        std::time::Duration::from_millis(30),
+        std::time::Duration::from_millis(10),
+    );
+
+    match result {
+        Err(timeout) if timeout.attempts >= 2 => Ok(()),
+        other => Err(format!("Expected a timeout after a few attempts, got {other:?}.")),
+    }
+}
+
+static SETUP_ORDER: std::sync::Mutex<Vec<&str>> = std::sync::Mutex::new(Vec::new());
+
+// Declared before `setup_first` so that the order doesn't depend on the declaration order.
+#[testify::setup(order = 2)]
+async fn setup_second() {
+    tokio::task::yield_now().await;
+    SETUP_ORDER.lock().unwrap().push("second");
+}
+
+#[testify::setup(order = -1)]
+fn setup_first() {
+    SETUP_ORDER.lock().unwrap().push("first");
+}
+
+#[testify::test(name = "Setup order")]
+fn test_setup_order() -> Result<(), String> {
+    let order = SETUP_ORDER.lock().unwrap();
+
+    // With `--repeat-setup`, the setups run again before every repetition.
+    if !order.is_empty() && order.chunks(2).all(|setups| setups == ["first", "second"]) {
+        Ok(())
+    } else {
+        Err(format!("The setups ran in the wrong order: {order:?}"))
+    }
+}
+
+static FUTURE_SETUP_RAN: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+async fn mark_future_setup() {
+    tokio::task::yield_now().await;
+    FUTURE_SETUP_RAN.store(true, std::sync::atomic::Ordering::Relaxed);
+}
+
 #[testify::setup]
-async fn setup() {}
+fn setup_returning_future() -> impl std::future::Future<Output = ()> {
+    mark_future_setup()
+}
+
+#[testify::test(name = "Future-returning setup")]
+fn test_future_setup() -> bool {
+    FUTURE_SETUP_RAN.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+#[testify::test(name = "Captured output")]
+fn test_captured_output() -> Result<(), String> {
+    let result = testify::test::run_test_fn(
+        || {
+            testify::println!("Sending {} requests", 2);
+            testify::eprintln!("Request failed");
+            false
+        },
+        false,
+        false,
+    );
+
+    match result.output.as_deref() {
+        Some("Sending 2 requests\nRequest failed\n") => Ok(()),
+        output => Err(format!("Unexpected output: {output:?}")),
+    }
+}
+
+#[testify::test(name = "Is test run")]
+fn test_is_test_run() -> bool {
+    testify::is_test_run()
+}
+
+#[testify::test(name = "Plan")]
+fn test_plan() -> Result<(), String> {
+    let cases: Vec<testify::PlannedCase> = testify::plan()
+        .into_iter()
+        .flat_map(|group| group.tests)
+        .flat_map(|test| test.cases)
+        .collect();
+
+    if !cases.iter().any(|case| case.id == "Plan") {
+        return Err("The plan doesn't include the running test.".to_string());
+    }
+
+    if cases.iter().any(|case| case.module_path.ends_with("experimental")) {
+        return Err("The plan includes ignored tests.".to_string());
+    }
+
+    Ok(())
+}
+
+mod defaults {
+    #[testify::test]
+    fn works() {}
+
+    #[testify::test(name = "Named", case = "first")]
+    fn named() {}
+}
+
+#[testify::test(name = "Default names")]
+fn test_default_names() -> Result<(), String> {
+    // Planned without the run's filters, so that the tests it looks for are always included.
+    let names: Vec<String> = testify::Runner::new()
+        .plan()
+        .into_iter()
+        .flat_map(|group| group.tests)
+        .map(|test| test.name)
+        .collect();
+
+    if ["defaults::works", "test_example"]
+        .iter()
+        .all(|expected| names.iter().any(|name| name == expected))
+    {
+        Ok(())
+    } else {
+        Err(format!("Unexpected default names: {names:?}"))
+    }
+}
+
+#[testify::bench(name = "Sorting", iterations = 50)]
+fn bench_sorting() {
+    let mut numbers: Vec<u32> = (0..1000).rev().collect();
+    numbers.sort();
+    std::hint::black_box(numbers);
+}
+
+#[testify::test(name = "Benchmark stats")]
+fn test_bench_stats() -> Result<(), String> {
+    let result = testify::test::run_bench(|| {}, 5);
+
+    match result.bench {
+        Some(stats) if stats.iterations == 5 => Ok(()),
+        stats => Err(format!("Unexpected benchmark stats: {stats:?}")),
+    }
+}
+
+#[testify::test(name = "Fresh runtime")]
+fn test_fresh_runtime() -> Result<(), String> {
+    let mut task = None;
+
+    testify::runtime::block_on_test(
+        async { task = Some(tokio::spawn(std::future::pending::<()>())) },
+        true,
+    );
+
+    match testify::async_runtime().block_on(task.unwrap()) {
+        Err(e) if e.is_cancelled() => Ok(()),
+        result => Err(format!("The task outlived its runtime: {result:?}")),
+    }
+}
+
+#[testify::test(name = "Fresh runtime attribute", fresh_runtime)]
+async fn test_fresh_runtime_attribute() {
+    tokio::spawn(async {}).await.unwrap();
+}
+
+#[testify::test(name = "Early exit", case = "pass")]
+fn test_early_pass() {
+    testify::pass!();
+    #[allow(unreachable_code)]
+    {
+        panic!("`testify::pass!` didn't stop the test.");
+    }
+}
+
+#[testify::test(name = "Early exit", case = "fail", should_fail = "fails early")]
+fn test_early_fail() -> Result<(), String> {
+    for n in 0..3 {
+        if n == 1 {
+            testify::fail!("Failed at {n}");
+        }
+    }
+
+    Ok(())
+}
+
+#[testify::test(name = "Early exit", case = "message")]
+fn test_early_fail_message() -> Result<(), String> {
+    let result = testify::test::run_test_fn(
+        || -> Result<(), String> { testify::fail!("Failed at {}", 1) },
+        false,
+        false,
+    );
+
+    match (result.status, result.message.as_deref()) {
+        (testify::test::TestStatus::Failed, Some("Failed at 1")) => Ok(()),
+        (status, message) => Err(format!("Unexpected result: {status:?}, {message:?}")),
+    }
+}
+
+struct Validated(usize);
+
+impl testify::TestTermination for Validated {
+    fn success(&self) -> bool {
+        true
+    }
+
+    fn success_detail(&self) -> Option<String> {
+        Some(format!("validated {} records", self.0))
+    }
+}
+
+#[testify::test(name = "Success detail", case = "reported")]
+fn test_success_detail() -> Result<(), String> {
+    let result = testify::test::run_test_fn(|| Ok::<_, ()>(Validated(1500)), false, false);
+
+    match result.detail.as_deref() {
+        Some("validated 1500 records") => Ok(()),
+        detail => Err(format!("Unexpected success detail: {detail:?}")),
+    }
+}
+
+#[testify::test(name = "Success detail", case = "shown")]
+fn test_success_detail_shown() -> Validated {
+    Validated(3)
+}
+
+#[testify::test(name = "Success detail", case = "isolated")]
+fn test_success_detail_isolated() -> Result<(), String> {
+    // The shown case, in a process of its own, which has to send its detail back.
+    let tests = registered_tests("Success detail")
+        .into_iter()
+        .filter(|test| test.case.as_deref() == Some("shown"))
+        .collect();
+    let config = testify::runner::TestifyConfig {
+        isolate: true,
+        ..Default::default()
+    };
+    let (report, contents) =
+        run_nested(testify::Runner::new().with_tests(tests).with_config(config));
+    let detail = report
+        .outcomes
+        .first()
+        .and_then(|outcome| outcome.result.detail.as_deref());
+
+    if detail == Some("validated 3 records") && contents.contains("- validated 3 records") {
+        Ok(())
+    } else {
+        Err(format!("Unexpected output:\n{contents}"))
+    }
+}
+
+static FIRST_ATTEMPT: std::sync::Mutex<Option<std::time::Instant>> = std::sync::Mutex::new(None);
+
+// Flaky on purpose: the first attempt fails, and the retry checks that it waited for the backoff.
+#[testify::test(name = "Retry backoff", retries = 1, retry_backoff = "20ms")]
+fn test_retry_backoff() -> Result<(), String> {
+    let mut first_attempt = FIRST_ATTEMPT.lock().unwrap();
+
+    match *first_attempt {
+        None => {
+            *first_attempt = Some(std::time::Instant::now());
+            Err("The first attempt fails.".to_string())
+        }
+        Some(start) if start.elapsed() >= std::time::Duration::from_millis(20) => Ok(()),
+        Some(start) => Err(format!("Retried after {:?}.", start.elapsed())),
+    }
+}
+
+static FIRST_ASYNC_ATTEMPT: std::sync::Mutex<Option<std::time::Instant>> =
+    std::sync::Mutex::new(None);
+
+// The same, but async, which waits for the backoff on the async runtime.
+#[testify::test(name = "Async retry backoff", retries = 1, retry_backoff = "20ms")]
+async fn test_async_retry_backoff() -> Result<(), String> {
+    let first_attempt = *FIRST_ASYNC_ATTEMPT.lock().unwrap();
+
+    match first_attempt {
+        None => {
+            *FIRST_ASYNC_ATTEMPT.lock().unwrap() = Some(std::time::Instant::now());
+            Err("The first attempt fails.".to_string())
+        }
+        Some(_) if !registered_tests("Async retry backoff")[0].is_async => {
+            Err("The test isn't known to be async.".to_string())
+        }
+        Some(start) if start.elapsed() >= std::time::Duration::from_millis(20) => Ok(()),
+        Some(start) => Err(format!("Retried after {:?}.", start.elapsed())),
+    }
+}
+
+#[testify::test(name = "Status reasons")]
+fn test_status_reasons() -> Result<(), String> {
+    use testify::test::TestStatus;
+
+    let reasons = [
+        (TestStatus::Passed, "passed"),
+        (TestStatus::Panicked, "panicked"),
+        (TestStatus::NotPanicked, "not_panicked"),
+        (TestStatus::Failed, "failed"),
+        (TestStatus::NotFailed, "not_failed"),
+        (TestStatus::Skipped, "skipped"),
+    ];
+
+    for (status, reason) in reasons {
+        if status.reason() != reason {
+            return Err(format!("{status:?} has the reason {:?}, not {reason:?}.", status.reason()));
+        }
+    }
+
+    Ok(())
+}
+
+#[testify::test(name = "Current-thread flavor", flavor = "current_thread")]
+async fn test_current_thread_flavor() -> Result<(), String> {
+    let count = std::rc::Rc::new(std::cell::Cell::new(0));
+    let task = tokio::task::spawn_local({
+        let count = count.clone();
+        async move { count.set(count.get() + 1) }
+    });
+
+    task.await.map_err(|e| e.to_string())?;
+
+    if count.get() == 1 {
+        Ok(())
+    } else {
+        Err(format!("The local task ran {} times.", count.get()))
+    }
+}
+
+#[testify::test(name = "Test paths")]
+fn test_paths() -> Result<(), String> {
+    let paths: Vec<String> = testify::Runner::new()
+        .plan()
+        .into_iter()
+        .flat_map(|group| group.tests)
+        .flat_map(|test| test.cases)
+        .map(|case| case.path)
+        .collect();
+
+    if ["defaults::works", "defaults::Named (first)", "Hello world! (success)"]
+        .iter()
+        .all(|expected| paths.iter().any(|path| path == expected))
+    {
+        Ok(())
+    } else {
+        Err(format!("Unexpected test paths: {paths:?}"))
+    }
+}
+
+const COMPUTED_VERSION: u32 = 2;
+
+#[testify::test(
+    name = format!("Computed metadata v{COMPUTED_VERSION}"),
+    case = tags::TAG1,
+    tags = [concat!("tag", "2")],
+)]
+fn test_computed_metadata() -> Result<(), String> {
+    let found = testify::Runner::new()
+        .plan()
+        .into_iter()
+        .flat_map(|group| group.tests)
+        .filter(|test| test.name == "Computed metadata v2")
+        .flat_map(|test| test.cases)
+        .any(|case| case.case.as_deref() == Some(tags::TAG1) && case.tags == ["tag2"]);
+
+    if found {
+        Ok(())
+    } else {
+        Err("The computed name, case or tags weren't registered.".to_string())
+    }
+}
+
+#[testify::test(name = "Regex filter")]
+fn test_regex_filter() -> Result<(), String> {
+    let config = testify::runner::TestifyConfig {
+        name_filters: vec!["^defaults::w".to_string()],
+        use_regex: true,
+        ..Default::default()
+    };
+
+    let names: Vec<String> = testify::Runner::new()
+        .with_config(config)
+        .plan()
+        .into_iter()
+        .flat_map(|group| group.tests)
+        .map(|test| test.name)
+        .collect();
+
+    if names == ["defaults::works"] {
+        Ok(())
+    } else {
+        Err(format!("Unexpected tests: {names:?}"))
+    }
+}
+
+#[testify::test(name = "Prometheus metrics")]
+fn test_prometheus_metrics() -> Result<(), String> {
+    let metrics = testify::metrics::format(&testify::TestReport::default());
+
+    if metrics.contains("# TYPE testify_tests_total gauge\ntestify_tests_total 0\n") {
+        Ok(())
+    } else {
+        Err(format!("Unexpected metrics:\n{metrics}"))
+    }
+}
+
+#[testify::test(name = "No captured output", case = "silent", no_captured_output)]
+fn test_no_captured_output() {
+    let verbose = false;
+
+    if verbose {
+        testify::println!("Only printed when debugging.");
+    }
+}
+
+/// Runs `runner` nested in the current run, returning its report and what it printed. Runs aren't
+/// reentrant, since they replace the panic hook while they run, so they're run one at a time.
+fn run_nested(runner: testify::Runner) -> (testify::TestReport, String) {
+    static NESTED_RUN: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    let _lock = NESTED_RUN
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+    let output = testify::OutputBuffer::default();
+    let report = runner.with_output(output.clone()).run();
+
+    (report, output.contents())
+}
+
+/// The registered tests named `name`, for a nested runner to run.
+fn registered_tests(name: &str) -> Vec<testify::test::Test> {
+    testify::TESTS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|test| test.name == name)
+        .cloned()
+        .collect()
+}
+
+static REPEATED_SETUPS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+static REPEATED_CLEANUPS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+#[testify::test(name = "Repeated runs")]
+fn test_repeated_runs() -> Result<(), String> {
+    use std::sync::atomic::Ordering;
+
+    let mut tests = registered_tests("defaults::works");
+    tests.iter_mut().for_each(|test| test.tags = vec!["repeated".to_string()]);
+
+    // The tag-scoped setup and cleanup run once, unless they're repeated with every iteration.
+    for (repeat_setup, expected) in [(false, 1), (true, 3)] {
+        REPEATED_SETUPS.store(0, Ordering::Relaxed);
+        REPEATED_CLEANUPS.store(0, Ordering::Relaxed);
+
+        let config = testify::runner::TestifyConfig {
+            repeat_until_failure: Some(3),
+            repeat_setup,
+            ..Default::default()
+        };
+        let (report, contents) = run_nested(
+            testify::Runner::new()
+                .with_tests(tests.clone())
+                .with_config(config)
+                .with_tag_setup(vec!["repeated".to_string()], || {
+                    REPEATED_SETUPS.fetch_add(1, Ordering::Relaxed);
+                })
+                .with_tag_cleanup(vec!["repeated".to_string()], || {
+                    REPEATED_CLEANUPS.fetch_add(1, Ordering::Relaxed);
+                }),
+        );
+
+        let counts = (
+            REPEATED_SETUPS.load(Ordering::Relaxed),
+            REPEATED_CLEANUPS.load(Ordering::Relaxed),
+        );
+
+        if report.successes() != 1
+            || counts != (expected, expected)
+            || !contents.contains("Iteration 3 of 3")
+            || !contents.contains("All 3 iterations passed.")
+        {
+            return Err(format!(
+                "Unexpected run, with {counts:?} setups and cleanups:\n{contents}"
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[testify::test(name = "Deeply nested cases")]
+fn test_deeply_nested_cases() -> Result<(), String> {
+    // The nested cases, one level deeper, with each level adding up the ones inside it.
+    let mut tests = registered_tests("Nested cases");
+    let mut email = tests
+        .iter()
+        .find(|test| test.case.as_deref() == Some("Admin > Strong password"))
+        .cloned()
+        .ok_or("The strong password case isn't registered.")?;
+    email.case = Some("Admin > Email".to_string());
+    tests.push(email);
+
+    for test in &mut tests {
+        test.case = test
+            .case
+            .as_deref()
+            .map(|case| case.replace("Admin > ", "Admin > Passwords > "))
+            .map(|case| case.replace("Passwords > Email", "Email"));
+    }
+
+    let (report, contents) = run_nested(testify::Runner::new().with_tests(tests));
+    let position = |line: &str| contents.find(line).unwrap_or(usize::MAX);
+
+    if report.successes() == 4
+        && position("Case Admin...") < position("Case Email...")
+        && position("Case Email...") < position("Case Passwords...")
+        && position("Case Passwords...") < position("2/2 passed.")
+        && position("2/2 passed.") < position("3/3 passed.")
+        && position("3/3 passed.") < position("Case Guest...")
+        && position("Case Guest...") < usize::MAX
+    {
+        Ok(())
+    } else {
+        Err(format!("Unexpected output:\n{contents}"))
+    }
+}
+
+#[testify::test(name = "No captured output", case = "printing")]
+fn test_no_captured_output_printing() -> Result<(), String> {
+    // The same test, but printing, which has to fail even though it passed.
+    let mut test = registered_tests("No captured output")
+        .into_iter()
+        .find(|test| test.case.as_deref() == Some("silent"))
+        .ok_or("The silent case isn't registered.")?;
+    test.function = std::sync::Arc::new(|| {
+        testify::test::run_test_fn(|| testify::println!("Printed anyway."), false, false)
+    });
+
+    let (report, contents) = run_nested(testify::Runner::new().with_tests(vec![test]));
+
+    if report.failures() == 1 && contents.contains("marked with `no_captured_output`") {
+        Ok(())
+    } else {
+        Err(format!("Unexpected output:\n{contents}"))
+    }
+}
+
+#[testify::test(name = "Nested test output")]
+fn test_nested_test_output() -> Result<(), String> {
+    let mut inner = None;
+    let outer = testify::test::run_test_fn(
+        || {
+            testify::println!("Before.");
+            inner =
+                testify::test::run_test_fn(|| testify::println!("Inside."), false, false).output;
+            testify::println!("After.");
+        },
+        false,
+        false,
+    );
+
+    // The inner output is part of the outer one, which keeps capturing after it.
+    match (inner.as_deref(), outer.output.as_deref()) {
+        (Some("Inside.\n"), Some("Before.\nInside.\nAfter.\n")) => Ok(()),
+        other => Err(format!("Unexpected output: {other:?}")),
+    }
+}
+
+#[testify::test(name = "Timeout", timeout = "5s")]
+fn test_timeout() {
+    std::thread::sleep(std::time::Duration::from_millis(10));
+}
+
+#[testify::test(name = "Timeout exceeded")]
+fn test_timeout_exceeded() -> Result<(), String> {
+    // The same test, but sleeping for longer than a shorter timeout.
+    let mut test = registered_tests("Timeout")
+        .pop()
+        .ok_or("The timeout test isn't registered.")?;
+    test.timeout = Some(std::time::Duration::from_millis(20));
+    test.function = std::sync::Arc::new(|| {
+        testify::test::run_test_fn(
+            || std::thread::sleep(std::time::Duration::from_millis(500)),
+            false,
+            false,
+        )
+    });
+
+    let (report, contents) = run_nested(testify::Runner::new().with_tests(vec![test]));
+
+    if report.failures() == 1 && contents.contains("The test timed out after 20ms") {
+        Ok(())
+    } else {
+        Err(format!("Unexpected output:\n{contents}"))
+    }
+}
+
+#[testify::test(name = "Captured runner output")]
+fn test_captured_runner_output() -> Result<(), String> {
+    let (report, contents) =
+        run_nested(testify::Runner::new().with_tests(registered_tests("defaults::works")));
+
+    if report.successes() == 1 && contents.contains("defaults::works...") {
+        Ok(())
+    } else {
+        Err(format!("Unexpected output:\n{contents}"))
+    }
+}
+
+#[testify::test(name = "Duration precision")]
+fn test_duration_precision() -> Result<(), String> {
+    let mut config = testify::runner::TestifyConfig::default();
+    config.theme.duration_precision = Some(3);
+
+    // The timeout test sleeps for 10ms, so its duration is never shown in nanoseconds, which have
+    // no decimals.
+    let (_, contents) = run_nested(
+        testify::Runner::new()
+            .with_tests(registered_tests("Timeout"))
+            .with_config(config),
+    );
+
+    // Durations have three decimals, as in `(10.123ms)`.
+    let line = contents
+        .lines()
+        .find(|line| line.contains("Timeout..."))
+        .unwrap_or_default();
+    let duration = line
+        .rsplit_once('(')
+        .and_then(|(_, duration)| duration.split_once('.'))
+        .map(|(_, decimals)| decimals.chars().take_while(char::is_ascii_digit).count());
+
+    if duration == Some(3) {
+        Ok(())
+    } else {
+        Err(format!("Unexpected output:\n{contents}"))
+    }
+}
+
+#[testify::test(name = "Reports")]
+fn test_reports() -> Result<(), String> {
+    let dir = testify::artifact_dir();
+    let config = testify::runner::TestifyConfig {
+        reports: vec![
+            (testify::reports::ReportKind::Junit, dir.join("junit.xml")),
+            (testify::reports::ReportKind::Json, dir.join("report.json")),
+            (testify::reports::ReportKind::Human, dir.join("output.txt")),
+        ],
+        ..Default::default()
+    };
+
+    let mut tests = registered_tests("defaults::works");
+    tests.iter_mut().for_each(|test| {
+        test.description = Some("Checks the defaults.".to_string());
+        test.tags = vec!["reported".to_string()];
+    });
+    let (path, file) = (tests[0].path(), tests[0].file.clone());
+
+    run_nested(testify::Runner::new().with_tests(tests).with_config(config));
+
+    let junit = std::fs::read_to_string(dir.join("junit.xml")).map_err(|e| e.to_string())?;
+    let json = std::fs::read_to_string(dir.join("report.json")).map_err(|e| e.to_string())?;
+    let output = std::fs::read_to_string(dir.join("output.txt")).map_err(|e| e.to_string())?;
+
+    let junit_fields = [
+        "<testcase name=\"defaults::works\"".to_string(),
+        format!("<property name=\"path\" value=\"{path}\" />"),
+        "<property name=\"description\" value=\"Checks the defaults.\" />".to_string(),
+        "<property name=\"tag\" value=\"reported\" />".to_string(),
+        "<property name=\"tag:reported\" value=\"1 passed, 0 failed\" />".to_string(),
+        format!("<property name=\"file:{file}\" value=\"1 passed, 0 failed\" />"),
+    ];
+    let json_fields = [
+        format!("\"path\": \"{path}\""),
+        "\"description\": \"Checks the defaults.\"".to_string(),
+        "\"reported\": {\n        \"failed\": 0,\n        \"passed\": 1".to_string(),
+        format!("\"{file}\": {{\n        \"failed\": 0,\n        \"passed\": 1"),
+    ];
+
+    let missing_junit = junit_fields
+        .iter()
+        .find(|field| !junit.contains(field.as_str()));
+    let missing_json = json_fields
+        .iter()
+        .find(|field| !json.contains(field.as_str()));
+
+    if let Some(field) = missing_junit {
+        Err(format!("The JUnit report is missing `{field}`:\n{junit}"))
+    } else if let Some(field) = missing_json {
+        Err(format!("The JSON report is missing `{field}`:\n{json}"))
+    } else if !output.contains("Finished running tests.") {
+        Err(format!("Unexpected human report:\n{output}"))
+    } else {
+        Ok(())
+    }
+}
+
+#[testify::test(name = "Reports of skipped tests")]
+fn test_reports_skipped() -> Result<(), String> {
+    let dir = testify::artifact_dir();
+    let config = testify::runner::TestifyConfig {
+        reports: vec![
+            (testify::reports::ReportKind::Junit, dir.join("junit.xml")),
+            (testify::reports::ReportKind::Tap, dir.join("report.tap")),
+        ],
+        fail_fast: true,
+        ..Default::default()
+    };
+
+    // A failing test with a colored message, which stops the run before the timeout test.
+    let mut tests = registered_tests("Cache write");
+    tests.iter_mut().for_each(|test| {
+        test.function = std::sync::Arc::new(|| {
+            testify::test::run_test_fn(|| Err::<(), _>("\u{1b}[31mDown\u{1b}[0m"), false, false)
+        });
+    });
+    tests.extend(registered_tests("Timeout"));
+
+    run_nested(testify::Runner::new().with_tests(tests).with_config(config));
+
+    let junit = std::fs::read_to_string(dir.join("junit.xml")).map_err(|e| e.to_string())?;
+    let tap = std::fs::read_to_string(dir.join("report.tap")).map_err(|e| e.to_string())?;
+
+    if !junit.contains("tests=\"2\" failures=\"1\" skipped=\"1\"")
+        || !junit.contains("<skipped message=\"The run stopped before reaching it.\" />")
+        || junit.contains('\u{1b}')
+    {
+        Err(format!("Unexpected JUnit report:\n{junit}"))
+    } else if !tap.contains("ok 2 - Timeout # SKIP The run stopped before reaching it.") {
+        Err(format!("Unexpected TAP report:\n{tap}"))
+    } else {
+        Ok(())
+    }
+}
+
+#[testify::test(name = "Known slow")]
+fn test_known_slow() -> Result<(), String> {
+    // The timeout test, which sleeps for 10ms, once as it is and once marked as slow.
+    let unmarked = registered_tests("Timeout")
+        .pop()
+        .ok_or("The timeout test isn't registered.")?;
+    let mut marked = unmarked.clone();
+    marked.case = Some("marked".to_string());
+    marked.slow = true;
+
+    let dir = testify::artifact_dir();
+    let config = testify::runner::TestifyConfig {
+        warn_slow: Some(std::time::Duration::from_millis(1)),
+        reports: vec![
+            (testify::reports::ReportKind::Junit, dir.join("junit.xml")),
+            (testify::reports::ReportKind::Tap, dir.join("report.tap")),
+        ],
+        ..Default::default()
+    };
+
+    let (report, contents) = run_nested(
+        testify::Runner::new()
+            .with_tests(vec![unmarked, marked])
+            .with_config(config),
+    );
+
+    let junit = std::fs::read_to_string(dir.join("junit.xml")).map_err(|e| e.to_string())?;
+    let tap = std::fs::read_to_string(dir.join("report.tap")).map_err(|e| e.to_string())?;
+
+    // Only the unmarked test is warned about, and the marked one is shown as slow.
+    if report.warnings.len() != 1
+        || report.warnings[0].contains("marked")
+        || !contents.contains("(slow)")
+    {
+        Err(format!("Unexpected output:\n{contents}"))
+    } else if junit.matches("<property name=\"slow\" value=\"true\" />").count() != 1 {
+        Err(format!("Unexpected JUnit report:\n{junit}"))
+    } else if tap.matches("slow: true").count() != 1 {
+        Err(format!("Unexpected TAP report:\n{tap}"))
+    } else {
+        Ok(())
+    }
+}
+
+#[testify::test(name = "Completes within", case = "in time")]
+async fn test_completes_within() {
+    let value = testify::assert_completes_within!(std::time::Duration::from_secs(1), async { 42 });
+    assert_eq!(value, 42);
+}
+
+#[testify::test(name = "Completes within", case = "too late", should_panic)]
+async fn test_completes_within_too_late() {
+    testify::assert_completes_within!(
+        std::time::Duration::from_millis(10),
+        std::future::pending::<()>()
+    );
+}
+
+#[testify::test(name = "Empty run")]
+fn test_empty_run() -> Result<(), String> {
+    let (report, contents) = run_nested(
+        testify::Runner::new()
+            .with_tests(Vec::new())
+            .with_setup(|| panic!("The setup shouldn't run without tests.")),
+    );
+
+    // The tests were given, so the setup can't add any with `register_test!` even though this
+    // binary calls it, and it's skipped.
+    if report.no_tests
+        && contents.contains("No tests are registered")
+        && report.warnings.is_empty()
+    {
+        Ok(())
+    } else {
+        Err(format!("Unexpected output:\n{contents}"))
+    }
+}
+
+static CACHE: std::sync::Mutex<Option<u32>> = std::sync::Mutex::new(None);
+
+#[testify::test(name = "Cache write")]
+fn test_cache_write() {
+    *CACHE.lock().unwrap() = Some(42);
+}
+
+// Sorted before "Cache write" by name, but run after it.
+#[testify::test(name = "Cache read", depends_on = "Cache write")]
+fn test_cache_read() -> Result<(), String> {
+    match *CACHE.lock().unwrap() {
+        Some(42) => Ok(()),
+        value => Err(format!("The cache wasn't written first: {value:?}")),
+    }
+}
+
+// Skipped, since `test_example` fails on purpose.
+#[testify::test(name = "After the example", depends_on = "test_example")]
+fn test_after_example() {
+    panic!("This test depends on a failing test, so it should be skipped.");
+}
+
+#[testify::test(name = "Skipped dependents")]
+fn test_skipped_dependents() -> Result<(), String> {
+    // The cache tests, but with a write that fails, so that the read is skipped.
+    let mut tests = registered_tests("Cache write");
+    tests.iter_mut().for_each(|test| {
+        test.function = std::sync::Arc::new(|| {
+            testify::test::run_test_fn(|| Err::<(), _>("The cache is down."), false, false)
+        });
+    });
+    tests.extend(registered_tests("Cache read"));
+
+    let (report, contents) = run_nested(testify::Runner::new().with_tests(tests));
+    let skipped = report
+        .outcomes
+        .iter()
+        .find(|outcome| outcome.test.name == "Cache read")
+        .map(|outcome| outcome.result.status);
+
+    if report.failures() == 1
+        && report.not_run == 1
+        && skipped == Some(testify::test::TestStatus::Skipped)
+    {
+        Ok(())
+    } else {
+        Err(format!("Unexpected output:\n{contents}"))
+    }
+}
+
+#[testify::test(name = "Dependency cycle")]
+fn test_dependency_cycle() -> Result<(), String> {
+    let mut tests = registered_tests("Cache write");
+    tests.extend(registered_tests("Cache read"));
+    tests[0].depends_on = vec!["Cache read".to_string()];
+
+    let (report, contents) = run_nested(testify::Runner::new().with_tests(tests));
+
+    if report.outcomes.is_empty()
+        && report
+            .setup_error
+            .is_some_and(|error| error.contains("form a cycle"))
+    {
+        Ok(())
+    } else {
+        Err(format!("Unexpected output:\n{contents}"))
+    }
+}
+
+mod hashed {
+    #[testify::test]
+    fn works() {
+        assert_eq!([1, 1].iter().sum::<u32>(), 2);
+    }
+}
+
+mod hashed_documented {
+    #[testify::test]
+    /// The same test as `hashed::works`, which the doc comment doesn't make different.
+    fn works() {
+        // Neither does this comment.
+        assert_eq!([1, 1].iter().sum::<u32>(), 2);
+    }
+}
+
+#[testify::test(name = "Source hash")]
+fn test_source_hash() -> Result<(), String> {
+    let tests = testify::TESTS.lock().unwrap();
+    let hash = |name: &str| {
+        tests
+            .iter()
+            .find(|test| test.name == name)
+            .and_then(|test| test.source_hash)
+    };
+
+    match (hash("hashed::works"), hash("hashed_documented::works")) {
+        (Some(a), Some(b)) if a == b => Ok(()),
+        hashes => Err(format!("Expected the same hashes, got {hashes:?}")),
+    }
+}
+
+// Ignored by `.testifyignore`, so it's never run.
+mod experimental {
+    #[testify::test(name = "Experimental")]
+    fn test_experimental() {
+        panic!("Tests in `example::experimental` should be ignored.");
+    }
+}
 
 // #[testify::group(tags = ["one", "two", "three"], in_order)]
 // mod tests {
@@ -59,4 +1271,9 @@ async fn setup() {}
 // }
 
 #[testify::cleanup]
-async fn cleanup() {}
+async fn cleanup() {
+    assert!(
+        testify::last_report().is_some(),
+        "The report should be available to the cleanup."
+    );
+}
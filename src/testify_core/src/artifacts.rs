@@ -0,0 +1,92 @@
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::test::Test;
+
+thread_local! {
+    /// The artifact directory of the test running on this thread, if any.
+    static CURRENT_ARTIFACT_DIR: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+/// Returns the directory where the running test can write its artifacts (screenshots, dumps,
+/// etc.), creating it if it doesn't exist yet. Every test case gets its own directory inside the
+/// run's output directory, which can be set with `cargo testify --output-dir`.
+///
+/// The directory is tracked per thread, so it must be called from the test itself and not from a
+/// thread or task spawned by it.
+///
+/// # Panics
+///
+/// If it's called outside of a test, or if the directory could not be created.
+pub fn artifact_dir() -> PathBuf {
+    let dir = CURRENT_ARTIFACT_DIR
+        .with_borrow(Clone::clone)
+        .expect("testify::artifact_dir() can only be called from a running test");
+
+    std::fs::create_dir_all(&dir).unwrap_or_else(|e| {
+        panic!("Could not create the artifact directory {}: {e}", dir.display())
+    });
+
+    dir
+}
+
+/// Returns the output directory used when `--output-dir` isn't passed, which is unique to the run.
+pub(crate) fn default_output_dir() -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis());
+
+    std::env::temp_dir()
+        .join("testify")
+        .join(format!("{timestamp}-{}", std::process::id()))
+}
+
+/// Turns a test's name or case into a string that's safe to use as a directory name. Other
+/// characters are percent-encoded, so that different names never share a directory.
+fn path_segment(value: &str) -> String {
+    let mut segment = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        if c.is_alphanumeric() || c == '-' || c == '_' {
+            segment.push(c);
+        } else {
+            for byte in c.encode_utf8(&mut [0; 4]).bytes() {
+                segment.push_str(&format!("%{byte:02X}"));
+            }
+        }
+    }
+
+    segment
+}
+
+/// Returns the artifact directory of a test case, as `<output dir>/<suite>/<name>[/<case>]`. Tests
+/// without a suite use a bare `%`, which an encoded name never is, so that a test's name is never
+/// taken for a suite.
+pub(crate) fn case_dir(output_dir: &Path, test: &Test) -> PathBuf {
+    let mut dir = output_dir.to_path_buf();
+
+    match &test.suite {
+        Some(suite) => dir.push(path_segment(suite)),
+        None => dir.push("%"),
+    }
+
+    dir.push(path_segment(&test.name));
+
+    if let Some(case) = &test.case {
+        dir.push(path_segment(case));
+    }
+
+    dir
+}
+
+/// Runs `f` with `dir` as the current thread's artifact directory.
+pub(crate) fn with_artifact_dir<T>(dir: PathBuf, f: impl FnOnce() -> T) -> T {
+    let previous = CURRENT_ARTIFACT_DIR.replace(Some(dir));
+    let result = f();
+    CURRENT_ARTIFACT_DIR.set(previous);
+
+    result
+}
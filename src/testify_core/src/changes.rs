@@ -0,0 +1,55 @@
+use std::{collections::BTreeMap, io, path::Path};
+
+use crate::{
+    runner::TestOutcome,
+    test::{Test, TestStatus},
+};
+
+/// The file where the source hashes of the tests that passed are stored by default.
+pub const DEFAULT_TEST_HASHES_FILE: &str = ".testify/test-hashes.json";
+
+/// Reads the source hashes written by `save`, mapping the IDs of the tests to the hash they had
+/// when they last passed. It's empty if there's no file yet, so that every test counts as changed.
+pub fn load(path: &Path) -> Result<BTreeMap<String, u64>, String> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(e) => {
+            return Err(format!(
+                "Could not read the test hashes {}: {e}.",
+                path.display()
+            ));
+        }
+    };
+
+    serde_json::from_str(&contents)
+        .map_err(|e| format!("Could not parse the test hashes {}: {e}.", path.display()))
+}
+
+/// Whether a test is new or its source changed since it last passed, according to `baseline`.
+/// Tests registered at runtime have no hash, so they always count as changed.
+pub(crate) fn is_changed(test: &Test, baseline: &BTreeMap<String, u64>) -> bool {
+    test.source_hash
+        .is_none_or(|hash| baseline.get(&test.id()) != Some(&hash))
+}
+
+/// Writes `baseline` to `path`, updated with the hashes of the tests that passed. Tests that failed
+/// keep their previous hash, so they count as changed until they pass.
+pub(crate) fn save(
+    path: &Path,
+    baseline: &BTreeMap<String, u64>,
+    outcomes: &[TestOutcome],
+) -> Result<(), String> {
+    let mut hashes = baseline.clone();
+
+    for outcome in outcomes {
+        if outcome.result.status == TestStatus::Passed
+            && let Some(hash) = outcome.test.source_hash
+        {
+            hashes.insert(outcome.test.id(), hash);
+        }
+    }
+
+    crate::write_file(path, serde_json::to_string_pretty(&hashes).unwrap())
+        .map_err(|e| format!("Could not write the test hashes to {}: {e}.", path.display()))
+}
@@ -0,0 +1,145 @@
+use std::collections::BTreeSet;
+
+use crate::{runner::matches_exactly, test::Test};
+
+/// Whether `name`, as given to `depends_on`, refers to `test`, by its name, its path or its ID. A
+/// test's name refers to all of its cases.
+pub(crate) fn refers_to(test: &Test, name: &str) -> bool {
+    test.id() == name || matches_exactly(test, name)
+}
+
+/// Returns the indices of the tests each of `tests` depends on. Dependencies on tests that aren't
+/// in `tests`, such as the ones filtered out, are left out.
+pub(crate) fn indices(tests: &[Test]) -> Vec<Vec<usize>> {
+    tests
+        .iter()
+        .map(|test| {
+            (0..tests.len())
+                .filter(|&i| test.depends_on.iter().any(|name| refers_to(&tests[i], name)))
+                .collect()
+        })
+        .collect()
+}
+
+/// Returns the indices of the tests along a cycle of dependencies in `tests`, with the first one
+/// repeated at the end, if there's any.
+pub(crate) fn find_cycle(tests: &[Test]) -> Option<Vec<usize>> {
+    /// Visits the dependencies of `index`, keeping the path from the visit's root in `path`.
+    fn visit(
+        index: usize,
+        dependencies: &[Vec<usize>],
+        done: &mut [bool],
+        path: &mut Vec<usize>,
+    ) -> Option<Vec<usize>> {
+        if let Some(start) = path.iter().position(|&i| i == index) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(index);
+            return Some(cycle);
+        }
+
+        if done[index] {
+            return None;
+        }
+
+        path.push(index);
+
+        for &dependency in &dependencies[index] {
+            if let Some(cycle) = visit(dependency, dependencies, done, path) {
+                return Some(cycle);
+            }
+        }
+
+        path.pop();
+        done[index] = true;
+        None
+    }
+
+    let dependencies = indices(tests);
+    let mut done = vec![false; tests.len()];
+
+    (0..tests.len()).find_map(|index| visit(index, &dependencies, &mut done, &mut Vec::new()))
+}
+
+/// Describes a cycle found by `find_cycle`.
+pub(crate) fn cycle_error(tests: &[Test], cycle: &[usize]) -> String {
+    let path: Vec<String> = cycle
+        .iter()
+        .map(|&i| format!("`{}`", tests[i].id()))
+        .collect();
+
+    format!(
+        "The dependencies of the tests form a cycle, so they can't run in order: {}.",
+        path.join(" -> ")
+    )
+}
+
+/// Reorders `tests` so that every test runs after the tests it depends on. Otherwise, the tests
+/// keep their order, so a test that depends on a later one is moved right after it. If the
+/// dependencies form a cycle, the tests can't be ordered, so they're returned as they are and the
+/// runner reports the cycle instead of running them.
+pub(crate) fn sort(tests: Vec<Test>) -> Vec<Test> {
+    if tests.iter().all(|test| test.depends_on.is_empty()) || find_cycle(&tests).is_some() {
+        return tests;
+    }
+
+    let dependencies = indices(&tests);
+    let mut remaining: Vec<usize> = dependencies.iter().map(Vec::len).collect();
+    let mut ready: BTreeSet<usize> = (0..tests.len()).filter(|&i| remaining[i] == 0).collect();
+    let mut order = Vec::with_capacity(tests.len());
+
+    while let Some(index) = ready.pop_first() {
+        order.push(index);
+
+        for (dependent, dependencies) in dependencies.iter().enumerate() {
+            if dependencies.contains(&index) {
+                remaining[dependent] -= 1;
+
+                if remaining[dependent] == 0 {
+                    ready.insert(dependent);
+                }
+            }
+        }
+    }
+
+    let mut tests: Vec<Option<Test>> = tests.into_iter().map(Some).collect();
+
+    order
+        .into_iter()
+        .map(|index| tests[index].take().unwrap())
+        .collect()
+}
+
+/// Returns the index of the group of tests connected by their dependencies that each of `tests`
+/// belongs to, directly or through other tests. Groups are numbered in the order in which their
+/// first test appears, and a test without dependencies nor dependents is a group of its own.
+pub(crate) fn components(tests: &[Test]) -> Vec<usize> {
+    let dependencies = indices(tests);
+    let mut component: Vec<Option<usize>> = vec![None; tests.len()];
+    let mut count = 0;
+
+    for start in 0..tests.len() {
+        if component[start].is_some() {
+            continue;
+        }
+
+        // The dependencies are followed both ways, from a test to the ones it depends on and to
+        // the ones depending on it.
+        let mut pending = vec![start];
+        component[start] = Some(count);
+
+        while let Some(index) = pending.pop() {
+            let dependents = (0..tests.len()).filter(|&other| dependencies[other].contains(&index));
+
+            for other in dependencies[index].iter().copied().chain(dependents) {
+                if component[other].is_none() {
+                    component[other] = Some(count);
+                    pending.push(other);
+                }
+            }
+        }
+
+        count += 1;
+    }
+
+    component.into_iter().map(Option::unwrap).collect()
+}
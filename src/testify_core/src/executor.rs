@@ -0,0 +1,481 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{self, AtomicBool, AtomicUsize},
+        mpsc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    artifacts, isolation,
+    observer::Observer,
+    runner::{self, ParallelBy},
+    runtime,
+    test::{Test, TestResult, TestStatus},
+};
+
+/// The result of a test case, how long its last attempt took, and how many attempts it took.
+pub type CaseResult = (TestResult, Duration, u32);
+
+/// Executes a function and returns the result together with the time the function took to execute.
+pub fn exec_with_timing<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let result = f();
+
+    (result, start.elapsed())
+}
+
+/// Runs the test cases the same way on every thread.
+pub struct CaseRunner {
+    /// The directory the cases' artifact directories are created in.
+    pub output_dir: PathBuf,
+    /// How many times a failing case is run again, unless the case sets its own `retries`.
+    pub retries: u32,
+    /// Run each case in its own process.
+    pub isolate: bool,
+    /// Run every async case on a runtime of its own.
+    pub fresh_runtime: bool,
+    /// How long a case can run before it times out, unless the case or one of its tags sets its
+    /// own.
+    pub timeout: Option<Duration>,
+    /// Pairs of `(tag, timeout)`, setting the timeout of the cases with the tag.
+    pub tag_timeouts: Vec<(String, Duration)>,
+    pub observers: Vec<Arc<dyn Observer>>,
+}
+
+impl CaseRunner {
+    /// Runs a test case with its artifact directory set, timing it. If it doesn't pass, it's run
+    /// again up to `retries` times, waiting for the case's `retry_backoff` in between.
+    fn run(&self, case: &Test) -> CaseResult {
+        let artifact_dir = artifacts::case_dir(&self.output_dir, case);
+        let retries = case.retries.unwrap_or(self.retries);
+
+        artifacts::with_artifact_dir(artifact_dir.clone(), || {
+            let mut attempts = 1;
+            let mut backoff = case.retry_backoff;
+
+            loop {
+                for observer in &self.observers {
+                    observer.on_test_started(case);
+                }
+
+                let (result, duration) = match self.timeout(case) {
+                    Some(timeout) => self.execute_with_timeout(case, &artifact_dir, timeout),
+                    None => self.execute(case, &artifact_dir),
+                };
+
+                if result.status == TestStatus::Passed || attempts > retries {
+                    return (result, duration, attempts);
+                }
+
+                // An async test waits on its runtime rather than blocking the thread, so that a
+                // single-threaded runtime keeps running the tasks the test left on it. The sleep is
+                // created inside the runtime, as its timer may need to be.
+                if let Some(delay) = backoff {
+                    if case.is_async {
+                        let runtime = runtime::async_runtime();
+                        runtime.block_on(async { runtime.sleep(delay).await });
+                    } else {
+                        thread::sleep(delay);
+                    }
+                    backoff = Some(delay.saturating_mul(2));
+                }
+
+                attempts += 1;
+            }
+        })
+    }
+
+    /// Runs a case like `run`, unless any of the cases it depends on, given by their indices in
+    /// `dependencies`, didn't pass or was skipped, in which case it's skipped and `None` is
+    /// returned. The indices of the cases that didn't pass are added to `failed`.
+    fn run_after(
+        &self,
+        index: usize,
+        case: &Test,
+        dependencies: &[usize],
+        failed: &mut HashSet<usize>,
+    ) -> Option<CaseResult> {
+        let result = if dependencies.iter().any(|i| failed.contains(i)) {
+            None
+        } else {
+            Some(self.run(case))
+        };
+
+        if !result
+            .as_ref()
+            .is_some_and(|(result, ..)| result.status == TestStatus::Passed)
+        {
+            failed.insert(index);
+        }
+
+        result
+    }
+
+    /// Returns how long a case can run, which is the first of its own `timeout`, the longest
+    /// timeout of its tags, and the default `timeout`.
+    fn timeout(&self, case: &Test) -> Option<Duration> {
+        case.timeout
+            .or_else(|| {
+                self.tag_timeouts
+                    .iter()
+                    .filter(|(tag, _)| case.tags.contains(tag))
+                    .map(|(_, timeout)| *timeout)
+                    .max()
+            })
+            .or(self.timeout)
+    }
+
+    /// Runs a case once on the current thread, timing it.
+    fn execute(&self, case: &Test, artifact_dir: &Path) -> (TestResult, Duration) {
+        if self.isolate {
+            exec_with_timing(|| isolation::run_isolated(case, artifact_dir))
+        } else {
+            runtime::with_fresh_runtimes(self.fresh_runtime, || exec_with_timing(&*case.function))
+        }
+    }
+
+    /// Runs a case once on a thread of its own, failing it if it doesn't finish within `timeout`.
+    /// Threads can't be stopped, so a case that times out is left running in the background,
+    /// unless it's isolated, in which case its process is killed.
+    fn execute_with_timeout(
+        &self,
+        case: &Test,
+        artifact_dir: &Path,
+        timeout: Duration,
+    ) -> (TestResult, Duration) {
+        if self.isolate {
+            return Self::execute_isolated_with_timeout(case, artifact_dir, timeout);
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let fresh_runtime = self.fresh_runtime;
+        let thread_case = case.clone();
+        let artifact_dir = artifact_dir.to_path_buf();
+
+        thread::spawn(move || {
+            let result = artifacts::with_artifact_dir(artifact_dir, || {
+                runtime::with_fresh_runtimes(fresh_runtime, || {
+                    exec_with_timing(&*thread_case.function)
+                })
+            });
+
+            // The runner stopped waiting if the case timed out.
+            let _ = sender.send(result);
+        });
+
+        receiver.recv_timeout(timeout).unwrap_or_else(|_| {
+            failed(
+                format!(
+                    "The test timed out after {timeout:?}, and was left running in the background."
+                ),
+                timeout,
+            )
+        })
+    }
+
+    /// Runs a case once in a process of its own, killing it if it doesn't finish within `timeout`.
+    fn execute_isolated_with_timeout(
+        case: &Test,
+        artifact_dir: &Path,
+        timeout: Duration,
+    ) -> (TestResult, Duration) {
+        let start = Instant::now();
+        let mut isolated = match isolation::spawn_isolated(case, artifact_dir) {
+            Ok(isolated) => isolated,
+            Err(message) => return failed(message, start.elapsed()),
+        };
+
+        loop {
+            match isolated.child.try_wait() {
+                Ok(Some(status)) => return (isolated.result(status), start.elapsed()),
+                Ok(None) if start.elapsed() < timeout => thread::sleep(ISOLATED_POLL_INTERVAL),
+                Ok(None) => {
+                    isolated.kill();
+
+                    return failed(
+                        format!(
+                            "The test timed out after {timeout:?}, and its process was killed."
+                        ),
+                        timeout,
+                    );
+                }
+                Err(e) => {
+                    let duration = start.elapsed();
+                    isolated.kill();
+
+                    return failed(
+                        format!("Could not wait for the test's process: {e}"),
+                        duration,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// How often the process of an isolated case with a timeout is checked for whether it ended.
+const ISOLATED_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// The failed result of a case that ran for `duration`, such as one that timed out.
+fn failed(message: String, duration: Duration) -> (TestResult, Duration) {
+    (
+        TestResult {
+            status: TestStatus::Failed,
+            message: Some(message),
+            backtrace: None,
+            value: None,
+            detail: None,
+            output: None,
+            bench: None,
+        },
+        duration,
+    )
+}
+
+/// Splits the indices of `cases` into the units the workers take, each of which runs on a single
+/// worker in order. Units are ordered by their first case, so the first cases still start first.
+/// Cases that depend on each other are always in the same unit, so that they run in order.
+fn partition(
+    cases: &[Test],
+    dependencies: &[Vec<usize>],
+    parallel_by: ParallelBy,
+) -> Vec<Vec<usize>> {
+    let units: Vec<Vec<usize>> = match parallel_by {
+        ParallelBy::Case => (0..cases.len()).map(|index| vec![index]).collect(),
+        ParallelBy::File => {
+            let mut units: Vec<Vec<usize>> = Vec::new();
+            let mut files: HashMap<&str, usize> = HashMap::new();
+
+            for (index, case) in cases.iter().enumerate() {
+                match files.get(case.file.as_str()) {
+                    Some(&unit) => units[unit].push(index),
+                    None => {
+                        files.insert(&case.file, units.len());
+                        units.push(vec![index]);
+                    }
+                }
+            }
+
+            units
+        }
+    };
+
+    if dependencies.iter().all(Vec::is_empty) {
+        return units;
+    }
+
+    // The units connected by a dependency are merged, pointing each unit to the one it was merged
+    // into.
+    let mut unit_of = vec![0; cases.len()];
+    let mut merged_into: Vec<usize> = (0..units.len()).collect();
+
+    for (unit, indices) in units.iter().enumerate() {
+        for &index in indices {
+            unit_of[index] = unit;
+        }
+    }
+
+    let root = |merged_into: &[usize], mut unit: usize| {
+        while merged_into[unit] != unit {
+            unit = merged_into[unit];
+        }
+
+        unit
+    };
+
+    for (index, dependencies) in dependencies.iter().enumerate() {
+        for &dependency in dependencies {
+            let (a, b) = (
+                root(&merged_into, unit_of[index]),
+                root(&merged_into, unit_of[dependency]),
+            );
+            merged_into[a.max(b)] = a.min(b);
+        }
+    }
+
+    let mut merged: Vec<Vec<usize>> = vec![Vec::new(); units.len()];
+
+    for (unit, indices) in units.into_iter().enumerate() {
+        merged[root(&merged_into, unit)].extend(indices);
+    }
+
+    let mut merged: Vec<Vec<usize>> = merged.into_iter().filter(|unit| !unit.is_empty()).collect();
+
+    for unit in &mut merged {
+        unit.sort_unstable();
+    }
+
+    merged.sort_unstable_by_key(|unit| unit[0]);
+    merged
+}
+
+/// Hands out the results of the test cases in the order in which they're rendered, either by
+/// running them in place or by waiting for a worker thread to finish them.
+pub enum Executor {
+    Serial {
+        runner: CaseRunner,
+        dependencies: Vec<Vec<usize>>,
+        failed: HashSet<usize>,
+    },
+    Parallel {
+        results: mpsc::Receiver<(usize, Option<CaseResult>)>,
+        pending: HashMap<usize, Option<CaseResult>>,
+        stop: Arc<AtomicBool>,
+        workers: Vec<JoinHandle<()>>,
+    },
+}
+
+impl Executor {
+    /// Creates an executor for `cases`. With more than one job, the cases start running right
+    /// away on `jobs` worker threads, in the order they're given. With `ParallelBy::File`, the
+    /// cases of each source file run one after the other on the same worker.
+    ///
+    /// `dependencies` holds the indices of the cases each case depends on, which must come before
+    /// it. A case is skipped if any of them doesn't pass.
+    ///
+    /// With `fail_fast`, the workers stop taking cases after the first failing one, since the
+    /// results after it won't be asked for.
+    pub fn new(
+        cases: Vec<Test>,
+        dependencies: Vec<Vec<usize>>,
+        jobs: usize,
+        runner: CaseRunner,
+        parallel_by: ParallelBy,
+        fail_fast: bool,
+    ) -> Self {
+        if !Self::is_parallel(cases.len(), jobs) {
+            return Executor::Serial {
+                runner,
+                dependencies,
+                failed: HashSet::new(),
+            };
+        }
+
+        let units = Arc::new(partition(&cases, &dependencies, parallel_by));
+        let dependencies = Arc::new(dependencies);
+        let runner = Arc::new(runner);
+        let cases = Arc::new(cases);
+        let next_unit = Arc::new(AtomicUsize::new(0));
+        let stop = Arc::new(AtomicBool::new(false));
+        // The lowest index of a case that failed, when running with `fail_fast`.
+        let first_failure = Arc::new(AtomicUsize::new(usize::MAX));
+        let (sender, results) = mpsc::channel();
+
+        let workers = (0..jobs.min(units.len()))
+            .map(|_| {
+                let units = units.clone();
+                let dependencies = dependencies.clone();
+                let cases = cases.clone();
+                let next_unit = next_unit.clone();
+                let stop = stop.clone();
+                let first_failure = first_failure.clone();
+                let sender = sender.clone();
+                let runner = runner.clone();
+
+                thread::spawn(move || {
+                    // A unit holds every case its cases depend on, so the failures of other units
+                    // don't matter.
+                    let mut failed = HashSet::new();
+
+                    while !stop.load(atomic::Ordering::Relaxed) {
+                        let unit = next_unit.fetch_add(1, atomic::Ordering::Relaxed);
+
+                        let Some(indices) = units.get(unit) else {
+                            break;
+                        };
+
+                        // Units are sorted by their first case, and the cases of a unit are
+                        // sorted, so none of the cases after a failure are needed from here on.
+                        for &index in indices {
+                            if stop.load(atomic::Ordering::Relaxed)
+                                || runner::interrupted()
+                                || index > first_failure.load(atomic::Ordering::Relaxed)
+                            {
+                                break;
+                            }
+
+                            let result = runner.run_after(
+                                index,
+                                &cases[index],
+                                &dependencies[index],
+                                &mut failed,
+                            );
+
+                            if fail_fast
+                                && result
+                                    .as_ref()
+                                    .is_some_and(|(result, ..)| result.status != TestStatus::Passed)
+                            {
+                                first_failure.fetch_min(index, atomic::Ordering::Relaxed);
+                            }
+
+                            if sender.send((index, result)).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        Executor::Parallel {
+            results,
+            pending: HashMap::new(),
+            stop,
+            workers,
+        }
+    }
+
+    /// Whether `cases` test cases would run on worker threads with `jobs` jobs.
+    pub fn is_parallel(cases: usize, jobs: usize) -> bool {
+        jobs > 1 && cases > 1
+    }
+
+    /// Returns the result of the `index`-th case, blocking until it's available. It's `None` if the
+    /// case was skipped, because a case it depends on didn't pass.
+    pub fn result(&mut self, index: usize, case: &Test) -> Option<CaseResult> {
+        match self {
+            Executor::Serial {
+                runner,
+                dependencies,
+                failed,
+            } => runner.run_after(index, case, &dependencies[index], failed),
+            Executor::Parallel {
+                results, pending, ..
+            } => loop {
+                if let Some(result) = pending.remove(&index) {
+                    return result;
+                }
+
+                let (i, result) = results
+                    .recv()
+                    .expect("A test worker thread stopped unexpectedly");
+
+                pending.insert(i, result);
+            },
+        }
+    }
+
+    /// Stops handing out new cases to the workers and waits for the running ones to finish.
+    pub fn finish(self) {
+        if let Executor::Parallel {
+            results,
+            stop,
+            workers,
+            ..
+        } = self
+        {
+            stop.store(true, atomic::Ordering::Relaxed);
+            drop(results);
+
+            for worker in workers {
+                let _ = worker.join();
+            }
+        }
+    }
+}
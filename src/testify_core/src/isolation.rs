@@ -0,0 +1,168 @@
+use std::{
+    backtrace::Backtrace,
+    panic,
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    TESTS, artifacts,
+    runner::TestifyConfig,
+    runtime,
+    test::{self, Test, TestResult, TestStatus},
+};
+
+/// The ID of the test an isolated process has to run.
+const ISOLATED_TEST_ENV_VAR_NAME: &str = "DO_NOT_MANUALLY_SET_TESTIFY_ISOLATED_TEST";
+/// The file an isolated process writes the test's result to.
+const ISOLATED_RESULT_ENV_VAR_NAME: &str = "DO_NOT_MANUALLY_SET_TESTIFY_ISOLATED_RESULT";
+/// The artifact directory of the test an isolated process runs.
+const ISOLATED_ARTIFACT_DIR_ENV_VAR_NAME: &str = "DO_NOT_MANUALLY_SET_TESTIFY_ISOLATED_ARTIFACT_DIR";
+
+/// The exit code of an isolated process that couldn't find the test it had to run.
+const TEST_NOT_FOUND_EXIT_CODE: i32 = 3;
+
+/// Used to give every isolated process its own result file.
+static NEXT_RESULT_FILE: AtomicUsize = AtomicUsize::new(0);
+
+/// A test case running in a process of its own, started by `spawn_isolated`.
+pub(crate) struct IsolatedCase {
+    /// The process running the case, which can be killed if it takes too long.
+    pub child: Child,
+    result_file: PathBuf,
+}
+
+impl IsolatedCase {
+    /// Waits for the process to end and returns the result it wrote.
+    pub fn wait(mut self) -> TestResult {
+        match self.child.wait() {
+            Ok(status) => self.result(status),
+            Err(e) => failure(format!("Could not wait for the test's process: {e}")),
+        }
+    }
+
+    /// Returns the result written by the process, which ended with `status`. It's reported as a
+    /// failure if the process ended without writing one.
+    pub fn result(self, status: ExitStatus) -> TestResult {
+        let result = std::fs::read_to_string(&self.result_file)
+            .ok()
+            .and_then(|result| serde_json::from_str(&result).ok());
+        let _ = std::fs::remove_file(&self.result_file);
+
+        if status.code() == Some(TEST_NOT_FOUND_EXIT_CODE) {
+            return failure(
+                "The test was not found in its process. Tests registered at runtime can't be isolated."
+                    .to_string(),
+            );
+        }
+
+        result.unwrap_or_else(|| failure(format!("The test's process crashed ({status}).")))
+    }
+
+    /// Kills the process, and removes the result it may have written.
+    pub fn kill(mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_file(&self.result_file);
+    }
+}
+
+/// A failed result with `message`, for the cases that couldn't run in their process.
+fn failure(message: String) -> TestResult {
+    TestResult {
+        status: TestStatus::Failed,
+        message: Some(message),
+        backtrace: None,
+        value: None,
+        output: None,
+        detail: None,
+        bench: None,
+    }
+}
+
+/// Starts a new process running the current binary to run a test case, so that an abort or a
+/// crash only ends that process. If it can't be started, the reason is returned instead.
+pub(crate) fn spawn_isolated(case: &Test, artifact_dir: &Path) -> Result<IsolatedCase, String> {
+    let executable = std::env::current_exe()
+        .map_err(|e| format!("Could not find the test binary to isolate the test: {e}"))?;
+
+    let result_file = std::env::temp_dir().join(format!(
+        "testify-result-{}-{}.json",
+        std::process::id(),
+        NEXT_RESULT_FILE.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let child = Command::new(executable)
+        .args(std::env::args_os().skip(1))
+        .env(ISOLATED_TEST_ENV_VAR_NAME, case.id())
+        .env(ISOLATED_RESULT_ENV_VAR_NAME, &result_file)
+        .env(ISOLATED_ARTIFACT_DIR_ENV_VAR_NAME, artifact_dir)
+        .spawn()
+        .map_err(|e| format!("Could not start the test's process: {e}"))?;
+
+    Ok(IsolatedCase { child, result_file })
+}
+
+/// Runs a test case in a process of its own, as started by `spawn_isolated`, and returns its
+/// result.
+pub(crate) fn run_isolated(case: &Test, artifact_dir: &Path) -> TestResult {
+    spawn_isolated(case, artifact_dir).map_or_else(failure, IsolatedCase::wait)
+}
+
+/// If the current process was started by `run_isolated`, runs the test it was started for, writes
+/// its result and exits.
+pub(crate) fn run_if_isolated(config: &TestifyConfig) {
+    let (Ok(id), Some(result_file), Some(artifact_dir)) = (
+        std::env::var(ISOLATED_TEST_ENV_VAR_NAME),
+        std::env::var_os(ISOLATED_RESULT_ENV_VAR_NAME).map(PathBuf::from),
+        std::env::var_os(ISOLATED_ARTIFACT_DIR_ENV_VAR_NAME).map(PathBuf::from),
+    ) else {
+        return;
+    };
+
+    let Some(test) = TESTS.lock().unwrap().iter().find(|test| test.id() == id).cloned() else {
+        std::process::exit(TEST_NOT_FOUND_EXIT_CODE);
+    };
+
+    let capture_backtrace = config.backtrace;
+    let hook_result_file = result_file.clone();
+
+    // With `panic = "abort"`, the process aborts right after the hook, so the panic is reported
+    // from here, without a detail as a panicking test has none. Otherwise it unwinds and
+    // `run_test_fn` reports it as usual, and its whole result is sent back, detail included.
+    panic::set_hook(Box::new(move |info| {
+        if capture_backtrace {
+            test::capture_panic_backtrace();
+        }
+
+        crate::call_chained_panic_hooks(info);
+
+        if cfg!(panic = "abort") {
+            write_result(
+                &hook_result_file,
+                &TestResult {
+                    status: TestStatus::Panicked,
+                    message: test::panic_message(info.payload()),
+                    backtrace: capture_backtrace.then(|| Backtrace::force_capture().to_string()),
+                    value: None,
+                    output: None,
+                    detail: None,
+                    bench: None,
+                },
+            );
+        }
+    }));
+
+    let result = artifacts::with_artifact_dir(artifact_dir, || {
+        runtime::with_fresh_runtimes(config.fresh_runtime, || (test.function)())
+    });
+    write_result(&result_file, &result);
+
+    std::process::exit(0);
+}
+
+fn write_result(path: &Path, result: &TestResult) {
+    let result = serde_json::to_string(result).expect("Could not serialize the test's result.");
+    std::fs::write(path, result).expect("Could not write the test's result.");
+}
@@ -0,0 +1,95 @@
+use std::{collections::BTreeMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    runner::TestOutcome,
+    test::{Test, TestStatus},
+};
+
+/// The file where the runner of `cargo testify` stores the status of every test after each run.
+pub const DEFAULT_LAST_RUN_FILE: &str = ".testify/last-run.json";
+
+/// The final status of a test in a run, as stored in the last run file.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum LastStatus {
+    /// The test passed on its first attempt.
+    Passed,
+    /// The test failed, even if it was retried.
+    Failed,
+    /// The test hasn't run since the file was created, because it was filtered out, ignored, or the
+    /// run stopped early.
+    Skipped,
+    /// The test passed, but only after being retried.
+    Flaky,
+}
+
+impl LastStatus {
+    /// The status as it's written in the last run file and passed to `--rerun-status`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Passed => "passed",
+            Self::Failed => "failed",
+            Self::Skipped => "skipped",
+            Self::Flaky => "flaky",
+        }
+    }
+}
+
+/// Updates the statuses stored in `path` with the final status of the tests that ran, written as a
+/// JSON object mapping their IDs to their statuses. The tests that didn't run keep the status they
+/// had, or are stored as skipped if they had none, and the ones that aren't in `tests` anymore are
+/// removed. If a test ran several times, as with `--repeat-until-failure`, its last run is the one
+/// stored.
+pub(crate) fn save(path: &Path, tests: &[Test], outcomes: &[TestOutcome]) -> Result<(), String> {
+    // A missing or unreadable file is replaced, like on the first run.
+    let previous = load(path).unwrap_or_default();
+    let mut statuses: BTreeMap<String, LastStatus> = tests
+        .iter()
+        .map(|test| {
+            let id = test.id();
+            let status = previous.get(&id).copied().unwrap_or(LastStatus::Skipped);
+
+            (id, status)
+        })
+        .collect();
+
+    for outcome in outcomes {
+        // Tests skipped because a test they depend on didn't pass didn't run either.
+        if outcome.result.status == TestStatus::Skipped {
+            continue;
+        }
+
+        let status = if outcome.result.status != TestStatus::Passed {
+            LastStatus::Failed
+        } else if outcome.is_flaky() {
+            LastStatus::Flaky
+        } else {
+            LastStatus::Passed
+        };
+
+        statuses.insert(outcome.test.id(), status);
+    }
+
+    crate::write_file(path, serde_json::to_string_pretty(&statuses).unwrap())
+        .map_err(|e| format!("Could not write the last run to {}: {e}.", path.display()))
+}
+
+/// Reads the statuses written by `save`.
+pub fn load(path: &Path) -> Result<BTreeMap<String, LastStatus>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Could not read the last run {}: {e}.", path.display()))?;
+
+    serde_json::from_str(&contents)
+        .map_err(|e| format!("Could not parse the last run {}: {e}.", path.display()))
+}
+
+/// Returns the IDs of the tests that had `status` in the run stored in `path`.
+pub fn ids_with_status(path: &Path, status: LastStatus) -> Result<Vec<String>, String> {
+    Ok(load(path)?
+        .into_iter()
+        .filter(|(_, last)| *last == status)
+        .map(|(id, _)| id)
+        .collect())
+}
@@ -1,22 +1,224 @@
-use std::sync::Mutex;
+use std::{
+    panic::PanicHookInfo,
+    path::Path,
+    sync::{Arc, Mutex},
+};
 
+pub mod artifacts;
+pub mod changes;
+mod dependencies;
+mod executor;
+mod isolation;
+pub mod last_run;
+pub mod macros;
+pub mod metrics;
+pub mod observer;
+pub mod reports;
 pub mod runner;
+pub mod runtime;
 pub mod test;
+pub mod theme;
+pub mod timings;
+pub mod wait;
 
-pub use runner::run;
+pub use artifacts::artifact_dir;
+pub use runner::{
+    OutputBuffer, PlannedCase, PlannedGroup, PlannedTest, Runner, TagCounts, TestReport,
+    last_report, plan, run,
+};
+pub use runtime::{
+    AsyncRuntime, async_runtime, set_async_runtime, shutdown_services, spawn_service,
+};
 pub use test::TestTermination;
+pub use wait::{
+    WaitTimeout, wait_until, wait_until_async, wait_until_every, wait_until_every_async,
+};
 
 pub static TESTS: Mutex<Vec<test::Test>> = Mutex::new(Vec::new());
-pub static SETUP: Mutex<Option<fn() -> ()>> = Mutex::new(None);
-pub static CLEANUP: Mutex<Option<fn() -> ()>> = Mutex::new(None);
+/// Setups that run before the tests, sorted by their order.
+pub static SETUP: Mutex<Vec<OrderedFn>> = Mutex::new(Vec::new());
+/// Cleanups that run after the tests, sorted by their order.
+pub static CLEANUP: Mutex<Vec<OrderedFn>> = Mutex::new(Vec::new());
+/// Setups that run before the first test with any of their tags.
+pub static TAG_SETUPS: Mutex<Vec<TagScopedFn>> = Mutex::new(Vec::new());
+/// Cleanups that run after the last test with any of their tags.
+pub static TAG_CLEANUPS: Mutex<Vec<TagScopedFn>> = Mutex::new(Vec::new());
+/// Setups that run before the first test of their suite.
+pub static SUITE_SETUPS: Mutex<Vec<SuiteScopedFn>> = Mutex::new(Vec::new());
+/// Cleanups that run after the last test of their suite.
+pub static SUITE_CLEANUPS: Mutex<Vec<SuiteScopedFn>> = Mutex::new(Vec::new());
+pub static KNOWN_TAGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+/// The hooks added with `chain_panic_hook`. They're kept in `Arc`s so that they can be called
+/// without holding the lock, since a hook may panic or add another hook.
+pub static PANIC_HOOKS: Mutex<Vec<Arc<PanicHookFn>>> = Mutex::new(Vec::new());
 
+/// A setup or cleanup function, with its order.
+pub type OrderedFn = (i32, fn());
+
+/// A setup or cleanup function, with its order and the `scope` of the tests it's scoped to.
+pub type ScopedFn<S> = (i32, S, fn());
+
+/// A setup or cleanup function, with its order and the tags of the tests it's scoped to.
+pub type TagScopedFn = ScopedFn<Vec<String>>;
+
+/// A setup or cleanup function, with its order and the suite it's scoped to.
+pub type SuiteScopedFn = ScopedFn<String>;
+
+pub type PanicHookFn = dyn Fn(&PanicHookInfo) + Send + Sync;
+
+pub type PanicHook = Box<PanicHookFn>;
+
+/// A hook added with `chain_panic_hook`, which can be removed with `unchain_panic_hook`.
+#[derive(Clone)]
+pub struct ChainedPanicHook(Arc<PanicHookFn>);
+
+/// Adds a function to be called whenever a panic happens while the tests run, in addition to
+/// testify's own panic hook. Testify replaces the panic hook when the tests start running, so
+/// hooks set with `std::panic::set_hook` would otherwise never be called.
+///
+/// Hooks are called in the order they were added. They can be added from `main` before `run()`, or
+/// from a `#[testify::setup]` function.
+///
+/// ```ignore
+/// #[testify::setup]
+/// fn setup() {
+///     testify::chain_panic_hook(Box::new(|info| {
+///         log::error!("A test panicked: {info}");
+///     }));
+/// }
+/// ```
+pub fn chain_panic_hook(hook: PanicHook) -> ChainedPanicHook {
+    let hook: Arc<PanicHookFn> = Arc::from(hook);
+    PANIC_HOOKS.lock().unwrap().push(hook.clone());
+
+    ChainedPanicHook(hook)
+}
+
+/// Calls the hooks added with `chain_panic_hook`, from testify's own panic hook.
+pub(crate) fn call_chained_panic_hooks(info: &PanicHookInfo) {
+    // Cloned so that the lock isn't held while the hooks run.
+    let hooks = PANIC_HOOKS.lock().unwrap().clone();
+
+    for hook in hooks {
+        hook(info);
+    }
+}
+
+/// Removes a hook added with `chain_panic_hook`, so that it isn't called for the panics that
+/// happen afterwards. Returns whether the hook was still chained.
+pub fn unchain_panic_hook(hook: &ChainedPanicHook) -> bool {
+    let mut hooks = PANIC_HOOKS.lock().unwrap();
+    let count = hooks.len();
+    hooks.retain(|chained| !Arc::ptr_eq(chained, &hook.0));
+
+    hooks.len() != count
+}
+
+/// The tokio runtime async tests, setups and cleanups run on by default. Its number of worker
+/// threads is taken from the `TESTIFY_RT_THREADS` environment variable when it's first accessed,
+/// defaulting to the number of CPUs if it's unset or invalid.
 #[cfg(feature = "async-tokio")]
-pub static ASYNC_RT: once_cell::sync::Lazy<tokio::runtime::Runtime> = once_cell::sync::Lazy::new(|| {
-    tokio::runtime::Builder::new_multi_thread()
+pub static ASYNC_RT: once_cell::sync::Lazy<tokio::runtime::Runtime> =
+    once_cell::sync::Lazy::new(new_tokio_runtime);
+
+/// Builds a tokio runtime the way `ASYNC_RT` is built, which is also used for the tests that run on
+/// a fresh runtime.
+#[cfg(feature = "async-tokio")]
+pub(crate) fn new_tokio_runtime() -> tokio::runtime::Runtime {
+    let mut builder = tokio::runtime::Builder::new_multi_thread();
+
+    // An invalid value is warned about by the runner, and ignored here.
+    if let Some(threads) = rt_threads().and_then(Result::ok) {
+        builder.worker_threads(threads.get());
+    }
+
+    builder
         .enable_all()
         .build()
         .expect("Could not initialize the tokio runtime")
-});
+}
+
+/// Returns the number of worker threads set in `TESTIFY_RT_THREADS`, if it's set, or the value it's
+/// set to if it isn't a positive integer.
+pub(crate) fn rt_threads() -> Option<Result<std::num::NonZeroUsize, String>> {
+    let threads = std::env::var(RT_THREADS_ENV_VAR_NAME).ok()?;
+
+    Some(threads.parse().map_err(|_| threads))
+}
+
+/// Writes `contents` to `path`, creating the directories it's in first if they don't exist.
+pub(crate) fn write_file(path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    // A relative path to a file in the working directory has an empty parent.
+    let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty());
+
+    if let Some(parent) = parent {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    std::fs::write(path, contents)
+}
+
+/// Registers a test at runtime, from a name and a closure returning a `TestTermination`. Unlike
+/// `#[testify::test]`, it can be used anywhere, such as in a loop to generate a test per file.
+///
+/// Tests must be registered before they start running, so call it from `main` before `run()` or
+/// from a `#[testify::setup]` function. Every call site is recorded before `main`, so that the
+/// setup still runs when no tests are registered yet.
+///
+/// ```ignore
+/// #[testify::setup]
+/// fn setup() {
+///     for path in std::fs::read_dir("fixtures").unwrap() {
+///         let path = path.unwrap().path();
+///
+///         testify::register_test!(format!("parse {}", path.display()), move || {
+///             parse(&std::fs::read_to_string(&path).unwrap())
+///         });
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! register_test {
+    ($name:expr, $function:expr $(,)?) => {{
+        #[doc(hidden)]
+        #[$crate::ctor::ctor(crate_path = $crate::ctor)]
+        fn __testify_mark_runtime_registration() {
+            $crate::test::mark_runtime_registration();
+        }
+
+        $crate::test::register_test($name, module_path!(), file!(), line!(), $function)
+    }};
+}
+
+#[doc(hidden)]
+pub use ctor;
 
 pub const TEST_RUNNER_TOGGLE_ENV_VAR_NAME: &str = "DO_NOT_MANUALLY_SET_TESTIFY_ARE_TESTS_BEING_RUN";
 pub const TEST_RUNNER_CONFIG: &str = "DO_NOT_MANUALLY_SET_TESTIFY_CONFIG";
+/// A file `run()` creates when it starts, so that `cargo testify` can tell whether the tests ran.
+pub const RUN_MARKER_ENV_VAR_NAME: &str = "DO_NOT_MANUALLY_SET_TESTIFY_RUN_MARKER";
+pub const RT_THREADS_ENV_VAR_NAME: &str = "TESTIFY_RT_THREADS";
+
+/// Returns whether the binary was started by `cargo testify` to run the tests, which is what the
+/// `#[testify::main]` function checks to decide between running them and running its own body.
+/// Code can use it to behave differently under test, such as using an in-memory backend.
+///
+/// It reflects how the process was started, so it also returns `true` outside of the tests, such
+/// as in a setup, and `false` when the tests are run with `Runner` from a binary that wasn't started
+/// by `cargo testify`.
+///
+/// Both `TEST_RUNNER_TOGGLE_ENV_VAR_NAME` and `TEST_RUNNER_CONFIG` have to be set, as `cargo
+/// testify` does, so that the toggle alone, such as one left exported in a shell, can't make a
+/// binary run its tests instead of its `main`.
+///
+/// ```ignore
+/// let storage: Box<dyn Storage> = if testify::is_test_run() {
+///     Box::new(MemoryStorage::default())
+/// } else {
+///     Box::new(DiskStorage::open("data")?)
+/// };
+/// ```
+pub fn is_test_run() -> bool {
+    std::env::var_os(TEST_RUNNER_TOGGLE_ENV_VAR_NAME).is_some()
+        && std::env::var_os(TEST_RUNNER_CONFIG).is_some()
+}
@@ -0,0 +1,142 @@
+//! Assertion macros for tests.
+
+/// Asserts that an expression matches a pattern, optionally with a guard. On mismatch, it panics
+/// with the expression, the expected pattern and the `Debug` representation of the actual value,
+/// which testify shows under the failing test.
+///
+/// ```ignore
+/// testify::assert_matches!(parse("42"), Ok(Token::Number(n)) if n > 0);
+/// testify::assert_matches!(user.role, Role::Admin, "{} should be an admin", user.name);
+/// ```
+#[macro_export]
+macro_rules! assert_matches {
+    ($expression:expr, $pattern:pat $(if $guard:expr)? $(,)?) => {
+        match $expression {
+            $pattern $(if $guard)? => {}
+            ref value => panic!(
+                "assertion failed: `{}` does not match `{}`\nvalue: {:?}",
+                stringify!($expression),
+                stringify!($pattern $(if $guard)?),
+                value
+            ),
+        }
+    };
+    ($expression:expr, $pattern:pat $(if $guard:expr)?, $($message:tt)+) => {
+        match $expression {
+            $pattern $(if $guard)? => {}
+            ref value => panic!(
+                "assertion failed: `{}` does not match `{}`: {}\nvalue: {:?}",
+                stringify!($expression),
+                stringify!($pattern $(if $guard)?),
+                format_args!($($message)+),
+                value
+            ),
+        }
+    };
+}
+
+/// Like `std::println!`, but writes to the output of the current test instead of stdout. The
+/// output is only shown if the test fails, under its result, so passing tests don't clutter the
+/// run. Outside of a test, such as in a setup, it prints to stdout.
+///
+/// ```ignore
+/// testify::println!("Sending {} requests", requests.len());
+/// ```
+#[macro_export]
+macro_rules! println {
+    () => {
+        $crate::test::write_output(format_args!("\n"), false)
+    };
+    ($($arg:tt)*) => {
+        $crate::test::write_output(format_args!("{}\n", format_args!($($arg)*)), false)
+    };
+}
+
+/// Like `std::eprintln!`, but writes to the output of the current test instead of stderr, the same
+/// way as `testify::println!`. Outside of a test, it prints to stderr.
+#[macro_export]
+macro_rules! eprintln {
+    () => {
+        $crate::test::write_output(format_args!("\n"), true)
+    };
+    ($($arg:tt)*) => {
+        $crate::test::write_output(format_args!("{}\n", format_args!($($arg)*)), true)
+    };
+}
+
+/// Stops the current test right away and fails it, with a message formatted like `format!` if one
+/// is given. It's shown under the test like the message of a returned error.
+///
+/// ```ignore
+/// if response.status() != 200 {
+///     testify::fail!("Unexpected status {}", response.status());
+/// }
+/// ```
+#[macro_export]
+macro_rules! fail {
+    () => {
+        $crate::test::exit_early($crate::test::EarlyExit::Fail(None))
+    };
+    ($($arg:tt)+) => {
+        $crate::test::exit_early($crate::test::EarlyExit::Fail(Some(format!($($arg)+))))
+    };
+}
+
+/// Stops the current test right away and passes it, skipping the rest of its body.
+///
+/// ```ignore
+/// if !cfg!(target_os = "linux") {
+///     testify::pass!();
+/// }
+/// ```
+#[macro_export]
+macro_rules! pass {
+    () => {
+        $crate::test::exit_early($crate::test::EarlyExit::Pass)
+    };
+}
+
+/// Asserts that a future completes within a duration, awaiting it and evaluating to its output.
+/// If it doesn't, it panics with the future's expression and the duration, failing the test. Only
+/// the future is timed, unlike the test's `timeout`, which covers the whole test.
+///
+/// It must be used inside an async test running on tokio, such as on `ASYNC_RT` by default, since
+/// it relies on tokio's timers. The future is dropped when it times out.
+///
+/// ```ignore
+/// let user = testify::assert_completes_within!(Duration::from_millis(100), cache.get("user"));
+/// testify::assert_completes_within!(Duration::from_secs(1), server.shutdown(), "Shutdown hung");
+/// ```
+#[cfg(feature = "async-tokio")]
+#[macro_export]
+macro_rules! assert_completes_within {
+    ($duration:expr, $future:expr $(,)?) => {{
+        let duration: ::std::time::Duration = $duration;
+
+        match $crate::runtime::complete_within(duration, $future).await {
+            Some(output) => output,
+            None => panic!(
+                "assertion failed: `{}` didn't complete within {:?}",
+                stringify!($future),
+                duration
+            ),
+        }
+    }};
+    ($duration:expr, $future:expr, $($message:tt)+) => {{
+        let duration: ::std::time::Duration = $duration;
+
+        match $crate::runtime::complete_within(duration, $future).await {
+            Some(output) => output,
+            None => panic!(
+                "assertion failed: `{}` didn't complete within {:?}: {}",
+                stringify!($future),
+                duration,
+                format_args!($($message)+)
+            ),
+        }
+    }};
+}
+
+pub use crate::{assert_matches, eprintln, fail, pass, println};
+#[cfg(feature = "async-tokio")]
+pub use crate::assert_completes_within;
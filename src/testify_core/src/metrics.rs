@@ -0,0 +1,93 @@
+use std::{fmt::Write, path::Path, time::Duration};
+
+use crate::runner::TestReport;
+
+/// Escapes a label value as required by the Prometheus text format, where backslashes, double
+/// quotes and line feeds must be escaped.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Appends a metric's `HELP` and `TYPE` lines, followed by one sample for each of `samples`, given
+/// as `(labels, value)`. The labels are written as they are, so they must already be escaped.
+fn write_gauge(out: &mut String, name: &str, help: &str, samples: &[(String, String)]) {
+    writeln!(out, "# HELP {name} {help}").unwrap();
+    writeln!(out, "# TYPE {name} gauge").unwrap();
+
+    for (labels, value) in samples {
+        writeln!(out, "{name}{labels} {value}").unwrap();
+    }
+}
+
+/// Formats the results of a run as metrics in the Prometheus text format. Every metric is a gauge,
+/// since its value belongs to a single run:
+///
+/// - `testify_tests_total`: The number of tests that ran, including the benchmarks.
+/// - `testify_tests_failed`: The number of tests that failed.
+/// - `testify_tests_skipped`: The number of tests that didn't run, including the ignored ones.
+/// - `testify_duration_seconds`: The time spent running the tests, added up.
+/// - `testify_tag_tests_passed` and `testify_tag_tests_failed`: The number of tests that passed
+///   and failed with each tag, labeled with `tag`.
+pub fn format(report: &TestReport) -> String {
+    let mut out = String::new();
+    let duration: Duration = report.outcomes.iter().map(|outcome| outcome.duration).sum();
+    let total = |value: usize| vec![(String::new(), value.to_string())];
+
+    write_gauge(
+        &mut out,
+        "testify_tests_total",
+        "The number of tests that ran.",
+        &total(report.outcomes.len() - report.skipped_dependents()),
+    );
+    write_gauge(
+        &mut out,
+        "testify_tests_failed",
+        "The number of tests that failed.",
+        &total(report.failures()),
+    );
+    write_gauge(
+        &mut out,
+        "testify_tests_skipped",
+        "The number of tests that didn't run.",
+        &total(report.skipped + report.ignored + report.not_run),
+    );
+    write_gauge(
+        &mut out,
+        "testify_duration_seconds",
+        "The time spent running the tests, added up.",
+        &[(String::new(), duration.as_secs_f64().to_string())],
+    );
+
+    let tags = report.tag_summary();
+    let by_tag = |count: fn(&crate::TagCounts) -> usize| {
+        tags.iter()
+            .map(|(tag, counts)| {
+                (format!("{{tag=\"{}\"}}", escape_label(tag)), count(counts).to_string())
+            })
+            .collect::<Vec<_>>()
+    };
+
+    write_gauge(
+        &mut out,
+        "testify_tag_tests_passed",
+        "The number of tests that passed with each tag.",
+        &by_tag(|counts| counts.passed),
+    );
+    write_gauge(
+        &mut out,
+        "testify_tag_tests_failed",
+        "The number of tests that failed with each tag.",
+        &by_tag(|counts| counts.failed),
+    );
+
+    out
+}
+
+/// Writes the metrics given by `format` to `path`.
+pub(crate) fn save(path: &Path, report: &TestReport) -> Result<(), String> {
+    crate::write_file(path, format(report))
+        .map_err(|e| format!("Could not write the metrics to {}: {e}.", path.display()))
+}
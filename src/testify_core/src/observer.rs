@@ -0,0 +1,28 @@
+use crate::{
+    runner::{TestOutcome, TestReport},
+    test::Test,
+};
+
+/// Receives events while the tests run, such as to show them in a live UI. All methods do nothing
+/// by default, so implementors only need to override the events they care about.
+///
+/// Observers are registered with `Runner::with_observer`. When tests run in parallel, events can
+/// be sent from several threads at the same time.
+pub trait Observer: Send + Sync {
+    /// Called right before a test case starts running, and again before every retry. It's useful
+    /// to find out which test is hanging.
+    fn on_test_started(&self, test: &Test) {
+        let _ = test;
+    }
+
+    /// Called when a test case's result is shown, in the order of the output.
+    fn on_test_finished(&self, outcome: &TestOutcome) {
+        let _ = outcome;
+    }
+
+    /// Called once the run finished, after the summary was shown, with the results of the whole
+    /// run. It's where reports covering every test are written.
+    fn on_run_finished(&self, report: &TestReport) {
+        let _ = report;
+    }
+}
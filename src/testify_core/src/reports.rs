@@ -0,0 +1,408 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    path::PathBuf,
+    sync::Mutex,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    observer::Observer,
+    runner::{self, OutputBuffer, TagCounts, TestOutcome, TestReport},
+    test::TestStatus,
+};
+
+/// The formats of the reports that can be written alongside the console output.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportKind {
+    /// The human-readable output, as shown in the console but without colors.
+    Human,
+    /// JUnit XML, as read by most CI services.
+    Junit,
+    /// A JSON object with the results of the tests, as given by `json`.
+    Json,
+    /// The Test Anything Protocol, version 13.
+    Tap,
+}
+
+/// The name of a test in the reports, with its case if it has one.
+fn display_name(outcome: &TestOutcome) -> String {
+    match &outcome.test.case {
+        Some(case) => format!("{} ({case})", outcome.test.name),
+        None => outcome.test.name.clone(),
+    }
+}
+
+/// Whether XML 1.0 allows `c` at all, even escaped.
+fn is_xml_char(c: &char) -> bool {
+    matches!(
+        c,
+        '\t' | '\n' | '\r' | ' '..='\u{d7ff}' | '\u{e000}'..='\u{fffd}' | '\u{10000}'..
+    )
+}
+
+/// Escapes text to be used in XML, both in attributes and as content. The characters XML 1.0
+/// doesn't allow at all, such as the escape character of colored output, are removed.
+fn escape_xml(text: &str) -> String {
+    text.chars()
+        .filter(is_xml_char)
+        .collect::<String>()
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Writes a JUnit `property` element, indented by `indent` spaces.
+fn write_property(out: &mut String, indent: usize, name: &str, value: &str) {
+    writeln!(
+        out,
+        "{:indent$}<property name=\"{}\" value=\"{}\" />",
+        "",
+        escape_xml(name),
+        escape_xml(value)
+    )
+    .unwrap();
+}
+
+/// Formats the counts of a tag or a file, as given in the reports.
+fn format_counts(counts: &TagCounts) -> String {
+    format!("{} passed, {} failed", counts.passed, counts.failed)
+}
+
+/// Formats the results of a run as JUnit XML, with a `testsuite` for each suite. The tests without
+/// a suite are in one named `default`. Each suite has a `tag:<tag>` and a `file:<file>` property
+/// with the counts of its tests with each tag and in each file. Each test has a `path` property, a
+/// `description` property if it has one, a `tag` property for each of its tags, and a `slow`
+/// property if it's marked as slow. The tests that didn't run are listed too, with a `skipped`
+/// element giving the reason why.
+pub fn junit(report: &TestReport) -> String {
+    let mut suites: BTreeMap<&str, Vec<&TestOutcome>> = BTreeMap::new();
+    let outcomes: Vec<&TestOutcome> = report.outcomes.iter().chain(&report.unrun).collect();
+
+    for &outcome in &outcomes {
+        suites
+            .entry(outcome.test.suite.as_deref().unwrap_or("default"))
+            .or_default()
+            .push(outcome);
+    }
+
+    let time = |outcomes: &[&TestOutcome]| {
+        outcomes
+            .iter()
+            .map(|outcome| outcome.duration)
+            .sum::<Duration>()
+            .as_secs_f64()
+    };
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    writeln!(
+        out,
+        "<testsuites name=\"testify\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{}\">",
+        outcomes.len(),
+        report.failures(),
+        report.ignored + report.not_run,
+        time(&outcomes)
+    )
+    .unwrap();
+
+    for (suite, outcomes) in &suites {
+        let failures = outcomes
+            .iter()
+            .filter(|outcome| outcome.result.status.is_failure())
+            .count();
+        let skipped = outcomes
+            .iter()
+            .filter(|outcome| outcome.result.status == TestStatus::Skipped)
+            .count();
+
+        writeln!(
+            out,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{failures}\" skipped=\"{skipped}\" \
+             time=\"{}\">",
+            escape_xml(suite),
+            outcomes.len(),
+            time(outcomes)
+        )
+        .unwrap();
+
+        let outcomes = outcomes.iter().copied();
+        let tags = runner::count_by(outcomes.clone(), |test| test.tags.clone());
+        let files = runner::count_by(outcomes.clone(), |test| vec![test.file.clone()]);
+
+        out.push_str("    <properties>\n");
+
+        for (tag, counts) in &tags {
+            write_property(&mut out, 6, &format!("tag:{tag}"), &format_counts(counts));
+        }
+
+        for (file, counts) in &files {
+            write_property(&mut out, 6, &format!("file:{file}"), &format_counts(counts));
+        }
+
+        out.push_str("    </properties>\n");
+
+        for outcome in outcomes {
+            let test = &outcome.test;
+            writeln!(
+                out,
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{}\" file=\"{}\" line=\"{}\">",
+                escape_xml(&display_name(outcome)),
+                escape_xml(&test.module_path),
+                outcome.duration.as_secs_f64(),
+                escape_xml(&test.file),
+                test.line
+            )
+            .unwrap();
+
+            out.push_str("      <properties>\n");
+            write_property(&mut out, 8, "path", &test.path());
+
+            if let Some(description) = &test.description {
+                write_property(&mut out, 8, "description", description);
+            }
+
+            for tag in &test.tags {
+                write_property(&mut out, 8, "tag", tag);
+            }
+
+            if test.slow {
+                write_property(&mut out, 8, "slow", "true");
+            }
+
+            out.push_str("      </properties>\n");
+
+            if outcome.result.status == TestStatus::Passed {
+                out.push_str("    </testcase>\n");
+                continue;
+            }
+
+            let message = outcome.result.message.as_deref().unwrap_or_default();
+
+            if outcome.result.status == TestStatus::Skipped {
+                writeln!(out, "      <skipped message=\"{}\" />", escape_xml(message)).unwrap();
+                out.push_str("    </testcase>\n");
+                continue;
+            }
+
+            let mut detail = message.to_string();
+
+            if let Some(backtrace) = &outcome.result.backtrace {
+                detail.push_str(&format!("\n{backtrace}"));
+            }
+
+            writeln!(
+                out,
+                "      <failure type=\"{}\" message=\"{}\">{}</failure>",
+                outcome.result.status.reason(),
+                escape_xml(message),
+                escape_xml(&detail)
+            )
+            .unwrap();
+
+            if let Some(output) = &outcome.result.output {
+                writeln!(out, "      <system-out>{}</system-out>", escape_xml(output)).unwrap();
+            }
+
+            out.push_str("    </testcase>\n");
+        }
+
+        out.push_str("  </testsuite>\n");
+    }
+
+    out.push_str("</testsuites>\n");
+    out
+}
+
+/// Formats the results of a run as a JSON object, with a `tests` array holding the result of every
+/// test that ran, and a `summary` with the counts of the whole run. The summary also has the
+/// counts for each tag under `tags` and for each source file under `files`, as given by
+/// `TestReport::tag_summary` and `TestReport::file_summary`. It's indented unless `compact` is
+/// set, in which case it's written on a single line. Either way, it ends with a newline.
+pub fn json(report: &TestReport, compact: bool) -> String {
+    let tests: Vec<serde_json::Value> = report
+        .outcomes
+        .iter()
+        .map(|outcome| {
+            serde_json::json!({
+                "id": outcome.test.id(),
+                "path": outcome.test.path(),
+                "name": outcome.test.name,
+                "case": outcome.test.case,
+                "description": outcome.test.description,
+                "suite": outcome.test.suite,
+                "tags": outcome.test.tags,
+                "status": outcome.result.status.reason(),
+                "message": outcome.result.message,
+                "duration_seconds": outcome.duration.as_secs_f64(),
+                "attempts": outcome.attempts,
+                "slow": outcome.test.slow,
+                "file": outcome.test.file,
+                "line": outcome.test.line,
+            })
+        })
+        .collect();
+
+    let report = serde_json::json!({
+        "tests": tests,
+        "summary": {
+            "passed": report.successes(),
+            "failed": report.failures(),
+            "benchmarks": report.benchmarks(),
+            "flaky": report.flaky(),
+            "skipped": report.skipped,
+            "ignored": report.ignored,
+            "not_run": report.not_run,
+            "warnings": report.warnings.len(),
+            "tags": report.tag_summary(),
+            "files": report.file_summary(),
+        },
+    });
+
+    let mut json = if compact {
+        serde_json::to_string(&report)
+    } else {
+        serde_json::to_string_pretty(&report)
+    }
+    .unwrap();
+
+    json.push('\n');
+    json
+}
+
+/// Formats the results of a run in the Test Anything Protocol, version 13. The message of a test
+/// that failed is given in a YAML block under it, along with `slow: true` for the tests marked as
+/// slow, which get the block even if they passed. The tests that didn't run are marked with a
+/// `SKIP` directive giving the reason why.
+pub fn tap(report: &TestReport) -> String {
+    let outcomes: Vec<&TestOutcome> = report.outcomes.iter().chain(&report.unrun).collect();
+    let mut out = format!("TAP version 13\n1..{}\n", outcomes.len());
+
+    for (i, outcome) in outcomes.into_iter().enumerate() {
+        if outcome.result.status == TestStatus::Skipped {
+            writeln!(
+                out,
+                "ok {} - {} # SKIP {}",
+                i + 1,
+                display_name(outcome).replace('#', "\\#"),
+                outcome.result.message.as_deref().unwrap_or_default()
+            )
+            .unwrap();
+            continue;
+        }
+
+        let passed = outcome.result.status == TestStatus::Passed;
+        writeln!(
+            out,
+            "{} {} - {}",
+            if passed { "ok" } else { "not ok" },
+            i + 1,
+            display_name(outcome).replace('#', "\\#")
+        )
+        .unwrap();
+
+        if passed && !outcome.test.slow {
+            continue;
+        }
+
+        out.push_str("  ---\n");
+        writeln!(out, "  status: {}", outcome.result.status.reason()).unwrap();
+
+        if outcome.test.slow {
+            out.push_str("  slow: true\n");
+        }
+
+        if let Some(message) = &outcome.result.message {
+            out.push_str("  message: |\n");
+
+            for line in message.lines() {
+                writeln!(out, "    {line}").unwrap();
+            }
+        }
+
+        out.push_str("  ...\n");
+    }
+
+    out
+}
+
+/// Removes the ANSI escape sequences that color the console output.
+fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' && chars.peek() == Some(&'[') {
+            // A sequence ends with its first character in `@..=~`, after the `[`.
+            chars.next();
+            chars.find(|c| ('@'..='~').contains(c));
+        } else {
+            out.push(c);
+        }
+    }
+
+    out
+}
+
+/// Writes a report to a file when the run finishes. If it can't be written, the error is kept for
+/// the runner to report as a warning.
+pub(crate) struct FileReporter {
+    kind: ReportKind,
+    path: PathBuf,
+    /// A copy of the runner's output, for human reports.
+    output: Option<OutputBuffer>,
+    /// Write JSON reports on a single line.
+    compact_json: bool,
+    error: Mutex<Option<String>>,
+}
+
+impl FileReporter {
+    /// Creates a reporter writing a `kind` report to `path`. Human reports are a copy of `output`,
+    /// and JSON reports are written on a single line with `compact_json`.
+    pub(crate) fn new(
+        kind: ReportKind,
+        path: PathBuf,
+        output: Option<OutputBuffer>,
+        compact_json: bool,
+    ) -> Self {
+        Self {
+            kind,
+            path,
+            output,
+            compact_json,
+            error: Mutex::new(None),
+        }
+    }
+
+    /// Returns the error that prevented the report from being written, if there was any.
+    pub(crate) fn take_error(&self) -> Option<String> {
+        self.error.lock().unwrap().take()
+    }
+
+    fn save(&self, contents: String) -> Result<(), String> {
+        let path = &self.path;
+
+        crate::write_file(path, contents)
+            .map_err(|e| format!("Could not write the report to {}: {e}.", path.display()))
+    }
+}
+
+impl Observer for FileReporter {
+    fn on_run_finished(&self, report: &TestReport) {
+        let contents = match self.kind {
+            ReportKind::Human => {
+                strip_ansi(&self.output.as_ref().map(OutputBuffer::contents).unwrap_or_default())
+            }
+            ReportKind::Junit => junit(report),
+            ReportKind::Json => json(report, self.compact_json),
+            ReportKind::Tap => tap(report),
+        };
+
+        if let Err(message) = self.save(contents) {
+            *self.error.lock().unwrap() = Some(message);
+        }
+    }
+}
@@ -1,79 +1,742 @@
 use std::{
+    cell::RefCell,
     cmp::Ordering,
+    collections::{BTreeMap, BTreeSet},
+    fmt,
     io::{self, Write},
+    num::NonZeroUsize,
+    path::PathBuf,
     panic,
-    time::{Duration, Instant},
+    sync::{
+        Arc, Mutex,
+        atomic::{self, AtomicBool},
+    },
+    thread,
+    time::Duration,
 };
 
-use colored::Colorize;
+use colored::{ColoredString, Colorize};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    CLEANUP, SETUP, TEST_RUNNER_CONFIG, TESTS,
-    test::{Test, TestStatus},
+    CLEANUP, KNOWN_TAGS, OrderedFn, PanicHook, RT_THREADS_ENV_VAR_NAME, RUN_MARKER_ENV_VAR_NAME,
+    SETUP, SUITE_CLEANUPS, SUITE_SETUPS, ScopedFn, TAG_CLEANUPS, TAG_SETUPS, TEST_RUNNER_CONFIG,
+    TEST_RUNNER_TOGGLE_ENV_VAR_NAME, TESTS, artifacts, changes, dependencies,
+    executor::{CaseRunner, Executor, exec_with_timing},
+    isolation,
+    last_run::{self, LastStatus},
+    metrics,
+    observer::Observer,
+    reports::{FileReporter, ReportKind},
+    test::{self, BenchStats, Test, TestKind, TestResult, TestStatus},
+    theme::OutputTheme,
+    timings::{self, TimingRegression},
 };
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The default, human-readable output.
+    Human,
+    /// The human-readable output, plus GitHub Actions annotations for the failing tests.
+    Github,
+}
+
+/// How the tests are grouped under headers in the output.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GroupBy {
+    /// One group for every distinct set of tags.
+    #[default]
+    TagSet,
+    /// One group for every test's first tag.
+    FirstTag,
+    /// No groups, only a flat list of tests in each suite.
+    None,
+}
+
+/// How the tests are split between the worker threads when they run in parallel.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParallelBy {
+    /// Any test case can run on any worker, at the same time as any other.
+    #[default]
+    Case,
+    /// The test cases of each source file run one after the other on the same worker, while the
+    /// files run in parallel.
+    File,
+}
+
+/// The order in which the tests are run.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TestOrder {
+    /// Sorted by suite, tags, name and case.
+    #[default]
+    Default,
+    /// Sorted by priority, highest first, and then as in `Default`.
+    Priority,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct TestifyConfig {
-    pub name_filter: Option<String>,
+    pub name_filters: Vec<String>,
     pub tags: Vec<String>,
     pub exclude_tags: Vec<String>,
+    /// Pairs of `(alias, tag)`. A tag filter matching an alias also matches the tests with the tag
+    /// it's an alias of, but not the other way around.
+    pub tag_aliases: Vec<(String, String)>,
+    /// Only run the tests in any of these suites. All tests are run if empty.
+    pub suites: Vec<String>,
     pub fail_fast: bool,
+    pub jobs: Option<NonZeroUsize>,
+    pub list_tags: bool,
+    /// The output format. If not set, it's detected from the environment.
+    pub format: Option<OutputFormat>,
+    /// Reports written when the run finishes, in addition to the output, as pairs of
+    /// `(kind, path)`.
+    pub reports: Vec<(ReportKind, PathBuf)>,
+    /// Write the JSON reports on a single line instead of indented.
+    pub compact_json: bool,
+    /// How verbose the output is, given by the number of times `-v` was passed.
+    pub verbose: u8,
+    /// Fail the run if any warning was emitted, even if all tests passed.
+    pub fail_on_warnings: bool,
+    /// Print the number of passed and failed tests for each tag at the end of the run.
+    pub tag_summary: bool,
+    /// Print the number of passed and failed tests for each source file after the run.
+    pub file_summary: bool,
+    /// The directory where the tests' artifacts are written. If not set, a new directory in the
+    /// system's temporary directory is used for each run.
+    pub output_dir: Option<PathBuf>,
+    /// How the tests are grouped in the output.
+    pub group_by: GroupBy,
+    /// Capture the backtrace of panicking tests and print it under them.
+    pub backtrace: bool,
+    /// Show the failing tests with as many details as with `-vv`, without changing how the passing
+    /// ones are shown.
+    pub verbose_failures: bool,
+    /// The order in which the tests are run.
+    pub order: TestOrder,
+    /// Match the name filters exactly against the tests' names instead of as glob patterns.
+    pub exact: bool,
+    /// Match the name filters against the tests' names as regular expressions instead of as glob
+    /// patterns.
+    pub use_regex: bool,
+    /// Report the tests that weren't registered in the previous run, tracking them in
+    /// `known_tests_file`.
+    pub new_tests: bool,
+    /// The file where the tests seen in the previous run are stored. Defaults to
+    /// `.testify/known-tests.json`.
+    pub known_tests_file: Option<PathBuf>,
+    /// The symbols and indentation of the output.
+    pub theme: OutputTheme,
+    /// The number of times a failing test is run again before it's considered failed.
+    pub retries: u32,
+    /// Fail the tests that only passed after being retried.
+    pub strict_flaky: bool,
+    /// Don't run the setup functions, including the tag-scoped ones.
+    pub no_setup: bool,
+    /// Don't run the cleanup functions, including the tag-scoped ones.
+    pub no_cleanup: bool,
+    /// Run the tests again and again until they fail, up to this many times (or forever, if 0).
+    pub repeat_until_failure: Option<u32>,
+    /// When repeating the tests, run the setup and cleanup for every iteration instead of once.
+    pub repeat_setup: bool,
+    /// Glob patterns of module paths whose tests are never run, read from `.testifyignore`.
+    pub ignore_patterns: Vec<String>,
+    /// If set, only the tests whose name or ID is in the list are run, along with the other
+    /// filters. It's read from the file passed to `--filter-file`.
+    pub filter_list: Option<Vec<String>>,
+    /// Only run one of several shards of the tests, as `(shard, shards)`, where `shard` starts at
+    /// 1. The tests are dealt to the shards one by one, after being filtered and sorted.
+    pub shard: Option<(usize, usize)>,
+    /// Run each test case in its own process, so that aborts and crashes only fail that case.
+    pub isolate: bool,
+    /// Don't run the tests marked with `should_panic` or `should_fail`.
+    pub skip_negative: bool,
+    /// Only run the tests marked with `should_panic` or `should_fail`.
+    pub only_negative: bool,
+    /// Fail the run if any test was ignored by `.testifyignore`, wasn't reached because the run
+    /// stopped early, or was skipped because a test it depends on didn't pass.
+    pub no_skips: bool,
+    /// Fail the run if no tests are registered.
+    pub error_on_empty: bool,
+    /// Write the durations of the passed tests to this file, to be used as a timings baseline.
+    pub save_timings: Option<PathBuf>,
+    /// Compare the durations of the passed tests with the ones in this file, written by
+    /// `save_timings`, and fail if any is slower by more than `regression_threshold`.
+    pub timings_baseline: Option<PathBuf>,
+    /// How much slower than in the timings baseline a test can be, as a percentage.
+    pub regression_threshold: f64,
+    /// Write the durations of the tests to this file as folded stacks, as read by flamegraph
+    /// tools.
+    pub fold_timings: Option<PathBuf>,
+    /// Run every async test on a tokio runtime of its own, instead of on the shared one.
+    pub fresh_runtime: bool,
+    /// Write the final status of every test to this file after the run, to be read with
+    /// `last_run::ids_with_status`.
+    pub last_run_file: Option<PathBuf>,
+    /// Only run the tests that had a status in the last run, as `(status, ids)`, where `ids` are
+    /// the IDs of the tests that had it. It's read from `last_run_file` by `--rerun-status`.
+    pub rerun: Option<(LastStatus, Vec<String>)>,
+    /// Write the results of the run to this file as Prometheus metrics, as given by
+    /// `metrics::format`.
+    pub metrics: Option<PathBuf>,
+    /// How the tests are split between the worker threads.
+    pub parallel_by: ParallelBy,
+    /// How long a test can run before it fails, unless the test or one of its tags sets its own.
+    pub timeout: Option<Duration>,
+    /// Pairs of `(tag, timeout)`, setting how long the tests with the tag can run. A test with
+    /// several of these tags gets the longest of their timeouts, and its own `timeout` overrides
+    /// them.
+    pub tag_timeouts: Vec<(String, Duration)>,
+    /// Warn about the tests that took longer than this to run, unless they're marked as `slow`.
+    pub warn_slow: Option<Duration>,
+    /// Print a single character for each test instead of a line, followed by the recap of the
+    /// failures. See `dot` for the characters.
+    pub dots: bool,
+    /// Only run the tests that are new or whose source changed since they last passed, given the
+    /// source hashes they had then, as read from `test_hashes_file` by `--changed-tests-only`.
+    pub changed_only: Option<BTreeMap<String, u64>>,
+    /// The file where the source hashes of the tests that pass are stored with `changed_only`.
+    /// Defaults to `.testify/test-hashes.json`.
+    pub test_hashes_file: Option<PathBuf>,
+}
+
+/// Where the runner writes its output.
+type Output = Box<dyn Write + Send>;
+
+thread_local! {
+    /// Where the runner running on this thread writes its output, or `None` to write it to stdout.
+    static OUTPUT: RefCell<Option<Output>> = const { RefCell::new(None) };
+}
+
+/// Writes the runner's output to the `OUTPUT` of the current thread. It's used through `out!` and
+/// `outln!`, which work like `print!` and `println!`.
+fn write_output(args: fmt::Arguments) {
+    OUTPUT.with_borrow_mut(|output| {
+        // Like `print!`, but without panicking if the output is closed.
+        let _ = match output {
+            Some(output) => output.write_fmt(args),
+            None => io::stdout().write_fmt(args),
+        };
+    });
+}
+
+macro_rules! out {
+    ($($arg:tt)*) => {
+        write_output(format_args!($($arg)*))
+    };
+}
+
+macro_rules! outln {
+    () => {
+        write_output(format_args!("\n"))
+    };
+    ($($arg:tt)*) => {
+        write_output(format_args!("{}\n", format_args!($($arg)*)))
+    };
 }
 
 fn flush() {
-    io::stdout().flush().unwrap();
+    OUTPUT.with_borrow_mut(|output| {
+        let _ = match output {
+            Some(output) => output.flush(),
+            None => io::stdout().flush(),
+        };
+    });
+}
+
+/// Sets the `OUTPUT` of the current thread while it's alive, restoring the previous one when it's
+/// dropped, so that the output of a run that panics doesn't leak into the next one.
+struct OutputGuard {
+    previous: Option<Output>,
+}
+
+impl OutputGuard {
+    fn new(output: Option<Output>) -> Self {
+        Self {
+            previous: OUTPUT.replace(output),
+        }
+    }
+}
+
+impl Drop for OutputGuard {
+    fn drop(&mut self) {
+        flush();
+        OUTPUT.set(self.previous.take());
+    }
+}
+
+/// A buffer that can be passed to `Runner::with_output` to capture the output of a run, while
+/// keeping a clone of it to read the output afterwards.
+///
+/// ```ignore
+/// let output = OutputBuffer::default();
+/// let report = Runner::new().with_output(output.clone()).run();
+///
+/// assert!(output.contents().contains("Finished running tests."));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct OutputBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl OutputBuffer {
+    /// Returns what was written to the buffer so far, replacing invalid UTF-8.
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned()
+    }
+}
+
+impl Write for OutputBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes to the runner's output while keeping a copy of it, for the human reports.
+struct TeeOutput {
+    output: Option<Output>,
+    copy: OutputBuffer,
+}
+
+impl Write for TeeOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = match &mut self.output {
+            Some(output) => output.write(buf)?,
+            None => io::stdout().write(buf)?,
+        };
+
+        self.copy.write_all(&buf[..written])?;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.output {
+            Some(output) => output.flush(),
+            None => io::stdout().flush(),
+        }
+    }
+}
+
+/// The output of a repeated run, shared by its iterations so that they write to the same place.
+#[derive(Clone)]
+struct SharedOutput(Arc<Mutex<Option<Output>>>);
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut *self.0.lock().unwrap() {
+            Some(output) => output.write(buf),
+            None => io::stdout().write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut *self.0.lock().unwrap() {
+            Some(output) => output.flush(),
+            None => io::stdout().flush(),
+        }
+    }
+}
+
+/// Prints a warning and records it, so that it can be reported at the end of the run.
+fn warn(warnings: &mut Vec<String>, message: String) {
+    outln!("{} {message}", "Warning:".yellow());
+    warnings.push(message);
+}
+
+/// Prints a (possibly multiline) message indented by `indent` spaces.
+fn print_message(message: &str, indent: usize) {
+    for line in message.lines() {
+        outln!("{:indent$}{}", "", line.dimmed());
+    }
+}
+
+/// Formats a count followed by a noun, adding an `s` to the noun unless the count is 1.
+fn pluralize(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {noun}")
+    } else {
+        format!("{count} {noun}s")
+    }
+}
+
+/// The legend printed above the tests with `dots`.
+const DOTS_LEGEND: &str = "Legend: . passed, F failed, P panicked, S skipped";
+
+/// How many dots are printed on each line with `dots`.
+const DOTS_PER_LINE: usize = 80;
+
+/// Returns the character printed for a test's result with `dots`: `.` if it passed, `P` if it
+/// panicked unexpectedly, and `F` if it failed otherwise. Skipped tests are printed as `S`.
+fn dot(status: TestStatus, theme: &OutputTheme) -> ColoredString {
+    match status {
+        TestStatus::Passed => theme.success("."),
+        TestStatus::Panicked => theme.failure("P"),
+        TestStatus::Failed | TestStatus::NotPanicked | TestStatus::NotFailed => theme.failure("F"),
+        TestStatus::Skipped => theme.skipped("S"),
+    }
 }
 
-fn format_duration(duration: Duration) -> String {
+/// Formats the timings shown next to a benchmark instead of its result.
+fn format_bench_stats(stats: &BenchStats, theme: &OutputTheme) -> String {
+    format!(
+        "{} mean, {} median {}",
+        format_duration(stats.mean, theme).bold(),
+        format_duration(stats.median, theme).bold(),
+        format!("(per iteration, {} iterations)", stats.iterations).dimmed()
+    )
+}
+
+fn format_duration(duration: Duration, theme: &OutputTheme) -> String {
     let nanos = duration.as_nanos();
+    let precision = theme.duration_precision.unwrap_or(0);
 
     if nanos < 1_000 {
         format!("{}ns", nanos)
     } else if nanos < 1_000_000 {
         let micros = nanos as f64 / 1_000.0;
-        format!("{:.0}µs", micros)
+        format!("{:.precision$}{}", micros, theme.micros)
     } else if nanos < 1_000_000_000 {
         let millis = nanos as f64 / 1_000_000.0;
-        format!("{:.0}ms", millis)
+        format!("{:.precision$}ms", millis)
     } else if nanos < 60_000_000_000 {
         let secs = nanos as f64 / 1_000_000_000.0;
-        format!("{:.2}s", secs)
+        format!("{:.precision$}s", secs, precision = theme.duration_precision.unwrap_or(2))
     } else {
         let secs_total = nanos as f64 / 1_000_000_000.0;
         let minutes = (secs_total / 60.0).floor();
         let remaining_secs = secs_total % 60.0;
-        format!("{:.0}m {:.0}s", minutes, remaining_secs)
+        format!("{:.0}m {:.precision$}s", minutes, remaining_secs)
     }
 }
 
 struct TestGroup {
-    tags: Vec<String>,
+    suite: Option<String>,
+    /// The tags shown in the group's header, or `None` if the group has no header.
+    tags: Option<Vec<String>>,
     test_plans: Vec<TestPlan>,
 }
 
 struct TestPlan {
     name: String,
     cases: Vec<Test>,
+    /// The cases nested as given by their paths, in the order they run.
+    case_tree: Vec<CaseNode>,
+}
+
+/// A level of a test's nested cases.
+enum CaseNode {
+    /// A case that runs, by its index in `TestPlan::cases`.
+    Case { name: String, index: usize },
+    /// A case grouping the cases nested inside it.
+    Parent {
+        name: String,
+        children: Vec<CaseNode>,
+    },
+}
+
+/// A step of rendering a test's nested cases, in the order they're printed.
+enum CaseStep<'a> {
+    Open(&'a str),
+    Run(&'a str, &'a Test),
+    Close,
+}
+
+impl TestPlan {
+    fn new(test: Test) -> Self {
+        Self {
+            name: test.name.clone(),
+            cases: vec![test],
+            case_tree: Vec::new(),
+        }
+    }
+
+    /// Nests the cases by their paths. Consecutive cases sharing a parent are its children, so
+    /// that the cases keep running in order even if the ones of a parent aren't contiguous.
+    fn build_case_tree(&mut self) {
+        self.case_tree.clear();
+
+        for (index, case) in self.cases.iter().enumerate() {
+            let path = case.case_path();
+            let (name, parents) = path.split_last().unwrap_or((&"unknown", &[]));
+            let mut level = &mut self.case_tree;
+
+            for parent in parents {
+                let is_open = matches!(
+                    level.last(),
+                    Some(CaseNode::Parent { name, .. }) if name == parent
+                );
+
+                if !is_open {
+                    level.push(CaseNode::Parent {
+                        name: parent.to_string(),
+                        children: Vec::new(),
+                    });
+                }
+
+                let Some(CaseNode::Parent { children, .. }) = level.last_mut() else {
+                    unreachable!("The parent was just pushed.");
+                };
+                level = children;
+            }
+
+            level.push(CaseNode::Case {
+                name: name.to_string(),
+                index,
+            });
+        }
+    }
+
+    /// Walks the case tree depth-first, opening and closing each parent around its children.
+    fn case_steps(&self) -> Vec<CaseStep<'_>> {
+        fn walk<'a>(plan: &'a TestPlan, nodes: &'a [CaseNode], steps: &mut Vec<CaseStep<'a>>) {
+            for node in nodes {
+                match node {
+                    CaseNode::Case { name, index } => {
+                        steps.push(CaseStep::Run(name, &plan.cases[*index]));
+                    }
+                    CaseNode::Parent { name, children } => {
+                        steps.push(CaseStep::Open(name));
+                        walk(plan, children, steps);
+                        steps.push(CaseStep::Close);
+                    }
+                }
+            }
+        }
+
+        let mut steps = Vec::new();
+        walk(self, &self.case_tree, &mut steps);
+        steps
+    }
+}
+
+/// A group of tests sharing a suite and tags, as returned by `plan()`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PlannedGroup {
+    pub suite: Option<String>,
+    /// The tags shown in the group's header, or `None` if the tests aren't grouped by tags.
+    pub tags: Option<Vec<String>>,
+    pub tests: Vec<PlannedTest>,
+}
+
+/// The cases of a test, in the order they'd run.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PlannedTest {
+    pub name: String,
+    pub cases: Vec<PlannedCase>,
+}
+
+/// The metadata of a test case, without the function that runs it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PlannedCase {
+    /// The ID of the test, as given by `Test::id`.
+    pub id: String,
+    /// The address of the test, as given by `Test::path`.
+    pub path: String,
+    pub case: Option<String>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub priority: i32,
+    pub should_panic: bool,
+    pub should_fail: Option<String>,
+    pub kind: TestKind,
+    pub depends_on: Vec<String>,
+    pub slow: bool,
+    pub module_path: String,
+    pub file: String,
+    pub line: u32,
+}
+
+impl From<&Test> for PlannedCase {
+    fn from(test: &Test) -> Self {
+        Self {
+            id: test.id(),
+            path: test.path(),
+            case: test.case.clone(),
+            description: test.description.clone(),
+            tags: test.tags.clone(),
+            priority: test.priority,
+            should_panic: test.should_panic,
+            should_fail: test.should_fail.clone(),
+            kind: test.kind,
+            depends_on: test.depends_on.clone(),
+            slow: test.slow,
+            module_path: test.module_path.clone(),
+            file: test.file.clone(),
+            line: test.line,
+        }
+    }
+}
+
+impl From<&TestGroup> for PlannedGroup {
+    fn from(group: &TestGroup) -> Self {
+        Self {
+            suite: group.suite.clone(),
+            tags: group.tags.clone(),
+            tests: group
+                .test_plans
+                .iter()
+                .map(|plan| PlannedTest {
+                    name: plan.name.clone(),
+                    cases: plan.cases.iter().map(PlannedCase::from).collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A nested case whose cases are still being run.
+struct OpenCase {
+    passed: usize,
+    total: usize,
+}
+
+/// Closes the innermost open case, printing how many of its cases passed if `show` is set. Cases
+/// nested `n` levels deep are indented `n + 2` times.
+fn close_case(open_cases: &mut Vec<OpenCase>, show: bool, theme: &OutputTheme) {
+    let Some(case) = open_cases.pop() else {
+        return;
+    };
+
+    if show {
+        let summary = format!("{}/{} passed.", case.passed, case.total);
+
+        outln!(
+            "{:indent$}{}",
+            "",
+            if case.passed == case.total {
+                theme.success(&summary)
+            } else {
+                theme.failure(&summary)
+            },
+            indent = theme.indent * (open_cases.len() + 3)
+        );
+    }
+}
+
+/// Returns the tags of the group a test belongs to, as given by `group_by`.
+pub(crate) fn group_tags(test: &Test, group_by: GroupBy) -> Option<Vec<String>> {
+    match group_by {
+        GroupBy::TagSet => Some(test.tags.clone()),
+        GroupBy::FirstTag => Some(test.tags.first().cloned().into_iter().collect()),
+        GroupBy::None => None,
+    }
+}
+
+/// Returns whether `name` is the test's name or its path, either of them followed by its case as in
+/// `Name (Case)` or without it.
+pub(crate) fn matches_exactly(test: &Test, name: &str) -> bool {
+    [test.name.clone(), test.qualified_name()].iter().any(|prefix| {
+        let Some(rest) = name.strip_prefix(prefix.as_str()) else {
+            return false;
+        };
+
+        rest.is_empty()
+            || test.case.as_ref().is_some_and(|case| {
+                rest.strip_prefix(" (").and_then(|rest| rest.strip_suffix(')'))
+                    == Some(case.as_str())
+            })
+    })
+}
+
+/// Whether a test was defined in a module matched by any of the ignore patterns, or inside one of
+/// its submodules.
+fn is_ignored(test: &Test, ignore_patterns: &[glob::Pattern]) -> bool {
+    let mut module_path = test.module_path.as_str();
+
+    loop {
+        if ignore_patterns.iter().any(|pattern| pattern.matches(module_path)) {
+            return true;
+        }
+
+        match module_path.rsplit_once("::") {
+            Some((parent, _)) => module_path = parent,
+            None => return false,
+        }
+    }
+}
+
+/// Returns a tag passed as a filter along with the tags it's an alias of.
+fn expand_tag<'a>(tag: &'a str, config: &'a TestifyConfig) -> impl Iterator<Item = &'a str> {
+    std::iter::once(tag).chain(
+        config
+            .tag_aliases
+            .iter()
+            .filter(move |(alias, _)| alias == tag)
+            .map(|(_, target)| target.as_str()),
+    )
 }
 
-fn organize(tests: Vec<Test>, config: &TestifyConfig, pattern: &glob::Pattern) -> Vec<TestGroup> {
+fn organize(
+    tests: &[Test],
+    config: &TestifyConfig,
+    patterns: &[NamePattern],
+    ignore_patterns: &[glob::Pattern],
+) -> Vec<TestGroup> {
     let mut tests: Vec<Test> = tests
         .iter()
         .filter(|test| {
+            if is_ignored(test, ignore_patterns) {
+                return false;
+            }
+
+            if let Some(filter_list) = &config.filter_list {
+                let id = test.id();
+
+                if !filter_list
+                    .iter()
+                    .any(|entry| *entry == id || matches_exactly(test, entry))
+                {
+                    return false;
+                }
+            }
+
+            if let Some((_, ids)) = &config.rerun
+                && !ids.contains(&test.id())
+            {
+                return false;
+            }
+
+            if let Some(baseline) = &config.changed_only
+                && !changes::is_changed(test, baseline)
+            {
+                return false;
+            }
+
+            if (config.skip_negative && test.is_negative())
+                || (config.only_negative && !test.is_negative())
+            {
+                return false;
+            }
+
             for tag in config.tags.iter() {
-                if !test.tags.contains(tag) {
+                if !expand_tag(tag, config).any(|tag| test.tags.iter().any(|t| t == tag)) {
                     return false;
                 }
             }
 
             for tag in config.exclude_tags.iter() {
-                if test.tags.contains(tag) {
+                if expand_tag(tag, config).any(|tag| test.tags.iter().any(|t| t == tag)) {
                     return false;
                 }
             }
 
-            if !pattern.matches(&test.name) {
+            if !config.suites.is_empty()
+                && !test.suite.as_ref().is_some_and(|suite| config.suites.contains(suite))
+            {
+                return false;
+            }
+
+            if config.exact {
+                if !config.name_filters.is_empty()
+                    && !config.name_filters.iter().any(|name| matches_exactly(test, name))
+                {
+                    return false;
+                }
+            } else if !patterns.is_empty()
+                && !patterns.iter().any(|pattern| pattern.matches(&test.name))
+            {
                 return false;
             }
 
@@ -83,7 +746,21 @@ fn organize(tests: Vec<Test>, config: &TestifyConfig, pattern: &glob::Pattern) -
         .collect();
 
     tests.sort_by(|a, b| {
-        let cmp = a.tags.cmp(&b.tags);
+        if config.order == TestOrder::Priority {
+            let cmp = b.priority.cmp(&a.priority);
+
+            if cmp != Ordering::Equal {
+                return cmp;
+            }
+        }
+
+        let cmp = a.suite.cmp(&b.suite);
+
+        if cmp != Ordering::Equal {
+            return cmp;
+        }
+
+        let cmp = group_tags(a, config.group_by).cmp(&group_tags(b, config.group_by));
 
         if cmp != Ordering::Equal {
             return cmp;
@@ -98,228 +775,2265 @@ fn organize(tests: Vec<Test>, config: &TestifyConfig, pattern: &glob::Pattern) -
         a.case.cmp(&b.case)
     });
 
+    // Tests that depend on others are moved after them, splitting their group if needed.
+    let mut tests = dependencies::sort(tests);
+
+    // The tests are dealt round-robin rather than split in contiguous chunks, so that each shard
+    // gets a similar mix of suites and priorities. As long as every machine sees the same tests,
+    // they all agree on the shards. Tests connected by their dependencies are dealt together, so
+    // that they still run in order.
+    if let Some((shard, shards)) = config.shard {
+        let components = dependencies::components(&tests);
+
+        tests = tests
+            .into_iter()
+            .zip(components)
+            .filter(|(_, component)| component % shards == shard - 1)
+            .map(|(test, _)| test)
+            .collect();
+    }
+
     let mut result: Vec<TestGroup> = Vec::new();
 
     for test in tests {
+        let tags = group_tags(&test, config.group_by);
+
         if let Some(last_group) = result.last_mut() {
-            if last_group.tags == test.tags {
+            if last_group.suite == test.suite && last_group.tags == tags {
                 if let Some(last_test) = last_group.test_plans.last_mut() {
                     if last_test.name == test.name {
                         last_test.cases.push(test);
                     } else {
-                        last_group.test_plans.push(TestPlan {
-                            name: test.name.clone(),
-                            cases: vec![test],
-                        });
+                        last_group.test_plans.push(TestPlan::new(test));
                     }
                 } else {
                     panic!("This code shouldn't be running.");
                 }
             } else {
                 result.push(TestGroup {
-                    tags: test.tags.clone(),
-                    test_plans: vec![TestPlan {
-                        name: test.name.clone(),
-                        cases: vec![test],
-                    }],
+                    suite: test.suite.clone(),
+                    tags,
+                    test_plans: vec![TestPlan::new(test)],
                 });
             }
         } else {
             result.push(TestGroup {
-                tags: test.tags.clone(),
-                test_plans: vec![TestPlan {
-                    name: test.name.clone(),
-                    cases: vec![test],
-                }],
+                suite: test.suite.clone(),
+                tags,
+                test_plans: vec![TestPlan::new(test)],
             });
         }
     }
 
+    for plan in result.iter_mut().flat_map(|group| &mut group.test_plans) {
+        plan.build_case_tree();
+    }
+
     result
 }
 
-/// Executes a function and returns the result together with the time the function took to execute.
-fn exec_with_timing<T>(f: fn() -> T) -> (T, Duration) {
-    let start = Instant::now();
-    let result = f();
+/// Computes the Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
 
-    (result, start.elapsed())
-}
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
 
-pub fn run() {
-    // TODO: Capture stdout and stderr to prevent polluting the test runner output. Currently, the
-    // function used to capture outputs by cargo test is only available on nightly builds of Rust.
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + if a_char == *b_char { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
 
-    // Initialize the runtime to avoid performance overhead later on.
-    #[cfg(feature = "async-tokio")]
-    let _ = &*crate::ASYNC_RT;
+        previous = current;
+    }
 
-    println!("✨ Testify! Running tests...\n");
-    let mut step = 1;
+    previous[b.len()]
+}
 
-    if SETUP.lock().unwrap().is_some() {
-        print!("{step}. Starting up...");
-        flush();
-        step += 1;
+/// Returns a " Did you mean ...?" suffix with the closest candidate to `tag`, if any is close
+/// enough to be a likely typo.
+fn did_you_mean<'a>(tag: &str, candidates: impl Iterator<Item = &'a String>) -> String {
+    let max_distance = tag.chars().count().div_ceil(3);
 
-        if let Some(startup) = SETUP.lock().unwrap().take() {
-            startup();
-        }
+    candidates
+        .filter(|candidate| candidate.as_str() != tag)
+        .map(|candidate| (edit_distance(tag, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| format!(" Did you mean `{candidate}`?"))
+        .unwrap_or_default()
+}
 
-        print!("{}", " Ok.\n".green());
-        flush();
-    }
+/// Warns about tag filters that don't match any test and, if any tags were declared with
+/// `register_tags!`, about tests using tags that weren't declared.
+fn validate_tags(tests: &[Test], config: &TestifyConfig, warnings: &mut Vec<String>) {
+    let known_tags = KNOWN_TAGS.lock().unwrap();
+    let used_tags: BTreeSet<&String> = tests.iter().flat_map(|test| test.tags.iter()).collect();
 
-    let config: TestifyConfig = serde_json::from_str(&std::env::var(TEST_RUNNER_CONFIG).expect("Testify configuration env var was not found")).expect("Could not parse testify's configuration. Are the versions of testify_core and testify correct?");
+    if !known_tags.is_empty() {
+        for tag in used_tags.iter().filter(|tag| !known_tags.contains(tag)) {
+            warn(
+                warnings,
+                format!(
+                    "The tag `{tag}` is used by a test but was never registered.{}",
+                    did_you_mean(tag, known_tags.iter())
+                ),
+            );
+        }
+    }
 
-    let pattern = match glob::Pattern::new(if let Some(p) = &config.name_filter {
-        p
-    } else {
-        "*"
-    }) {
-        Ok(pa) => pa,
-        Err(_) => {
-            eprintln!("The pattern passed to the glob filter was invalid.");
-            std::process::exit(1);
+    for tag in config.tags.iter().chain(config.exclude_tags.iter()) {
+        if !expand_tag(tag, config).any(|tag| used_tags.iter().any(|used| used.as_str() == tag)) {
+            warn(
+                warnings,
+                format!(
+                    "The tag `{tag}` is not used by any test.{}",
+                    did_you_mean(tag, used_tags.iter().copied().chain(known_tags.iter()))
+                ),
+            );
         }
-    };
+    }
+}
 
-    // TODO: Collect panic messages to display them nicely later on.
-    panic::set_hook(Box::new(|_info| {}));
+/// Prints every tag used by the tests or registered with `register_tags!`, sorted by name, along
+/// with the number of tests using it. Each line is formatted as `<tag>\t<count>`.
+fn list_tags(tests: &[Test]) {
+    let mut counts: BTreeMap<&String, usize> = BTreeMap::new();
+    let known_tags = KNOWN_TAGS.lock().unwrap();
 
-    let all_tests = TESTS.lock().unwrap();
+    for tag in known_tags.iter() {
+        counts.entry(tag).or_default();
+    }
 
-    let groups = organize(all_tests.clone(), &config, &pattern);
+    for tag in tests.iter().flat_map(|test| test.tags.iter()) {
+        *counts.entry(tag).or_default() += 1;
+    }
 
-    let tests_to_run = groups.iter().fold(0, |prev, group| {
-        prev + group
-            .test_plans
-            .iter()
-            .fold(0, |gprev, test_plan| gprev + test_plan.cases.len())
-    });
+    for (tag, count) in counts {
+        outln!("{tag}\t{count}");
+    }
+}
 
-    let mut failures = 0;
-    let mut successes = 0;
+/// Returns the output format to use, defaulting to GitHub annotations when running on GitHub
+/// Actions.
+fn output_format(config: &TestifyConfig) -> OutputFormat {
+    config.format.unwrap_or_else(|| {
+        if std::env::var("GITHUB_ACTIONS").is_ok_and(|value| value == "true") {
+            OutputFormat::Github
+        } else {
+            OutputFormat::Human
+        }
+    })
+}
 
-    println!(
-        "{step}. Running {} tests {}...",
-        tests_to_run,
-        format!("({} skipped)", all_tests.len() - tests_to_run).black()
-    );
-    step += 1;
+/// Escapes a string to be used in a GitHub Actions workflow command. Property values need `:` and
+/// `,` to be escaped as well.
+fn escape_github(value: &str, is_property: bool) -> String {
+    let escaped = value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A");
 
-    let mut test_i = 1;
+    if is_property {
+        escaped.replace(':', "%3A").replace(',', "%2C")
+    } else {
+        escaped
+    }
+}
 
-    'groups_loop: for (group_i, group) in groups.iter().enumerate() {
-        let tags_str = group.tags.join(", ");
+/// Explains why a test with the given status didn't pass.
+fn failure_reason(test: &Test, status: TestStatus) -> String {
+    match (status, test.should_fail.as_deref()) {
+        (TestStatus::Panicked, _) => "The test panicked.".to_string(),
+        (TestStatus::NotPanicked, _) => "The test was expected to panic, but it did not.".to_string(),
+        (TestStatus::NotFailed, Some(description)) if !description.is_empty() => {
+            format!("The test was expected to fail ({description}), but it did not.")
+        }
+        (TestStatus::NotFailed, _) => "The test was expected to fail, but it did not.".to_string(),
+        (TestStatus::Skipped, _) => "A test it depends on didn't pass.".to_string(),
+        _ => "The test failed.".to_string(),
+    }
+}
 
-        println!(
-            "{}   {}",
-            if group_i == 0 { "" } else { "\n" },
-            format!(
-                "---- {} ----",
-                if group.tags.is_empty() {
-                    "No tags"
-                } else {
-                    &tags_str
-                }
-            )
-            .black()
-        );
+/// The outcome of a test that didn't run, with the reason why as its message.
+fn skipped_outcome(test: &Test, message: String) -> TestOutcome {
+    TestOutcome {
+        test: test.clone(),
+        result: TestResult {
+            status: TestStatus::Skipped,
+            message: Some(message),
+            backtrace: None,
+            value: None,
+            detail: None,
+            output: None,
+            bench: None,
+        },
+        duration: Duration::ZERO,
+        attempts: 0,
+    }
+}
 
-        for plan in &group.test_plans {
-            if plan.cases.len() == 1 {
-                print!("   {test_i}. {}...", plan.name);
-                flush();
+/// Prints every test that didn't pass, with the reason why, so that they can be found without
+/// scrolling through the whole output.
+fn print_failures_recap(report: &TestReport, theme: &OutputTheme) {
+    let indent = theme.indent;
 
-                let (result, duration) = exec_with_timing(plan.cases.first().unwrap().function);
+    let failures = report
+        .outcomes
+        .iter()
+        .filter(|outcome| outcome.result.status.is_failure());
 
-                match result {
-                    TestStatus::Passed => {
-                        println!(
-                            " {} {}",
-                            "Ok.".green(),
-                            format!("({})", format_duration(duration)).dimmed()
-                        );
+    outln!("\n{}", theme.failure(&format!("{}Failures:", theme.failures)).bold());
 
-                        successes += 1;
-                    }
-                    _ => {
-                        print!(" {}", "Failed!".red());
-                        failures += 1;
+    for (i, outcome) in failures.enumerate() {
+        let test = &outcome.test;
 
-                        if config.fail_fast {
-                            print!(" {}", "Aborted.".red());
-                            flush();
+        out!("{:indent$}{}. {}", "", i + 1, test.name.bold());
 
-                            break 'groups_loop;
-                        }
+        if let Some(case) = &test.case {
+            out!(" {}", format!("({case})").bold());
+        }
 
-                        println!();
-                    }
-                }
-            } else {
-                println!("   {test_i}. {}...", plan.name);
-
-                for case in &plan.cases {
-                    print!(
-                        "      {} {}{}",
-                        "Case".black(),
-                        case.case.as_deref().unwrap_or("unknown"),
-                        "...".dimmed()
-                    );
-                    flush();
+        if !test.tags.is_empty() {
+            out!(" {}", format!("[{}]", test.tags.join(", ")).black());
+        }
 
-                    let (result, duration) = exec_with_timing(case.function);
+        outln!(" {}", format!("{}:{}", test.file, test.line).dimmed());
+        print_message(&format!("Path: {}", test.path()), indent * 2);
+        outln!(
+            "{:indent$}{}",
+            "",
+            theme.failure(&failure_reason(test, outcome.result.status)),
+            indent = indent * 2
+        );
 
-                    match result {
-                        TestStatus::Passed => {
-                            println!(
-                                " {} {}",
-                                "Ok.".green(),
-                                format!("({})", format_duration(duration)).dimmed()
-                            );
+        if let Some(message) = &outcome.result.message {
+            print_message(message, indent * 2);
+        }
+    }
+}
 
-                            successes += 1;
-                        }
-                        _ => {
-                            print!(" {}", "Failed!".red());
-                            failures += 1;
+/// Prints every test that only passed after being retried, with the number of attempts it took.
+fn print_flaky_recap(report: &TestReport, theme: &OutputTheme) {
+    let indent = theme.indent;
 
-                            if config.fail_fast {
-                                print!(" {}", "Aborted.".red());
-                                flush();
+    outln!("\n{}", format!("{}Flaky:", theme.flaky).yellow().bold());
 
-                                break 'groups_loop;
-                            }
+    let flaky = report.outcomes.iter().filter(|outcome| outcome.is_flaky());
 
-                            println!();
-                        }
-                    }
-                }
-            }
+    for (i, outcome) in flaky.enumerate() {
+        let test = &outcome.test;
 
-            test_i += 1;
-        }
-    }
+        out!("{:indent$}{}. {}", "", i + 1, test.name.bold());
 
-    if CLEANUP.lock().unwrap().is_some() {
-        print!("{}{step}. Cleaning up...", if groups.len() > 1 { "\n" } else { "" });
-        flush();
-        if let Some(cleanup) = CLEANUP.lock().unwrap().take() {
-            cleanup();
+        if let Some(case) = &test.case {
+            out!(" {}", format!("({case})").bold());
         }
-        print!("{}", " Ok.\n".green());
-        flush();
+
+        outln!(
+            " {}",
+            format!("(passed after {} attempts)", outcome.attempts).dimmed()
+        );
     }
+}
 
-    println!(
-        "\n✅ Finished running tests. {} and {}.",
-        format!("{failures} failed").red(),
-        format!("{successes} succeeded").green()
+/// Prints every test that was slower than in the timings baseline, with both durations.
+fn print_regressions_recap(regressions: &[TimingRegression], threshold: f64, theme: &OutputTheme) {
+    let indent = theme.indent;
+
+    outln!(
+        "\n{}",
+        theme
+            .failure(&format!(
+                "{}Slower than the baseline by more than {threshold}%:",
+                theme.regressions
+            ))
+            .bold()
+    );
+
+    for (i, regression) in regressions.iter().enumerate() {
+        outln!(
+            "{:indent$}{}. {} {}",
+            "",
+            i + 1,
+            regression.id.bold(),
+            format!(
+                "({}, {} in the baseline, +{:.0}%)",
+                format_duration(regression.duration, theme),
+                format_duration(regression.baseline, theme),
+                regression.slowdown()
+            )
+            .dimmed()
+        );
+    }
+}
+
+/// Runs a setup or cleanup function, returning its panic message if it panics.
+fn catch_panic(function: fn()) -> Result<(), String> {
+    panic::catch_unwind(function).map_err(|payload| {
+        test::panic_message(&*payload).unwrap_or_else(|| "It panicked without a message.".to_string())
+    })
+}
+
+/// Runs the cleanups after printing `label`, timing them. Unlike the setups, the cleanups are
+/// independent, so they all run even if one panics. Returns the panic messages, one per line, if
+/// any of them panicked.
+fn run_cleanups(label: &str, cleanups: Vec<fn()>, theme: &OutputTheme) -> Option<String> {
+    out!("{label}");
+    flush();
+
+    let (errors, duration) = exec_with_timing(|| {
+        cleanups
+            .into_iter()
+            .filter_map(|cleanup| catch_panic(cleanup).err())
+            .collect::<Vec<_>>()
+    });
+
+    if errors.is_empty() {
+        outln!(
+            " {} {}",
+            theme.ok(),
+            format!("({})", format_duration(duration, theme)).dimmed()
+        );
+        return None;
+    }
+
+    let message = errors.join("\n");
+
+    outln!(" {}", theme.failed());
+    print_message(&message, theme.indent);
+    Some(message)
+}
+
+/// Fails a test that only passed after being retried.
+fn fail_flaky(result: TestResult, attempts: u32) -> TestResult {
+    if result.status != TestStatus::Passed || attempts <= 1 {
+        return result;
+    }
+
+    TestResult {
+        status: TestStatus::Failed,
+        message: Some(format!(
+            "The test only passed after {attempts} attempts, and `--strict-flaky` is set."
+        )),
+        backtrace: None,
+        value: None,
+        output: result.output,
+        detail: None,
+        bench: None,
+    }
+}
+
+/// Prints a GitHub Actions `error` annotation pointing at a failing test, titled with its name and
+/// the reason of its status.
+fn print_github_annotation(test: &Test, result: &TestResult) {
+    let title = match &test.case {
+        Some(case) => format!("{} ({case}) [{}]", test.name, result.status.reason()),
+        None => format!("{} [{}]", test.name, result.status.reason()),
+    };
+
+    let reason = failure_reason(test, result.status);
+
+    let message = match &result.message {
+        Some(message) => format!("{reason}\n{message}"),
+        None => reason.to_string(),
+    };
+
+    outln!(
+        "::error file={},line={},title={}::{}",
+        escape_github(&test.file, true),
+        test.line,
+        escape_github(&title, true),
+        escape_github(&message, false)
+    );
+}
+
+/// Fails a passing test if it took less than its `min_time` or more than its `max_time`.
+fn check_time_bounds(
+    test: &Test,
+    result: TestResult,
+    duration: Duration,
+    theme: &OutputTheme,
+) -> TestResult {
+    if result.status != TestStatus::Passed {
+        return result;
+    }
+
+    let message = match (test.min_time, test.max_time) {
+        (Some(min_time), _) if duration < min_time => format!(
+            "The test took {}, but it was expected to take at least {}.",
+            format_duration(duration, theme),
+            format_duration(min_time, theme)
+        ),
+        (_, Some(max_time)) if duration > max_time => format!(
+            "The test took {}, but it was expected to take at most {}.",
+            format_duration(duration, theme),
+            format_duration(max_time, theme)
+        ),
+        _ => return result,
+    };
+
+    TestResult {
+        status: TestStatus::Failed,
+        message: Some(message),
+        backtrace: None,
+        value: None,
+        output: result.output,
+        detail: None,
+        bench: None,
+    }
+}
+
+/// Fails a passing test marked with `no_captured_output` if it wrote to its captured output, which
+/// is kept so that it's shown under the test.
+fn check_no_captured_output(test: &Test, result: TestResult) -> TestResult {
+    if !test.no_captured_output || result.status != TestStatus::Passed || result.output.is_none() {
+        return result;
+    }
+
+    TestResult {
+        status: TestStatus::Failed,
+        message: Some(
+            "The test wrote to its captured output, but it's marked with `no_captured_output`."
+                .to_string(),
+        ),
+        backtrace: None,
+        value: None,
+        output: result.output,
+        detail: None,
+        bench: None,
+    }
+}
+
+/// Compares the IDs of `tests` with the ones stored in the known tests file, returning the new
+/// ones, and replaces the stored IDs with the current ones. Nothing is reported the first time,
+/// when there's no file yet.
+fn find_new_tests(tests: &[Test], config: &TestifyConfig, warnings: &mut Vec<String>) -> Vec<String> {
+    let path = config
+        .known_tests_file
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(".testify/known-tests.json"));
+
+    let ids: BTreeSet<String> = tests.iter().map(Test::id).collect();
+
+    let known: Option<BTreeSet<String>> = match std::fs::read_to_string(&path) {
+        Ok(contents) => match serde_json::from_str(&contents) {
+            Ok(known) => Some(known),
+            Err(e) => {
+                warn(warnings, format!("Could not parse {}: {e}.", path.display()));
+                None
+            }
+        },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => {
+            warn(warnings, format!("Could not read {}: {e}.", path.display()));
+            None
+        }
+    };
+
+    let saved = crate::write_file(&path, serde_json::to_string_pretty(&ids).unwrap());
+
+    if let Err(e) = saved {
+        warn(warnings, format!("Could not write {}: {e}.", path.display()));
+    }
+
+    match known {
+        Some(known) => ids.difference(&known).cloned().collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Warns about the tests that depend on a test that isn't registered, since they run as if they
+/// didn't depend on it.
+fn validate_dependencies(tests: &[Test], warnings: &mut Vec<String>) {
+    for test in tests {
+        for name in &test.depends_on {
+            if !tests.iter().any(|other| dependencies::refers_to(other, name)) {
+                warn(
+                    warnings,
+                    format!(
+                        "The test `{}` depends on `{name}`, but no test with that name is registered.{}",
+                        test.id(),
+                        did_you_mean(name, tests.iter().map(|other| &other.name))
+                    ),
+                );
+            }
+        }
+    }
+}
+
+/// Warns about the tests that took longer than `threshold`, other than the ones marked as `slow`
+/// and the benchmarks, whose duration covers all of their iterations.
+fn warn_slow(
+    outcomes: &[TestOutcome],
+    threshold: Duration,
+    theme: &OutputTheme,
+    warnings: &mut Vec<String>,
+) {
+    for outcome in outcomes {
+        if outcome.duration > threshold && !outcome.test.slow && outcome.test.kind == TestKind::Test
+        {
+            warn(
+                warnings,
+                format!(
+                    "The test `{}` took {}, longer than {}. Mark it with `slow` if that's expected.",
+                    outcome.test.id(),
+                    format_duration(outcome.duration, theme),
+                    format_duration(threshold, theme)
+                ),
+            );
+        }
+    }
+}
+
+/// Warns if `rerun` won't run any test, either because no test had the status in the last run or
+/// because the tests that had it aren't registered anymore.
+fn validate_rerun(tests: &[Test], config: &TestifyConfig, warnings: &mut Vec<String>) {
+    let Some((status, ids)) = &config.rerun else {
+        return;
+    };
+
+    if ids.is_empty() {
+        warn(warnings, format!("No test was {} in the last run.", status.as_str()));
+        return;
+    }
+
+    let registered: BTreeSet<String> = tests.iter().map(Test::id).collect();
+    let missing = ids.iter().filter(|id| !registered.contains(*id)).count();
+
+    if missing > 0 {
+        warn(
+            warnings,
+            format!(
+                "{} {} in the last run {} not registered anymore, so the last run may be stale.",
+                pluralize(missing, "test"),
+                status.as_str(),
+                if missing == 1 { "is" } else { "are" }
+            ),
+        );
+    }
+}
+
+/// Prints the tests that weren't registered in the previous run.
+fn print_new_tests(new_tests: &[String], theme: &OutputTheme) {
+    if new_tests.len() == 1 {
+        outln!("{}1 new test since the last run:", theme.new_tests);
+    } else {
+        outln!("{}{} new tests since the last run:", theme.new_tests, new_tests.len());
+    }
+
+    for id in new_tests {
+        outln!("{:indent$}- {id}", "", indent = theme.indent);
+    }
+}
+
+/// Returns the number of threads to run the tests on. The `--jobs` flag takes precedence over the
+/// `RUST_TEST_THREADS` environment variable, which takes precedence over the number of CPUs.
+fn job_count(config: &TestifyConfig, warnings: &mut Vec<String>) -> usize {
+    if let Some(jobs) = config.jobs {
+        return jobs.get();
+    }
+
+    if let Ok(threads) = std::env::var("RUST_TEST_THREADS") {
+        match threads.parse::<NonZeroUsize>() {
+            Ok(jobs) => return jobs.get(),
+            Err(_) => warn(
+                warnings,
+                format!("Ignoring `RUST_TEST_THREADS={threads}`, it must be a positive integer."),
+            ),
+        }
+    }
+
+    thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+/// Warns if `TESTIFY_RT_THREADS` is set to something that isn't a positive integer, in which case
+/// the async runtime uses its default number of worker threads.
+fn validate_rt_threads(warnings: &mut Vec<String>) {
+    if let Some(Err(threads)) = crate::rt_threads() {
+        warn(
+            warnings,
+            format!(
+                "Ignoring `{RT_THREADS_ENV_VAR_NAME}={threads}`, it must be a positive integer."
+            ),
+        );
+    }
+}
+
+/// The outcome of running a single test case.
+#[derive(Debug, Clone)]
+pub struct TestOutcome {
+    pub test: Test,
+    pub result: TestResult,
+    pub duration: Duration,
+    /// How many times the test was run. It's more than 1 if it was retried.
+    pub attempts: u32,
+}
+
+impl TestOutcome {
+    /// Whether the test passed, but only after being retried.
+    pub fn is_flaky(&self) -> bool {
+        self.result.status == TestStatus::Passed && self.attempts > 1
+    }
+}
+
+/// The results of a test run, in the order in which the tests were run.
+#[derive(Debug, Clone, Default)]
+pub struct TestReport {
+    pub outcomes: Vec<TestOutcome>,
+    /// The number of tests that were filtered out of the run.
+    pub skipped: usize,
+    /// The number of tests in modules ignored by `.testifyignore`.
+    pub ignored: usize,
+    /// The number of tests that passed the filters but didn't run, because `fail_fast` stopped the
+    /// run early or because a test they depend on didn't pass. The latter are also in `outcomes`,
+    /// with the `Skipped` status.
+    pub not_run: usize,
+    /// The tests counted in `ignored` and `not_run` that aren't in `outcomes`, with the `Skipped`
+    /// status and the reason they didn't run as their message, for the reports to list them.
+    pub unrun: Vec<TestOutcome>,
+    /// The warnings emitted during the run.
+    pub warnings: Vec<String>,
+    /// The IDs of the tests that weren't registered in the previous run, if `new_tests` is set.
+    pub new_tests: Vec<String>,
+    /// The tests that were slower than in the timings baseline, if `timings_baseline` is set.
+    pub regressions: Vec<TimingRegression>,
+    /// The panic message of the setup, if it panicked, or the cycle formed by the dependencies of
+    /// the tests, in which case no test was run.
+    pub setup_error: Option<String>,
+    /// The panic messages of the cleanups that panicked, one per line.
+    pub cleanup_error: Option<String>,
+    /// Whether no tests were registered. Unless the setup could register them with
+    /// `register_test!`, nothing ran then, not even the setup and cleanup.
+    pub no_tests: bool,
+    /// Whether the run was stopped early with Ctrl-C. The tests it didn't reach are counted in
+    /// `not_run`.
+    pub interrupted: bool,
+}
+
+impl TestReport {
+    /// The number of tests that passed, not counting the benchmarks.
+    pub fn successes(&self) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|outcome| {
+                outcome.result.status == TestStatus::Passed && outcome.test.kind == TestKind::Test
+            })
+            .count()
+    }
+
+    /// The number of tests that failed, including the benchmarks that panicked.
+    pub fn failures(&self) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|outcome| outcome.result.status.is_failure())
+            .count()
+    }
+
+    /// The number of tests that weren't run because a test they depend on didn't pass. They're
+    /// also counted in `not_run`.
+    pub fn skipped_dependents(&self) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|outcome| outcome.result.status == TestStatus::Skipped)
+            .count()
+    }
+
+    /// The number of benchmarks that ran all their iterations.
+    pub fn benchmarks(&self) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|outcome| outcome.result.bench.is_some())
+            .count()
+    }
+
+    /// The number of tests that passed only after being retried. They're also counted as
+    /// successes.
+    pub fn flaky(&self) -> usize {
+        self.outcomes.iter().filter(|outcome| outcome.is_flaky()).count()
+    }
+
+    /// Counts the passed and failed tests for each tag, sorted by tag. A test with several tags
+    /// is counted under each of them.
+    pub fn tag_summary(&self) -> BTreeMap<String, TagCounts> {
+        count_by(&self.outcomes, |test| test.tags.clone())
+    }
+
+    /// Counts the passed and failed tests for each source file, sorted by path.
+    pub fn file_summary(&self) -> BTreeMap<String, TagCounts> {
+        count_by(&self.outcomes, |test| vec![test.file.clone()])
+    }
+}
+
+/// Counts the passed and failed tests of `outcomes` under each of the keys `keys` gives them,
+/// sorted by key.
+pub(crate) fn count_by<'a>(
+    outcomes: impl IntoIterator<Item = &'a TestOutcome>,
+    keys: impl Fn(&Test) -> Vec<String>,
+) -> BTreeMap<String, TagCounts> {
+    let mut summary: BTreeMap<String, TagCounts> = BTreeMap::new();
+
+    for outcome in outcomes {
+        for key in keys(&outcome.test) {
+            let counts = summary.entry(key).or_default();
+
+            if outcome.result.status == TestStatus::Passed {
+                counts.passed += 1;
+            } else if outcome.result.status.is_failure() {
+                counts.failed += 1;
+            }
+        }
+    }
+
+    summary
+}
+
+/// The number of passed and failed tests with a given tag, or in a given file.
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TagCounts {
+    pub passed: usize,
+    pub failed: usize,
+}
+
+/// Prints a table with the number of passed and failed tests for each key of `summary`, such as
+/// each tag, under `title`. `column` is the header of the keys' column.
+fn print_counts_table(
+    title: &str,
+    column: &str,
+    summary: BTreeMap<String, TagCounts>,
+    theme: &OutputTheme,
+) {
+    let indent = theme.indent;
+
+    if summary.is_empty() {
+        return;
+    }
+
+    let width = summary
+        .keys()
+        .map(|key| key.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(column.chars().count());
+
+    outln!("\n{}", title.bold());
+    outln!(
+        "{:indent$}{:width$}  {:>6}  {:>6}",
+        "",
+        column.bold(),
+        "Passed".bold(),
+        "Failed".bold()
     );
 
-    if failures > 0 {
+    for (key, counts) in summary {
+        let failed = format!("{:>6}", counts.failed);
+
+        outln!(
+            "{:indent$}{key:width$}  {:>6}  {}",
+            "",
+            counts.passed,
+            if counts.failed > 0 { theme.failure(&failed) } else { failed.normal() }
+        );
+    }
+}
+
+/// Set when Ctrl-C is pressed during a run started by `run()`, to stop it before the next test.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Whether Ctrl-C was pressed, in which case no more tests should be started.
+pub(crate) fn interrupted() -> bool {
+    INTERRUPTED.load(atomic::Ordering::Relaxed)
+}
+
+/// Makes Ctrl-C stop the run before the next test instead of killing the process, so that the
+/// cleanups still run. Pressing it again exits right away, without cleaning up.
+fn handle_interrupts() {
+    let result = ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, atomic::Ordering::Relaxed) {
+            std::process::exit(INTERRUPTED_EXIT_CODE);
+        }
+
+        eprintln!(
+            "\n{}",
+            "Interrupted. Waiting for the running tests to clean up, press Ctrl-C again to exit now."
+                .yellow()
+        );
+    });
+
+    if let Err(e) = result {
+        eprintln!(
+            "{} Could not handle Ctrl-C, so interrupting the tests will skip the cleanup: {e}",
+            "Warning:".yellow()
+        );
+    }
+}
+
+/// The report of the latest run whose tests finished running, as returned by `last_report`.
+static LAST_REPORT: Mutex<Option<TestReport>> = Mutex::new(None);
+
+/// Returns the report of the latest run, which is available once its tests finished running, so
+/// that the `#[testify::cleanup]` functions can tell how the tests went. It's `None` before then,
+/// such as from the setup, the tests, or the tag- and suite-scoped cleanups of the first run.
+///
+/// The report holds the outcome of every test that ran, along with the number of skipped and
+/// ignored tests, the warnings and the setup's error, as in the report returned by
+/// `Runner::run`. Since it's taken before the cleanup, its `cleanup_error` is always `None`, and
+/// the timing regressions aren't filled in yet. There's one for the whole process, so with runs
+/// nested in tests, it's the report of the latest one whose tests finished.
+///
+/// ```ignore
+/// #[testify::cleanup]
+/// fn cleanup() {
+///     if testify::last_report().is_some_and(|report| report.failures() > 0) {
+///         println!("Keeping the test database for inspection.");
+///     } else {
+///         drop_test_database();
+///     }
+/// }
+/// ```
+pub fn last_report() -> Option<TestReport> {
+    LAST_REPORT.lock().unwrap().clone()
+}
+
+/// Runs the cleanup functions if it's dropped before the cleanup ran normally, which happens when a
+/// panic escapes the tests (for example, from the `Drop` implementation of a test's return value).
+struct CleanupGuard {
+    cleanups: Vec<fn()>,
+}
+
+impl CleanupGuard {
+    /// Takes the cleanup functions so that they're run normally instead of when dropped.
+    fn take(&mut self) -> Vec<fn()> {
+        std::mem::take(&mut self.cleanups)
+    }
+}
+
+impl Drop for CleanupGuard {
+    fn drop(&mut self) {
+        let cleanups = self.take();
+
+        if !cleanups.is_empty() {
+            outln!(
+                "\n{}",
+                "The test run stopped unexpectedly. Cleaning up...".red()
+            );
+
+            for cleanup in cleanups {
+                cleanup();
+            }
+        }
+    }
+}
+
+/// Runs a set of tests with a given configuration.
+///
+/// By default it runs the tests registered by the macros and `register_test!`, which is what `run()`
+/// does with the configuration passed by `cargo testify`, but it can also run tests built
+/// programmatically:
+///
+/// ```ignore
+/// let report = testify::Runner::new()
+///     .with_tests(tests)
+///     .with_config(TestifyConfig { fail_fast: true, ..Default::default() })
+///     .run();
+///
+/// assert_eq!(report.failures(), 0);
+/// ```
+#[derive(Default)]
+pub struct Runner {
+    /// The tests to run. If not set, the tests registered in `TESTS` are run, read after the setup
+    /// so that it can register tests at runtime.
+    tests: Option<Vec<Test>>,
+    config: TestifyConfig,
+    setups: Vec<fn()>,
+    cleanups: Vec<fn()>,
+    tag_setups: Vec<(Vec<String>, fn())>,
+    tag_cleanups: Vec<(Vec<String>, fn())>,
+    suite_setups: Vec<(String, fn())>,
+    suite_cleanups: Vec<(String, fn())>,
+    observers: Vec<Arc<dyn Observer>>,
+    /// Don't stop the services spawned with `spawn_service` after the run, because the setup that
+    /// spawned them isn't run again for the next one.
+    keep_services: bool,
+    /// Where the output is written. If not set, it's written to stdout.
+    output: Option<Output>,
+}
+
+/// A function scoped to the tests with some tags or in a suite, with the indices of the first and
+/// last of those tests in the run.
+struct Scoped {
+    first: usize,
+    last: usize,
+    function: fn(),
+}
+
+impl Scoped {
+    /// Finds the tests among `cases` that a function with `tags` applies to. Returns `None` if
+    /// there are none.
+    fn with_tags(cases: &[Test], tags: &[String], function: fn()) -> Option<Self> {
+        Self::new(cases, |case| case.tags.iter().any(|tag| tags.contains(tag)), function)
+    }
+
+    /// Finds the tests among `cases` that a function scoped to `suite` applies to. Returns `None`
+    /// if there are none.
+    fn with_suite(cases: &[Test], suite: &str, function: fn()) -> Option<Self> {
+        Self::new(cases, |case| case.suite.as_deref() == Some(suite), function)
+    }
+
+    fn new(cases: &[Test], applies: impl Fn(&Test) -> bool, function: fn()) -> Option<Self> {
+        Some(Self {
+            first: cases.iter().position(&applies)?,
+            last: cases.iter().rposition(applies)?,
+            function,
+        })
+    }
+}
+
+/// Sends the results of the run to the observers, and warns about the reports that couldn't be
+/// written.
+fn finish_run(
+    observers: &[Arc<dyn Observer>],
+    reporters: &[Arc<FileReporter>],
+    report: &mut TestReport,
+) {
+    for observer in observers {
+        observer.on_run_finished(report);
+    }
+
+    for reporter in reporters {
+        if let Some(message) = reporter.take_error() {
+            warn(&mut report.warnings, message);
+        }
+    }
+}
+
+/// Runs the scoped functions whose last test is the one before `case_i`, which was just run or
+/// skipped, and removes them from `scoped`.
+fn run_last_scoped_cleanups(scoped: &mut Vec<Scoped>, case_i: usize) {
+    scoped.retain(|cleanup| {
+        let is_last = cleanup.last + 1 == case_i;
+
+        if is_last {
+            (cleanup.function)();
+        }
+
+        !is_last
+    });
+}
+
+/// Returns a copy of `functions` if `condition` holds, or none of them otherwise.
+fn functions_if<T: Clone>(condition: bool, functions: &[T]) -> Vec<T> {
+    if condition {
+        functions.to_vec()
+    } else {
+        Vec::new()
+    }
+}
+
+/// Returns whether a run failed, which includes the reasons to fail other than failing tests that
+/// were enabled by `config`.
+fn report_failed(report: &TestReport, config: &TestifyConfig) -> bool {
+    report.failures() > 0
+        || !report.regressions.is_empty()
+        || report.setup_error.is_some()
+        || report.cleanup_error.is_some()
+        || (config.no_skips && report.ignored + report.not_run > 0)
+        || (config.error_on_empty && report.no_tests)
+        || report.interrupted
+        || (config.fail_on_warnings && !report.warnings.is_empty())
+}
+
+impl Runner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_tests(mut self, tests: Vec<Test>) -> Self {
+        self.tests = Some(tests);
+        self
+    }
+
+    pub fn with_config(mut self, config: TestifyConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Adds a function to run before the tests, after the ones added before it.
+    pub fn with_setup(mut self, setup: fn()) -> Self {
+        self.setups.push(setup);
+        self
+    }
+
+    /// Adds a function to run after the tests, after the ones added before it.
+    pub fn with_cleanup(mut self, cleanup: fn()) -> Self {
+        self.cleanups.push(cleanup);
+        self
+    }
+
+    /// Writes the output of the run to `output` instead of stdout, such as an `OutputBuffer` to
+    /// capture it. Only the runner's own output is redirected: what the tests, setups and
+    /// cleanups print, and the messages of a run that can't continue, still go to stdout and
+    /// stderr.
+    pub fn with_output(mut self, output: impl Write + Send + 'static) -> Self {
+        self.output = Some(Box::new(output));
+        self
+    }
+
+    /// Adds an observer to be notified of the tests' progress.
+    pub fn with_observer(mut self, observer: impl Observer + 'static) -> Self {
+        self.observers.push(Arc::new(observer));
+        self
+    }
+
+    /// Adds a function to run before the first test with any of `tags`.
+    pub fn with_tag_setup(mut self, tags: Vec<String>, setup: fn()) -> Self {
+        self.tag_setups.push((tags, setup));
+        self
+    }
+
+    /// Adds a function to run after the last test with any of `tags`.
+    pub fn with_tag_cleanup(mut self, tags: Vec<String>, cleanup: fn()) -> Self {
+        self.tag_cleanups.push((tags, cleanup));
+        self
+    }
+
+    /// Adds a function to run before the first test of `suite`, before its tag-scoped setups.
+    pub fn with_suite_setup(mut self, suite: String, setup: fn()) -> Self {
+        self.suite_setups.push((suite, setup));
+        self
+    }
+
+    /// Adds a function to run after the last test of `suite`, after its tag-scoped cleanups.
+    pub fn with_suite_cleanup(mut self, suite: String, cleanup: fn()) -> Self {
+        self.suite_cleanups.push((suite, cleanup));
+        self
+    }
+
+    /// Returns the tests `run` would run, grouped and sorted the way it would show them, without
+    /// running anything. Tests registered at runtime by the setup aren't included, since the setup
+    /// doesn't run.
+    ///
+    /// # Panics
+    ///
+    /// If any of the configured name filters or ignore patterns is not a valid glob pattern (or
+    /// regular expression, with `use_regex`).
+    pub fn plan(&self) -> Vec<PlannedGroup> {
+        let tests = self
+            .tests
+            .clone()
+            .unwrap_or_else(|| TESTS.lock().unwrap().clone());
+
+        self.plan_groups(&tests)
+            .iter()
+            .map(PlannedGroup::from)
+            .collect()
+    }
+
+    /// Filters, groups and sorts `tests` the way `run` would.
+    fn plan_groups(&self, tests: &[Test]) -> Vec<TestGroup> {
+        let config = &self.config;
+        let patterns =
+            compile_patterns(config).expect("A pattern passed to the name filter was invalid.");
+        let ignore_patterns = compile_ignore_patterns(config)
+            .expect("A pattern in the `.testifyignore` file was invalid.");
+
+        organize(tests, config, &patterns, &ignore_patterns)
+    }
+
+    /// Runs the tests, printing their progress to stdout (or the output set with `with_output`),
+    /// and returns their results. If the dependencies of the tests to run form a cycle, none of
+    /// them runs and the cycle is reported as the `setup_error`. With `repeat_until_failure`, the
+    /// tests are run several times, and the results of the last iteration are returned.
+    ///
+    /// A run can be nested in a test of another run, but it isn't reentrant: until it finishes, it
+    /// replaces the process's panic hook, and it sets the report returned by `last_report`. Runs
+    /// started from tests that run in parallel have to take turns, such as by holding a shared
+    /// lock while they run, or they'd restore each other's panic hooks and see each other's
+    /// reports.
+    ///
+    /// # Panics
+    ///
+    /// If any of the configured name filters or ignore patterns is not a valid glob pattern (or
+    /// regular expression, with `use_regex`).
+    pub fn run(self) -> TestReport {
+        match self.config.repeat_until_failure {
+            Some(iterations) => self.run_repeated(iterations),
+            None => self.run_once(),
+        }
+    }
+
+    /// Runs the tests `iterations` times, or forever if it's 0, stopping at the first iteration
+    /// that fails. Without `repeat_setup`, the setups run with the first iteration, including the
+    /// ones scoped to tags and suites, and the cleanups after the last one. Otherwise, every
+    /// iteration is a run of its own, and the tests registered by the setup are discarded before
+    /// it runs again.
+    fn run_repeated(mut self, iterations: u32) -> TestReport {
+        let output = SharedOutput(Arc::new(Mutex::new(self.output.take())));
+        let _output_guard = OutputGuard::new(Some(Box::new(output.clone())));
+        let config = self.config.clone();
+        let every_iteration = config.repeat_setup;
+        let registered_tests = TESTS.lock().unwrap().clone();
+
+        // The cleanup still runs if a panic escapes one of the iterations, same as in a single run.
+        let mut cleanup_guard = CleanupGuard {
+            cleanups: if every_iteration || config.no_cleanup {
+                Vec::new()
+            } else {
+                self.cleanups.clone()
+            },
+        };
+
+        let mut iteration = 1;
+
+        let mut report = loop {
+            let title = if iterations == 0 {
+                format!("Iteration {iteration}")
+            } else {
+                format!("Iteration {iteration} of {iterations}")
+            };
+
+            outln!("{}{}\n", if iteration == 1 { "" } else { "\n" }, title.bold());
+
+            if every_iteration {
+                *TESTS.lock().unwrap() = registered_tests.clone();
+            }
+
+            let sets_up = every_iteration || iteration == 1;
+            let report = Runner {
+                tests: self.tests.clone(),
+                config: config.clone(),
+                setups: functions_if(sets_up, &self.setups),
+                cleanups: functions_if(every_iteration, &self.cleanups),
+                tag_setups: functions_if(sets_up, &self.tag_setups),
+                tag_cleanups: functions_if(every_iteration, &self.tag_cleanups),
+                suite_setups: functions_if(sets_up, &self.suite_setups),
+                suite_cleanups: functions_if(every_iteration, &self.suite_cleanups),
+                observers: self.observers.clone(),
+                keep_services: !every_iteration,
+                output: Some(Box::new(output.clone())),
+            }
+            .run_once();
+
+            // The scoped cleanups are the ones of the tests the first iteration planned to run,
+            // which is none of them if its setup failed.
+            let defers_cleanups = !every_iteration && !config.no_cleanup;
+
+            if iteration == 1 && defers_cleanups && report.setup_error.is_none() {
+                let mut cleanups = self.scoped_cleanups();
+                cleanups.append(&mut cleanup_guard.cleanups);
+                cleanup_guard.cleanups = cleanups;
+            }
+
+            if report_failed(&report, &config) || (iterations != 0 && iteration == iterations) {
+                break report;
+            }
+
+            iteration += 1;
+        };
+
+        let failed = report_failed(&report, &config);
+        let cleanups = cleanup_guard.take();
+
+        if !cleanups.is_empty() {
+            let error = run_cleanups("\nCleaning up...", cleanups, &config.theme);
+            report.cleanup_error = report.cleanup_error.take().or(error);
+        }
+
+        crate::shutdown_services();
+
+        if failed {
+            outln!(
+                "{}",
+                config
+                    .theme
+                    .failure(&format!("\nFailed on iteration {iteration}."))
+                    .bold()
+            );
+        } else {
+            outln!(
+                "{}",
+                config
+                    .theme
+                    .success(&format!("\nAll {iterations} iterations passed."))
+                    .bold()
+            );
+        }
+
+        report
+    }
+
+    /// Returns the cleanups scoped to the tags and suites of the tests that would run, in the order
+    /// they'd run in.
+    fn scoped_cleanups(&self) -> Vec<fn()> {
+        let tests = self
+            .tests
+            .clone()
+            .unwrap_or_else(|| TESTS.lock().unwrap().clone());
+        let cases: Vec<Test> = self
+            .plan_groups(&tests)
+            .iter()
+            .flat_map(|group| &group.test_plans)
+            .flat_map(|plan| plan.cases.iter().cloned())
+            .collect();
+
+        let tag_cleanups = self
+            .tag_cleanups
+            .iter()
+            .filter_map(|(tags, cleanup)| Scoped::with_tags(&cases, tags, *cleanup));
+        let suite_cleanups = self
+            .suite_cleanups
+            .iter()
+            .filter_map(|(suite, cleanup)| Scoped::with_suite(&cases, suite, *cleanup));
+
+        tag_cleanups
+            .chain(suite_cleanups)
+            .map(|cleanup| cleanup.function)
+            .collect()
+    }
+
+    fn run_once(mut self) -> TestReport {
+        let config = &self.config;
+        let human_report = config
+            .reports
+            .iter()
+            .any(|(kind, _)| *kind == ReportKind::Human)
+            .then(OutputBuffer::default);
+        let output = match &human_report {
+            Some(copy) => Some(Box::new(TeeOutput {
+                output: self.output.take(),
+                copy: copy.clone(),
+            }) as Output),
+            None => self.output.take(),
+        };
+        let _output_guard = OutputGuard::new(output);
+
+        // The reporters are observers like any other, and are kept to report their errors.
+        let reporters: Vec<Arc<FileReporter>> = config
+            .reports
+            .iter()
+            .map(|(kind, path)| {
+                Arc::new(FileReporter::new(
+                    *kind,
+                    path.clone(),
+                    human_report.clone(),
+                    config.compact_json,
+                ))
+            })
+            .collect();
+        self.observers
+            .extend(reporters.iter().map(|reporter| reporter.clone() as Arc<dyn Observer>));
+
+        let theme = &config.theme;
+        let indent = theme.indent;
+
+        outln!("{}Testify! Running tests...\n", theme.start);
+        let mut step = 1;
+        let mut warnings = Vec::new();
+
+        // Without tests, the setup and cleanup would only have side effects, which may be costly.
+        // Unless the tests were given, the setup may register them with `register_test!` though.
+        let registered = self
+            .tests
+            .as_ref()
+            .map_or_else(|| TESTS.lock().unwrap().len(), Vec::len);
+        let may_register = self.tests.is_none() && test::registers_at_runtime();
+
+        if registered == 0 && !may_register {
+            outln!(
+                "{}",
+                "No tests are registered, so the setup and cleanup were skipped.".yellow()
+            );
+
+            if config.error_on_empty {
+                outln!("{}", theme.failure("Failing because of `--error-on-empty`."));
+            }
+
+            let mut report = TestReport {
+                warnings,
+                no_tests: true,
+                ..TestReport::default()
+            };
+            finish_run(&self.observers, &reporters, &mut report);
+
+            return report;
+        }
+
+        if config.no_setup
+            && (!self.setups.is_empty()
+                || !self.tag_setups.is_empty()
+                || !self.suite_setups.is_empty())
+        {
+            warn(
+                &mut warnings,
+                "Skipping the setup because of `--no-setup`. Tests that depend on it may fail."
+                    .to_string(),
+            );
+        }
+
+        if config.no_cleanup
+            && (!self.cleanups.is_empty()
+                || !self.tag_cleanups.is_empty()
+                || !self.suite_cleanups.is_empty())
+        {
+            warn(
+                &mut warnings,
+                "Skipping the cleanup because of `--no-cleanup`.".to_string(),
+            );
+        }
+
+        // Panic messages are taken from the panics' payloads, so the default hook's output is
+        // silenced, including for the setup and cleanup. The backtrace has to be captured here,
+        // while the panicking stack still exists.
+        let capture_backtrace = config.backtrace;
+        // Restored after the run, for runs embedded in another program or nested in a test, even
+        // if the run panics.
+        let _previous_hook = PanicHookGuard(Some(panic::take_hook()));
+
+        panic::set_hook(Box::new(move |info| {
+            if capture_backtrace {
+                test::capture_panic_backtrace();
+            }
+
+            crate::call_chained_panic_hooks(info);
+
+            if cfg!(panic = "abort") {
+                eprintln!(
+                    "{}",
+                    format!(
+                        "A test panicked, and panics abort the whole run because the binary was built with `panic = \"abort\"`: {info}\nPass `--isolate` to run each test in its own process instead."
+                    )
+                    .red()
+                );
+            }
+        }));
+
+        let mut setup_error = None;
+
+        if !config.no_setup && !self.setups.is_empty() {
+            out!("{step}. Starting up...");
+            flush();
+            step += 1;
+
+            // Async setups block on the runtime, so their duration includes the whole future. The
+            // setups after one that panics depend on it, so they aren't run.
+            let (result, duration) = exec_with_timing(|| {
+                self.setups.iter().try_for_each(|setup| catch_panic(*setup))
+            });
+
+            match result {
+                Ok(()) => outln!(
+                    " {} {}",
+                    theme.ok(),
+                    format!("({})", format_duration(duration, theme)).dimmed()
+                ),
+                Err(message) => {
+                    outln!(" {}", theme.failed());
+                    print_message(&message, indent);
+                    setup_error = Some(message);
+                }
+            }
+        }
+
+        let mut cleanup_guard = CleanupGuard {
+            cleanups: if config.no_cleanup {
+                Vec::new()
+            } else {
+                self.cleanups.clone()
+            },
+        };
+
+        let patterns =
+            compile_patterns(config).expect("A pattern passed to the name filter was invalid.");
+        let ignore_patterns = compile_ignore_patterns(config)
+            .expect("A pattern in the `.testifyignore` file was invalid.");
+
+        let tests = self
+            .tests
+            .unwrap_or_else(|| TESTS.lock().unwrap().clone());
+
+        validate_tags(&tests, config, &mut warnings);
+        validate_rerun(&tests, config, &mut warnings);
+        validate_dependencies(&tests, &mut warnings);
+        let jobs = job_count(config, &mut warnings);
+        validate_rt_threads(&mut warnings);
+        let format = output_format(config);
+
+        let new_tests = if config.new_tests {
+            find_new_tests(&tests, config, &mut warnings)
+        } else {
+            Vec::new()
+        };
+
+        let groups = organize(&tests, config, &patterns, &ignore_patterns);
+        let planned: Vec<Test> = groups
+            .iter()
+            .flat_map(|group| &group.test_plans)
+            .flat_map(|plan| plan.cases.iter().cloned())
+            .collect();
+        let cycle_error = dependencies::find_cycle(&planned)
+            .map(|cycle| dependencies::cycle_error(&planned, &cycle));
+        let ignored = tests
+            .iter()
+            .filter(|test| is_ignored(test, &ignore_patterns))
+            .count();
+
+        let tests_to_run = groups.iter().fold(0, |prev, group| {
+            prev + group
+                .test_plans
+                .iter()
+                .fold(0, |gprev, test_plan| gprev + test_plan.cases.len())
+        });
+
+        let setup_failed = setup_error.is_some();
+        let mut report = TestReport {
+            outcomes: Vec::with_capacity(tests_to_run),
+            skipped: tests.len() - tests_to_run - ignored,
+            ignored,
+            not_run: 0,
+            unrun: Vec::new(),
+            warnings,
+            new_tests,
+            regressions: Vec::new(),
+            setup_error: setup_error.or_else(|| cycle_error.clone()),
+            cleanup_error: None,
+            no_tests: tests.is_empty(),
+            interrupted: false,
+        };
+
+        // Without the setup, the tests would fail for reasons unrelated to them.
+        let groups = if setup_failed {
+            outln!(
+                "{step}. {}",
+                theme.failure(&format!(
+                    "Skipping {} because the setup failed.",
+                    pluralize(tests_to_run, "test")
+                ))
+            );
+            Vec::new()
+        } else if let Some(message) = cycle_error {
+            outln!(
+                "{step}. {}",
+                theme.failure(&format!(
+                    "Skipping {} because their dependencies form a cycle.",
+                    pluralize(tests_to_run, "test")
+                ))
+            );
+            print_message(&message, indent);
+            Vec::new()
+        } else {
+            outln!(
+                "{step}. Running {} {}...",
+                pluralize(tests_to_run, "test"),
+                if report.ignored > 0 {
+                    format!("({} skipped, {} ignored)", report.skipped, report.ignored)
+                } else {
+                    format!("({} skipped)", report.skipped)
+                }
+                .black()
+            );
+
+            if config.dots {
+                outln!("{:indent$}{}", "", DOTS_LEGEND.dimmed());
+                out!("{:indent$}", "");
+            }
+
+            groups
+        };
+        step += 1;
+
+        let cases: Vec<Test> = groups
+            .iter()
+            .flat_map(|group| &group.test_plans)
+            .flat_map(|plan| plan.cases.iter().cloned())
+            .collect();
+        let output_dir = config
+            .output_dir
+            .clone()
+            .unwrap_or_else(artifacts::default_output_dir);
+
+        // Suite setups run before the tag setups of the same test, and suite cleanups after the tag
+        // cleanups, so that the narrower fixtures can rely on the wider ones.
+        let suite_setups = self
+            .suite_setups
+            .iter()
+            .filter_map(|(suite, setup)| Scoped::with_suite(&cases, suite, *setup));
+        let tag_setups = self
+            .tag_setups
+            .iter()
+            .filter_map(|(tags, setup)| Scoped::with_tags(&cases, tags, *setup));
+        let scoped_setups: Vec<Scoped> = suite_setups
+            .chain(tag_setups)
+            .filter(|_| !config.no_setup)
+            .collect();
+
+        let tag_cleanups = self
+            .tag_cleanups
+            .iter()
+            .filter_map(|(tags, cleanup)| Scoped::with_tags(&cases, tags, *cleanup));
+        let suite_cleanups = self
+            .suite_cleanups
+            .iter()
+            .filter_map(|(suite, cleanup)| Scoped::with_suite(&cases, suite, *cleanup));
+        let mut scoped_cleanups: Vec<Scoped> = tag_cleanups
+            .chain(suite_cleanups)
+            .filter(|_| !config.no_cleanup)
+            .collect();
+
+        // Worker threads may start any case right away, so the scoped setups can't wait for their
+        // first test to be reached.
+        let is_parallel = Executor::is_parallel(cases.len(), jobs);
+
+        if is_parallel {
+            for setup in &scoped_setups {
+                (setup.function)();
+            }
+        }
+
+        let case_dependencies = dependencies::indices(&cases);
+        let mut executor = Executor::new(
+            cases,
+            case_dependencies,
+            jobs,
+            CaseRunner {
+                output_dir: output_dir.clone(),
+                retries: config.retries,
+                isolate: config.isolate,
+                fresh_runtime: config.fresh_runtime,
+                timeout: config.timeout,
+                tag_timeouts: config.tag_timeouts.clone(),
+                observers: self.observers.clone(),
+            },
+            config.parallel_by,
+            config.fail_fast,
+        );
+
+        let mut test_i = 1;
+        let mut case_i = 0;
+
+        'groups_loop: for (group_i, group) in groups.iter().enumerate() {
+            let is_new_suite = group_i == 0 || groups[group_i - 1].suite != group.suite;
+
+            if let Some(suite) = group.suite.as_ref().filter(|_| is_new_suite && !config.dots) {
+                outln!(
+                    "{}{:indent$}{}",
+                    if group_i == 0 { "" } else { "\n" },
+                    "",
+                    format!("{rule} {suite} {rule}", rule = theme.suite_rule).bold()
+                );
+            }
+
+            if let Some(tags) = group.tags.as_ref().filter(|_| !config.dots) {
+                outln!(
+                    "{}{:indent$}{}",
+                    if group_i == 0 || (is_new_suite && group.suite.is_some()) {
+                        ""
+                    } else {
+                        "\n"
+                    },
+                    "",
+                    format!(
+                        "{rule} {} {rule}",
+                        if tags.is_empty() {
+                            "No tags".to_string()
+                        } else {
+                            tags.join(", ")
+                        },
+                        rule = theme.group_rule
+                    )
+                    .black()
+                );
+            }
+
+            for plan in &group.test_plans {
+                if interrupted() {
+                    report.interrupted = true;
+                    break 'groups_loop;
+                }
+
+                let is_single_case = plan.cases.len() == 1;
+
+                if config.dots {
+                    // The test's result is its only output.
+                } else if is_single_case {
+                    out!("{:indent$}{test_i}. {}...", "", plan.name);
+                } else {
+                    outln!("{:indent$}{test_i}. {}...", "", plan.name);
+                }
+
+                let mut open_cases: Vec<OpenCase> = Vec::new();
+
+                let show_cases = !is_single_case && !config.dots;
+
+                for step in plan.case_steps() {
+                    let (name, case) = match step {
+                        CaseStep::Open(name) => {
+                            if show_cases {
+                                outln!(
+                                    "{:indent$}{} {}{}",
+                                    "",
+                                    "Case".black(),
+                                    name,
+                                    "...".dimmed(),
+                                    indent = indent * (open_cases.len() + 2)
+                                );
+                            }
+                            open_cases.push(OpenCase {
+                                passed: 0,
+                                total: 0,
+                            });
+                            continue;
+                        }
+                        CaseStep::Close => {
+                            close_case(&mut open_cases, show_cases, theme);
+                            continue;
+                        }
+                        CaseStep::Run(name, case) => (name, case),
+                    };
+
+                    // Messages are indented one level deeper than the line they're under.
+                    let mut message_indent = indent * 2;
+
+                    if show_cases {
+                        out!(
+                            "{:indent$}{} {}{}",
+                            "",
+                            "Case".black(),
+                            name,
+                            "...".dimmed(),
+                            indent = indent * (open_cases.len() + 2)
+                        );
+                        message_indent = indent * (open_cases.len() + 3);
+                    }
+                    flush();
+
+                    if !is_parallel {
+                        for setup in scoped_setups.iter().filter(|setup| setup.first == case_i) {
+                            (setup.function)();
+                        }
+                    }
+
+                    let Some((result, duration, attempts)) = executor.result(case_i, case) else {
+                        case_i += 1;
+
+                        for open_case in &mut open_cases {
+                            open_case.total += 1;
+                        }
+
+                        let message = format!(
+                            "It depends on {}, which didn't pass.",
+                            case.depends_on
+                                .iter()
+                                .map(|name| format!("`{name}`"))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        );
+
+                        if config.dots {
+                            out!("{}", dot(TestStatus::Skipped, theme));
+
+                            if case_i % DOTS_PER_LINE == 0 {
+                                out!("\n{:indent$}", "");
+                            }
+                        } else {
+                            outln!(" {}", theme.skipped("Skipped."));
+                            print_message(&message, message_indent);
+                        }
+
+                        let outcome = skipped_outcome(case, message);
+
+                        for observer in &self.observers {
+                            observer.on_test_finished(&outcome);
+                        }
+
+                        report.outcomes.push(outcome);
+                        run_last_scoped_cleanups(&mut scoped_cleanups, case_i);
+                        continue;
+                    };
+                    let result = check_time_bounds(case, result, duration, theme);
+                    let mut result = check_no_captured_output(case, result);
+
+                    if config.strict_flaky {
+                        result = fail_flaky(result, attempts);
+                    }
+                    case_i += 1;
+
+                    let passed = result.status == TestStatus::Passed;
+                    let verbose = if passed || !config.verbose_failures {
+                        config.verbose
+                    } else {
+                        config.verbose.max(2)
+                    };
+                    let description = case.description.as_deref().filter(|_| verbose > 0);
+                    let path = Some(case.path()).filter(|_| verbose > 0);
+
+                    for open_case in &mut open_cases {
+                        open_case.total += 1;
+                        open_case.passed += usize::from(passed);
+                    }
+
+                    if config.dots {
+                        out!("{}", dot(result.status, theme));
+
+                        if case_i % DOTS_PER_LINE == 0 {
+                            out!("\n{:indent$}", "");
+                        }
+                    } else if let Some(stats) = &result.bench {
+                        outln!(" {}", format_bench_stats(stats, theme));
+
+                        if let Some(path) = &path {
+                            print_message(&format!("Path: {path}"), message_indent);
+                        }
+
+                        if let Some(description) = description {
+                            print_message(description, message_indent);
+                        }
+                    } else if passed {
+                        out!(
+                            " {} {}",
+                            theme.ok(),
+                            format!("({})", format_duration(duration, theme)).dimmed()
+                        );
+
+                        if attempts > 1 {
+                            out!(" {}", format!("(flaky, {attempts} attempts)").yellow());
+                        }
+
+                        if case.slow {
+                            out!(" {}", "(slow)".dimmed());
+                        }
+
+                        if let Some(detail) = &result.detail {
+                            out!(" - {}", detail.replace('\n', " "));
+                        }
+
+                        // Passing tests only have a message if they were expected to fail. Tests
+                        // that fail without one are explained by their `should_fail` description.
+                        let expected_failure = result.message.as_deref().or(case
+                            .should_fail
+                            .as_deref()
+                            .filter(|description| !description.is_empty()));
+
+                        match expected_failure {
+                            Some(message) if config.verbose > 0 => outln!(
+                                " {}",
+                                format!("(expected failure: {})", message.replace('\n', " "))
+                                    .dimmed()
+                            ),
+                            _ => outln!(),
+                        }
+
+                        if let Some(path) = &path {
+                            print_message(&format!("Path: {path}"), message_indent);
+                        }
+
+                        if let Some(description) = description {
+                            print_message(description, message_indent);
+                        }
+                    } else {
+                        out!(" {}", theme.failed());
+
+                        if case.slow {
+                            out!(" {}", "(slow)".dimmed());
+                        }
+
+                        if config.fail_fast {
+                            out!(" {}", theme.failure("Aborted."));
+                        }
+
+                        outln!();
+
+                        if let Some(path) = &path {
+                            print_message(&format!("Path: {path}"), message_indent);
+                        }
+
+                        if let Some(description) = description {
+                            print_message(description, message_indent);
+                        }
+
+                        if let Some(message) = &result.message {
+                            print_message(message, message_indent);
+                        }
+
+                        if let Some(value) = result.value.as_ref().filter(|_| verbose > 1) {
+                            print_message(&format!("Returned: {value}"), message_indent);
+                        }
+
+                        if let Some(backtrace) = &result.backtrace {
+                            print_message(backtrace, message_indent);
+                        }
+
+                        if let Some(output) = &result.output {
+                            print_message("Output:", message_indent);
+                            print_message(output, message_indent + indent);
+                        }
+
+                        let artifact_dir = artifacts::case_dir(&output_dir, case);
+
+                        if artifact_dir.exists() {
+                            print_message(
+                                &format!("Artifacts: {}", artifact_dir.display()),
+                                message_indent,
+                            );
+                        }
+
+                        if format == OutputFormat::Github {
+                            print_github_annotation(case, &result);
+                        }
+                    }
+
+                    let outcome = TestOutcome {
+                        test: case.clone(),
+                        result,
+                        duration,
+                        attempts,
+                    };
+
+                    for observer in &self.observers {
+                        observer.on_test_finished(&outcome);
+                    }
+
+                    report.outcomes.push(outcome);
+                    run_last_scoped_cleanups(&mut scoped_cleanups, case_i);
+
+                    if !passed && config.fail_fast {
+                        break 'groups_loop;
+                    }
+                }
+
+                test_i += 1;
+            }
+        }
+
+        executor.finish();
+        report.not_run = tests_to_run - report.outcomes.len() + report.skipped_dependents();
+
+        // The cases run in order, so the ones that weren't reached are the last ones.
+        let not_reached = if report.setup_error.is_some() {
+            "No test was run because of the setup error."
+        } else {
+            "The run stopped before reaching it."
+        };
+        report.unrun = tests
+            .iter()
+            .filter(|test| is_ignored(test, &ignore_patterns))
+            .map(|test| {
+                skipped_outcome(test, "Its module is ignored by `.testifyignore`.".to_string())
+            })
+            .chain(
+                planned[report.outcomes.len()..]
+                    .iter()
+                    .map(|test| skipped_outcome(test, not_reached.to_string())),
+            )
+            .collect();
+
+        if config.dots && !groups.is_empty() {
+            for i in 1..=report.ignored + report.not_run - report.skipped_dependents() {
+                out!("{}", dot(TestStatus::Skipped, theme));
+
+                if (case_i + i) % DOTS_PER_LINE == 0 {
+                    out!("\n{:indent$}", "");
+                }
+            }
+
+            outln!();
+
+            // The annotations would break the line of dots, so they're printed after it.
+            if format == OutputFormat::Github {
+                for outcome in &report.outcomes {
+                    if outcome.result.status.is_failure() {
+                        print_github_annotation(&outcome.test, &outcome.result);
+                    }
+                }
+            }
+        }
+
+        if report.interrupted {
+            outln!("\n{}", theme.failure("The run was interrupted."));
+        }
+
+        // The run was stopped early, so some scoped cleanups weren't reached. Only the ones whose
+        // tests started running are needed, which may be any of them when running in parallel.
+        for cleanup in scoped_cleanups {
+            if is_parallel || cleanup.first < case_i {
+                (cleanup.function)();
+            }
+        }
+
+        *LAST_REPORT.lock().unwrap() = Some(report.clone());
+        let cleanups = cleanup_guard.take();
+
+        if !cleanups.is_empty() {
+            let label = format!("{}{step}. Cleaning up...", if groups.len() > 1 { "\n" } else { "" });
+            report.cleanup_error = run_cleanups(&label, cleanups, theme);
+        }
+
+        if !self.keep_services {
+            crate::shutdown_services();
+        }
+
+        // The annotations already point at every failure, so the recap would only repeat them.
+        if report.failures() > 0 && format != OutputFormat::Github {
+            print_failures_recap(&report, theme);
+        }
+
+        if report.flaky() > 0 {
+            print_flaky_recap(&report, theme);
+        }
+
+        if let Some(threshold) = config.warn_slow {
+            warn_slow(&report.outcomes, threshold, theme, &mut report.warnings);
+        }
+
+        // The baseline is read before the timings are saved, so both can be the same file.
+        if let Some(path) = &config.timings_baseline {
+            match timings::load(path) {
+                Ok(baseline) => {
+                    report.regressions = timings::find_regressions(
+                        &report.outcomes,
+                        &baseline,
+                        config.regression_threshold,
+                    );
+                }
+                Err(message) => warn(&mut report.warnings, message),
+            }
+        }
+
+        if let Some(path) = &config.save_timings
+            && let Err(message) = timings::save(path, &report.outcomes)
+        {
+            warn(&mut report.warnings, message);
+        }
+
+        if let Some(path) = &config.fold_timings
+            && let Err(message) = timings::save_folded(path, &report.outcomes, config.group_by)
+        {
+            warn(&mut report.warnings, message);
+        }
+
+        if let Some(path) = &config.last_run_file
+            && let Err(message) = last_run::save(path, &tests, &report.outcomes)
+        {
+            warn(&mut report.warnings, message);
+        }
+
+        if let Some(baseline) = &config.changed_only {
+            let path = config
+                .test_hashes_file
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(changes::DEFAULT_TEST_HASHES_FILE));
+
+            if let Err(message) = changes::save(&path, baseline, &report.outcomes) {
+                warn(&mut report.warnings, message);
+            }
+        }
+
+        if let Some(path) = &config.metrics
+            && let Err(message) = metrics::save(path, &report)
+        {
+            warn(&mut report.warnings, message);
+        }
+
+        if !report.regressions.is_empty() {
+            print_regressions_recap(&report.regressions, config.regression_threshold, theme);
+        }
+
+        if config.tag_summary {
+            print_counts_table(
+                &format!("{}Tags:", theme.tags),
+                "Tag",
+                report.tag_summary(),
+                theme,
+            );
+        }
+
+        if config.file_summary {
+            print_counts_table(
+                &format!("{}Files:", theme.files),
+                "File",
+                report.file_summary(),
+                theme,
+            );
+        }
+
+        outln!(
+            "\n{}Finished running tests. {} and {}{}.",
+            theme.finish,
+            if report.failures() > 0 {
+                theme.failure(&format!("{} failed", pluralize(report.failures(), "test")))
+            } else {
+                format!("{} failed", pluralize(0, "test")).dimmed()
+            },
+            if report.successes() > 0 {
+                theme
+                    .success(&format!("{} succeeded", pluralize(report.successes(), "test")))
+                    .bold()
+            } else {
+                format!("{} succeeded", pluralize(0, "test")).dimmed()
+            },
+            if report.flaky() > 0 {
+                format!(" ({} flaky)", report.flaky()).yellow()
+            } else {
+                "".normal()
+            }
+        );
+
+        if report.benchmarks() > 0 {
+            outln!(
+                "{}",
+                format!("{} measured.", pluralize(report.benchmarks(), "benchmark")).dimmed()
+            );
+        }
+
+        if !report.new_tests.is_empty() {
+            print_new_tests(&report.new_tests, theme);
+        }
+
+        if !report.warnings.is_empty() {
+            outln!(
+                "{}{}",
+                if report.warnings.len() == 1 {
+                    format!("{}1 warning was emitted.", theme.warnings)
+                } else {
+                    format!("{}{} warnings were emitted.", theme.warnings, report.warnings.len())
+                }
+                .yellow(),
+                if config.fail_on_warnings {
+                    theme.failure(" Failing because of `--fail-on-warnings`.")
+                } else {
+                    "".normal()
+                }
+            );
+        }
+
+        if config.no_skips && report.ignored + report.not_run > 0 {
+            outln!(
+                "{}",
+                theme.failure(&format!(
+                    "{} ignored and {} not run. Failing because of `--no-skips`.",
+                    pluralize(report.ignored, "test"),
+                    report.not_run
+                ))
+            );
+        }
+
+        finish_run(&self.observers, &reporters, &mut report);
+
+        report
+    }
+}
+
+/// Sets the panic hook it holds back when it's dropped, which restores the hook that was set
+/// before a run when the run ends or unwinds.
+struct PanicHookGuard(Option<PanicHook>);
+
+impl Drop for PanicHookGuard {
+    fn drop(&mut self) {
+        let Some(hook) = self.0.take() else {
+            return;
+        };
+
+        if thread::panicking() {
+            // The panic hook can't be set from a panicking thread, but it can from another one.
+            let _ = thread::spawn(move || panic::set_hook(hook)).join();
+        } else {
+            panic::set_hook(hook);
+        }
+    }
+}
+
+/// A name filter, compiled as a glob pattern or, with `use_regex`, as a regular expression.
+enum NamePattern {
+    Glob(glob::Pattern),
+    Regex(regex::Regex),
+}
+
+impl NamePattern {
+    /// Whether the glob pattern matches the whole name, or the regular expression any part of it.
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            Self::Glob(pattern) => pattern.matches(name),
+            Self::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+fn compile_patterns(config: &TestifyConfig) -> Result<Vec<NamePattern>, String> {
+    if config.exact {
+        return Ok(Vec::new());
+    }
+
+    config
+        .name_filters
+        .iter()
+        .map(|pattern| {
+            if config.use_regex {
+                regex::Regex::new(pattern)
+                    .map(NamePattern::Regex)
+                    .map_err(|e| e.to_string())
+            } else {
+                glob::Pattern::new(pattern)
+                    .map(NamePattern::Glob)
+                    .map_err(|e| e.to_string())
+            }
+        })
+        .collect()
+}
+
+/// Takes the setups or cleanups registered by the macros, sorted by their order. The ones with the
+/// same order are kept in the order they were registered in, which is unspecified.
+fn take_sorted(registry: &Mutex<Vec<OrderedFn>>) -> Vec<fn()> {
+    let mut functions = std::mem::take(&mut *registry.lock().unwrap());
+    functions.sort_by_key(|(order, _)| *order);
+
+    functions.into_iter().map(|(_, function)| function).collect()
+}
+
+/// Takes the tag-scoped or suite-scoped setups or cleanups registered by the macros, sorted by their
+/// order, along with their tags or suite.
+fn take_sorted_scoped<S>(registry: &Mutex<Vec<ScopedFn<S>>>) -> Vec<(S, fn())> {
+    let mut functions = std::mem::take(&mut *registry.lock().unwrap());
+    functions.sort_by_key(|(order, _, _)| *order);
+
+    functions.into_iter().map(|(_, scope, function)| (scope, function)).collect()
+}
+
+/// The exit code of the tests' binary when the configuration passed by `cargo testify` is missing
+/// or can't be parsed, to tell it apart from failing tests.
+pub const CONFIG_ERROR_EXIT_CODE: i32 = 2;
+
+/// The exit code of the tests' binary when Ctrl-C is pressed twice, exiting without cleaning up.
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// Returns the registered tests `run()` would run, grouped and sorted the way it would show them,
+/// without running anything. It's meant for tools that show the structure of the suite, such as
+/// editor plugins, and the result can be serialized with serde.
+///
+/// The tests are filtered with the configuration passed by `cargo testify`, or, when the binary is
+/// run some other way, with the default configuration, which selects every test.
+///
+/// ```ignore
+/// for group in testify::plan() {
+///     println!("{}", serde_json::to_string(&group).unwrap());
+/// }
+/// ```
+pub fn plan() -> Vec<PlannedGroup> {
+    let config = std::env::var(TEST_RUNNER_CONFIG)
+        .map(|_| load_config())
+        .unwrap_or_default();
+
+    Runner::new().with_config(config).plan()
+}
+
+/// Reads the configuration passed by `cargo testify`, exiting with `CONFIG_ERROR_EXIT_CODE` and an
+/// explanation if it's missing or invalid.
+fn load_config() -> TestifyConfig {
+    let config = match std::env::var(TEST_RUNNER_CONFIG) {
+        Ok(config) => config,
+        Err(std::env::VarError::NotPresent) => {
+            eprintln!(
+                "The testify configuration was not found. Run the tests with `cargo testify` instead of running the binary directly."
+            );
+            std::process::exit(CONFIG_ERROR_EXIT_CODE);
+        }
+        Err(std::env::VarError::NotUnicode(_)) => {
+            eprintln!(
+                "The testify configuration is not valid UTF-8. Run the tests with `cargo testify` instead of setting it manually."
+            );
+            std::process::exit(CONFIG_ERROR_EXIT_CODE);
+        }
+    };
+
+    serde_json::from_str(&config).unwrap_or_else(|e| {
+        eprintln!(
+            "Could not parse the testify configuration: {e}. Run the tests with `cargo testify`, and make sure it's the same version as the `testify-rs` dependency."
+        );
+        std::process::exit(CONFIG_ERROR_EXIT_CODE);
+    })
+}
+
+fn compile_ignore_patterns(
+    config: &TestifyConfig,
+) -> Result<Vec<glob::Pattern>, glob::PatternError> {
+    config
+        .ignore_patterns
+        .iter()
+        .map(|pattern| glob::Pattern::new(pattern))
+        .collect()
+}
+
+/// Runs the tests registered by the macros, with the configuration passed by `cargo testify`, and
+/// exits with an error code if any of them failed. If the binary wasn't run by `cargo testify`, it
+/// exits with `CONFIG_ERROR_EXIT_CODE` instead.
+pub fn run() {
+    // TODO: Capture stdout and stderr to prevent polluting the test runner output. Currently, the
+    // function used to capture outputs by cargo test is only available on nightly builds of Rust.
+
+    // Initialize the runtime to avoid performance overhead later on.
+    #[cfg(feature = "async-tokio")]
+    let _ = &*crate::ASYNC_RT;
+
+    let config = load_config();
+
+    // `cargo testify` always sets both, so only one of them being set means it was set by hand.
+    if std::env::var_os(TEST_RUNNER_TOGGLE_ENV_VAR_NAME).is_none() {
+        eprintln!(
+            "The testify configuration was set, but `{TEST_RUNNER_TOGGLE_ENV_VAR_NAME}` wasn't. Run the tests with `cargo testify` instead of setting the configuration manually."
+        );
+        std::process::exit(CONFIG_ERROR_EXIT_CODE);
+    }
+
+    isolation::run_if_isolated(&config);
+    handle_interrupts();
+
+    // Tells `cargo testify` that `#[testify::main]` routed to the tests, since it can't tell that
+    // from the binary's exit code.
+    if let Some(marker) = std::env::var_os(RUN_MARKER_ENV_VAR_NAME) {
+        let _ = std::fs::write(marker, "");
+    }
+
+    if config.list_tags {
+        list_tags(&TESTS.lock().unwrap());
+        return;
+    }
+
+    if let Err(e) = compile_patterns(&config) {
+        eprintln!("A pattern passed to the name filter was invalid: {e}");
+        std::process::exit(1);
+    }
+
+    if let Err(e) = compile_ignore_patterns(&config) {
+        eprintln!("A pattern in the `.testifyignore` file was invalid: {e}");
+        std::process::exit(1);
+    }
+
+    let mut runner = Runner::new().with_config(config.clone());
+
+    for setup in take_sorted(&SETUP) {
+        runner = runner.with_setup(setup);
+    }
+
+    for cleanup in take_sorted(&CLEANUP) {
+        runner = runner.with_cleanup(cleanup);
+    }
+
+    for (tags, setup) in take_sorted_scoped(&TAG_SETUPS) {
+        runner = runner.with_tag_setup(tags, setup);
+    }
+
+    for (tags, cleanup) in take_sorted_scoped(&TAG_CLEANUPS) {
+        runner = runner.with_tag_cleanup(tags, cleanup);
+    }
+
+    for (suite, setup) in take_sorted_scoped(&SUITE_SETUPS) {
+        runner = runner.with_suite_setup(suite, setup);
+    }
+
+    for (suite, cleanup) in take_sorted_scoped(&SUITE_CLEANUPS) {
+        runner = runner.with_suite_cleanup(suite, cleanup);
+    }
+
+    if report_failed(&runner.run(), &config) {
         std::process::exit(1);
     }
 }
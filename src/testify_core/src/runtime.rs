@@ -0,0 +1,293 @@
+use std::{
+    cell::Cell,
+    future::{Future, poll_fn},
+    pin::{Pin, pin},
+    sync::{
+        Arc, Mutex, OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    task::{Context, Poll, Waker},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// A future that's been boxed to be run by an `AsyncRuntime`.
+pub type BoxedFuture<'a> = Pin<Box<dyn Future<Output = ()> + 'a>>;
+
+/// A boxed future that can be sent to another thread, as returned by `AsyncRuntime::sleep`.
+pub type SendBoxedFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// An async runtime that async tests, setups and cleanups can run on. With the `async-tokio`
+/// feature, it's implemented for tokio's `Runtime`, which is used by default.
+///
+/// The trait only requires running a boxed future so that it can be used as a trait object, which
+/// is what lets `set_async_runtime` replace it. To run any future and get its output, use
+/// `block_on`, which is implemented for `dyn AsyncRuntime`.
+pub trait AsyncRuntime: Send + Sync {
+    /// Runs a future to completion on the current thread.
+    fn block_on_boxed(&self, future: BoxedFuture<'_>);
+
+    /// Returns a future that completes after `duration`, which `wait_until_async` awaits in between
+    /// calls to its predicate. By default, the waiting happens on a thread of its own, which works
+    /// on any runtime. Runtimes with their own timers can use them instead.
+    fn sleep(&self, duration: Duration) -> SendBoxedFuture {
+        Box::pin(ThreadSleep::new(duration))
+    }
+}
+
+impl dyn AsyncRuntime {
+    /// Runs a future to completion on the current thread, returning its output.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        let mut output = None;
+
+        self.block_on_boxed(Box::pin(async {
+            output = Some(future.await);
+        }));
+
+        output.expect("The async runtime returned before the future completed")
+    }
+}
+
+#[cfg(feature = "async-tokio")]
+impl AsyncRuntime for tokio::runtime::Runtime {
+    fn block_on_boxed(&self, future: BoxedFuture<'_>) {
+        self.block_on(future);
+    }
+
+    fn sleep(&self, duration: Duration) -> SendBoxedFuture {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// The state shared by a `ThreadSleep` and the thread it waits on.
+#[derive(Default)]
+struct SleepState {
+    done: bool,
+    waker: Option<Waker>,
+}
+
+/// A future that completes after a duration, waiting on a thread that's started the first time
+/// it's polled, so that it doesn't depend on any runtime's timers.
+struct ThreadSleep {
+    duration: Option<Duration>,
+    state: Arc<Mutex<SleepState>>,
+}
+
+impl ThreadSleep {
+    fn new(duration: Duration) -> Self {
+        Self {
+            duration: Some(duration),
+            state: Arc::default(),
+        }
+    }
+}
+
+impl Future for ThreadSleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.state.lock().unwrap();
+
+        if state.done {
+            return Poll::Ready(());
+        }
+
+        state.waker = Some(cx.waker().clone());
+        drop(state);
+
+        if let Some(duration) = self.duration.take() {
+            let state = self.state.clone();
+
+            thread::spawn(move || {
+                thread::sleep(duration);
+
+                let mut state = state.lock().unwrap();
+                state.done = true;
+
+                if let Some(waker) = state.waker.take() {
+                    waker.wake();
+                }
+            });
+        }
+
+        Poll::Pending
+    }
+}
+
+static ASYNC_RUNTIME: OnceLock<&'static dyn AsyncRuntime> = OnceLock::new();
+
+/// Returns the runtime async tests, setups and cleanups run on. Unless another one was set with
+/// `set_async_runtime`, it's `ASYNC_RT`.
+///
+/// # Panics
+///
+/// If no runtime was set and the `async-tokio` feature is disabled.
+pub fn async_runtime() -> &'static dyn AsyncRuntime {
+    *ASYNC_RUNTIME.get_or_init(default_async_runtime)
+}
+
+#[cfg(feature = "async-tokio")]
+fn default_async_runtime() -> &'static dyn AsyncRuntime {
+    &*crate::ASYNC_RT
+}
+
+#[cfg(not(feature = "async-tokio"))]
+fn default_async_runtime() -> &'static dyn AsyncRuntime {
+    panic!("No async runtime was set. Enable the `async-tokio` feature or call `testify::set_async_runtime()`.")
+}
+
+/// Replaces the runtime async tests, setups and cleanups run on. It must be called before anything
+/// runs on it, such as from `main` before `run()` or from a non-async `#[testify::setup]`.
+///
+/// # Panics
+///
+/// If the runtime was already used or set.
+pub fn set_async_runtime(runtime: impl AsyncRuntime + 'static) {
+    let runtime: &'static dyn AsyncRuntime = Box::leak(Box::new(runtime));
+
+    if ASYNC_RUNTIME.set(runtime).is_err() {
+        panic!("The async runtime can only be set before it's used, and only once.");
+    }
+}
+
+thread_local! {
+    /// Whether every async test running on this thread runs on a runtime of its own, as set by
+    /// `--fresh-runtime`.
+    static FRESH_RUNTIMES: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs `f` with every async test it runs on the current thread running on a runtime of its own
+/// if `fresh` is set, or only the ones with `fresh_runtime` otherwise.
+pub(crate) fn with_fresh_runtimes<T>(fresh: bool, f: impl FnOnce() -> T) -> T {
+    let previous = FRESH_RUNTIMES.replace(fresh);
+    let result = f();
+    FRESH_RUNTIMES.set(previous);
+
+    result
+}
+
+/// Runs the future of an async test to completion, returning its output. It runs on
+/// `async_runtime()`, unless `fresh_runtime` is set or the tests run with `--fresh-runtime`, in
+/// which case a new tokio runtime is built for it and shut down right after, along with the tasks
+/// and timers the test left behind.
+#[doc(hidden)]
+pub fn block_on_test<F: Future>(future: F, fresh_runtime: bool) -> F::Output {
+    #[cfg(feature = "async-tokio")]
+    if fresh_runtime || FRESH_RUNTIMES.get() {
+        let runtime = crate::new_tokio_runtime();
+        let output = runtime.block_on(future);
+        // Tasks stuck in blocking code would make dropping the runtime wait for them.
+        runtime.shutdown_background();
+
+        return output;
+    }
+
+    // Fresh runtimes are tokio runtimes, so the macro only allows them with `async-tokio`.
+    #[cfg(not(feature = "async-tokio"))]
+    let _ = fresh_runtime;
+
+    async_runtime().block_on(future)
+}
+
+/// Runs the future of an async test marked with `flavor = "current_thread"` to completion on a new
+/// current-thread tokio runtime, inside a `LocalSet` so that it can use `tokio::task::spawn_local`.
+/// The runtime is shut down right after, like a fresh runtime.
+#[cfg(feature = "async-tokio")]
+#[doc(hidden)]
+pub fn block_on_current_thread<F: Future>(future: F) -> F::Output {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("Could not initialize the tokio runtime");
+    let output = tokio::task::LocalSet::new().block_on(&runtime, future);
+    runtime.shutdown_background();
+
+    output
+}
+
+/// Tells a service spawned with `spawn_service` to stop.
+#[derive(Default)]
+struct StopSignal {
+    stopped: AtomicBool,
+    /// The waker of the service's future, to poll it again once it's told to stop.
+    waker: Mutex<Option<Waker>>,
+}
+
+/// A service spawned with `spawn_service`, and the thread it runs on.
+struct Service {
+    stop: Arc<StopSignal>,
+    thread: JoinHandle<()>,
+}
+
+/// The services spawned with `spawn_service`, in the order they were spawned.
+static SERVICES: Mutex<Vec<Service>> = Mutex::new(Vec::new());
+
+/// Spawns a future that keeps running until the tests finish, such as a server the tests send
+/// requests to. It's meant to be called from a `#[testify::setup]` function, which would otherwise
+/// have no way to keep it running after returning.
+///
+/// The future runs on a thread of its own, on `async_runtime()`, so the tasks it spawns run on the
+/// same runtime as the tests. The services are stopped by `shutdown_services`, which is called
+/// after the cleanup, so the cleanup can still use them.
+///
+/// ```ignore
+/// #[testify::setup]
+/// async fn setup() {
+///     let listener = TcpListener::bind("127.0.0.1:8080").await.unwrap();
+///     testify::spawn_service(async move { serve(listener).await });
+/// }
+/// ```
+pub fn spawn_service(future: impl Future<Output = ()> + Send + 'static) {
+    let stop = Arc::new(StopSignal::default());
+    let signal = stop.clone();
+
+    let thread = thread::spawn(move || {
+        let mut future = pin!(future);
+
+        async_runtime().block_on(poll_fn(|cx| {
+            // The waker is stored before checking the flag, so that a stop can't be missed.
+            *signal.waker.lock().unwrap() = Some(cx.waker().clone());
+
+            if signal.stopped.load(Ordering::Acquire) {
+                Poll::Ready(())
+            } else {
+                future.as_mut().poll(cx)
+            }
+        }));
+    });
+
+    SERVICES.lock().unwrap().push(Service { stop, thread });
+}
+
+/// Stops the services spawned with `spawn_service`, in the reverse order they were spawned, and
+/// waits for them to stop. The runner calls it after the cleanup.
+///
+/// A service is stopped the next time it yields, by dropping its future, so one stuck in blocking
+/// code is waited for.
+pub fn shutdown_services() {
+    let services = std::mem::take(&mut *SERVICES.lock().unwrap());
+
+    for service in services.into_iter().rev() {
+        service.stop.stopped.store(true, Ordering::Release);
+
+        if let Some(waker) = service.stop.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+
+        // The service either finished, was stopped or panicked. Either way, there's nothing left
+        // to report.
+        let _ = service.thread.join();
+    }
+}
+
+/// Awaits `future`, returning `None` if it doesn't complete within `duration`. It's used by
+/// `assert_completes_within!`, and relies on tokio's timers, so it must be awaited on a tokio
+/// runtime such as `ASYNC_RT`.
+#[cfg(feature = "async-tokio")]
+#[doc(hidden)]
+pub async fn complete_within<F: Future>(
+    duration: std::time::Duration,
+    future: F,
+) -> Option<F::Output> {
+    tokio::time::timeout(duration, future).await.ok()
+}
@@ -1,5 +1,23 @@
-use std::fmt::Debug;
+use std::{
+    any::Any,
+    backtrace::Backtrace,
+    cell::RefCell,
+    fmt::{self, Debug},
+    io,
+    panic::{self, AssertUnwindSafe},
+    process::ExitCode,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
 
+use serde::{Deserialize, Serialize};
+
+use crate::TESTS;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TestStatus {
     Passed,
     Panicked,
@@ -10,20 +28,521 @@ pub enum TestStatus {
 
     // The test was expected to pass, but it failed.
     NotFailed,
+
+    // The test wasn't run because a test it depends on didn't pass.
+    Skipped,
+}
+
+impl TestStatus {
+    /// A stable identifier of the status for machine-readable outputs, such as `not_panicked`,
+    /// which doesn't change between versions even if the variants are renamed. Outputs show it next
+    /// to the result's message, which explains it.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            TestStatus::Passed => "passed",
+            TestStatus::Panicked => "panicked",
+            TestStatus::NotPanicked => "not_panicked",
+            TestStatus::Failed => "failed",
+            TestStatus::NotFailed => "not_failed",
+            TestStatus::Skipped => "skipped",
+        }
+    }
+
+    /// Whether the test failed, which neither tests that passed nor skipped ones did.
+    pub fn is_failure(&self) -> bool {
+        !matches!(self, TestStatus::Passed | TestStatus::Skipped)
+    }
+}
+
+/// The outcome of running a test function, with a message explaining it if there's any.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TestResult {
+    pub status: TestStatus,
+    pub message: Option<String>,
+    /// The backtrace of the test's panic, if it panicked and backtraces were being captured.
+    pub backtrace: Option<String>,
+    /// The `Debug` representation of the value returned by the test, if it failed and the
+    /// returned type provides one. It's printed with `-vv`.
+    pub value: Option<String>,
+    /// What a passing test reported with `TestTermination::success_detail`, shown next to its
+    /// result.
+    pub detail: Option<String>,
+    /// What the test wrote with `testify::println!` and `testify::eprintln!`, if anything. It's
+    /// printed under the test if it fails.
+    pub output: Option<String>,
+    /// The timings of a benchmark that ran all its iterations.
+    pub bench: Option<BenchStats>,
+}
+
+/// How a test ended early, through `testify::fail!` or `testify::pass!`. It's the payload of the
+/// unwind that stops the test, which tells it apart from a panic.
+#[doc(hidden)]
+#[derive(Debug)]
+pub enum EarlyExit {
+    Pass,
+    Fail(Option<String>),
+}
+
+/// Unwinds out of the current test with `exit` as the payload, without calling the panic hook,
+/// since it isn't a panic. It's used by `testify::fail!` and `testify::pass!`.
+#[doc(hidden)]
+pub fn exit_early(exit: EarlyExit) -> ! {
+    panic::resume_unwind(Box::new(exit))
+}
+
+/// Whether a test checks that something works or measures how long it takes.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TestKind {
+    #[default]
+    Test,
+    /// A benchmark registered with `#[testify::bench]`, which runs its body many times and is
+    /// reported with its timings instead of as passed.
+    Bench,
+}
+
+/// The time per iteration of a benchmark, leaving out its slowest and its fastest iterations.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchStats {
+    pub iterations: u32,
+    pub mean: Duration,
+    pub median: Duration,
+}
+
+impl BenchStats {
+    /// Computes the stats from the duration of every iteration, of which there's at least one.
+    fn new(mut timings: Vec<Duration>) -> Self {
+        let iterations = timings.len() as u32;
+
+        timings.sort();
+
+        if timings.len() > 2 {
+            timings.pop();
+            timings.remove(0);
+        }
+
+        let middle = timings.len() / 2;
+        let median = if timings.len().is_multiple_of(2) {
+            (timings[middle - 1] + timings[middle]) / 2
+        } else {
+            timings[middle]
+        };
+
+        Self {
+            iterations,
+            mean: timings.iter().sum::<Duration>() / timings.len() as u32,
+            median,
+        }
+    }
+}
+
+thread_local! {
+    /// The backtrace of the last panic on this thread, captured by the panic hook.
+    static PANIC_BACKTRACE: RefCell<Option<Backtrace>> = const { RefCell::new(None) };
+
+    /// The output of the test running on this thread, or `None` if no test is running on it.
+    static TEST_OUTPUT: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Writes to the output of the test running on the current thread, which is shown if it fails.
+/// Outside of a test, it's printed to stdout, or to stderr if `stderr` is set. It's used by
+/// `testify::println!` and `testify::eprintln!`.
+#[doc(hidden)]
+pub fn write_output(args: fmt::Arguments, stderr: bool) {
+    let captured = TEST_OUTPUT.with_borrow_mut(|output| {
+        output
+            .as_mut()
+            .map(|output| fmt::Write::write_fmt(output, args))
+            .is_some()
+    });
+
+    if !captured {
+        if stderr {
+            eprint!("{args}");
+        } else {
+            print!("{args}");
+        }
+    }
+}
+
+/// Captures the backtrace of the current panic, so that it can be reported with the test that
+/// panicked. It's meant to be called from a panic hook.
+pub fn capture_panic_backtrace() {
+    PANIC_BACKTRACE.set(Some(Backtrace::force_capture()));
 }
 
-pub type TestFn = fn() -> TestStatus;
+/// Separates the levels of a nested case when it's given as a string, as in
+/// `"Admin > Weak password"`. The runner nests the cases of a test by these levels.
+pub const CASE_SEPARATOR: &str = " > ";
 
-#[derive(Debug, Clone)]
+pub type TestFn = Arc<dyn Fn() -> TestResult + Send + Sync>;
+
+#[derive(Clone)]
 pub struct Test {
     pub name: String,
     pub case: Option<String>,
+    /// A longer explanation of what the test checks, shown with `--verbose`.
+    pub description: Option<String>,
+    pub suite: Option<String>,
     pub tags: Vec<String>,
     pub function: TestFn,
+    /// The minimum time the test is expected to take to pass.
+    pub min_time: Option<Duration>,
+    /// The maximum time the test is expected to take to pass.
+    pub max_time: Option<Duration>,
+    /// How important the test is. With `--order priority`, higher priority tests run first.
+    pub priority: i32,
+    /// Whether the test is expected to panic. It's already handled by `function`, so it's only
+    /// informative.
+    pub should_panic: bool,
+    /// Set if the test is expected to fail, with the description of the expected failure given to
+    /// `should_fail = "..."`, which is empty if there's none. It's already handled by `function`,
+    /// so it's only informative.
+    pub should_fail: Option<String>,
+    pub kind: TestKind,
+    /// Whether the test's function is async, in which case the waits between its attempts happen
+    /// on the async runtime.
+    pub is_async: bool,
+    /// How many times the test is run again if it fails, overriding `--retries`.
+    pub retries: Option<u32>,
+    /// How long to wait before retrying the test, doubled after every attempt. Without it, the test
+    /// is retried right away.
+    pub retry_backoff: Option<Duration>,
+    /// How long the test can run before it fails, overriding `--tag-timeout` and `--timeout`.
+    pub timeout: Option<Duration>,
+    /// Fail the test if it passes, but wrote to its captured output with `testify::println!` or
+    /// `testify::eprintln!`. What's printed to stdout or stderr directly isn't checked.
+    pub no_captured_output: bool,
+    /// The tests that must pass before this one runs, by name, path or ID. If any of them doesn't
+    /// pass, this test is skipped.
+    pub depends_on: Vec<String>,
+    /// Whether the test is known to be slow, which exempts it from `--warn-slow`.
+    pub slow: bool,
+    /// A hash of the test's source, which changes when its definition does. It's `None` for the
+    /// tests registered at runtime.
+    pub source_hash: Option<u64>,
+    /// The path of the module where the test was defined, as given by `module_path!()`.
+    pub module_path: String,
+    pub file: String,
+    pub line: u32,
+}
+
+impl Test {
+    /// An identifier of the test that doesn't change between runs, formatted as
+    /// `[<suite>/]<name>[ (<case>)]`.
+    pub fn id(&self) -> String {
+        let mut id = String::new();
+
+        if let Some(suite) = &self.suite {
+            id.push_str(suite);
+            id.push('/');
+        }
+
+        id.push_str(&self.name);
+
+        if let Some(case) = &self.case {
+            id.push_str(&format!(" ({case})"));
+        }
+
+        id
+    }
+
+    /// The address of the test that can be passed to `cargo testify --exact`, formatted as
+    /// `<module>::<name> (<case>)` with the module relative to the crate's root, such as
+    /// `auth::Register User (Weak Password)`. Tests defined in the crate's root, or whose default
+    /// name already includes their module, are addressed by their name alone.
+    pub fn path(&self) -> String {
+        let mut path = self.qualified_name();
+
+        if let Some(case) = &self.case {
+            path.push_str(&format!(" ({case})"));
+        }
+
+        path
+    }
+
+    /// The test's name prefixed by its module relative to the crate's root, which is its path
+    /// without the case.
+    pub(crate) fn qualified_name(&self) -> String {
+        match self.module_path.split_once("::") {
+            Some((_, module)) if !self.name.starts_with(&format!("{module}::")) => {
+                format!("{module}::{}", self.name)
+            }
+            _ => self.name.clone(),
+        }
+    }
+
+    /// The levels of the test's case, which is nested inside other cases by separating them with
+    /// `CASE_SEPARATOR`, as in `"Admin > Weak password"`. It's empty if the test has no case.
+    pub fn case_path(&self) -> Vec<&str> {
+        self.case
+            .as_deref()
+            .map(|case| case.split(CASE_SEPARATOR).collect())
+            .unwrap_or_default()
+    }
+
+    /// Whether the test checks that something fails, with `should_panic` or `should_fail`.
+    pub fn is_negative(&self) -> bool {
+        self.should_panic || self.should_fail.is_some()
+    }
+}
+
+impl Debug for Test {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Test")
+            .field("name", &self.name)
+            .field("case", &self.case)
+            .field("description", &self.description)
+            .field("suite", &self.suite)
+            .field("tags", &self.tags)
+            .field("min_time", &self.min_time)
+            .field("max_time", &self.max_time)
+            .field("priority", &self.priority)
+            .field("should_panic", &self.should_panic)
+            .field("should_fail", &self.should_fail)
+            .field("kind", &self.kind)
+            .field("is_async", &self.is_async)
+            .field("retries", &self.retries)
+            .field("retry_backoff", &self.retry_backoff)
+            .field("timeout", &self.timeout)
+            .field("no_captured_output", &self.no_captured_output)
+            .field("depends_on", &self.depends_on)
+            .field("slow", &self.slow)
+            .field("source_hash", &self.source_hash)
+            .field("module_path", &self.module_path)
+            .field("file", &self.file)
+            .field("line", &self.line)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Runs a test function, catching any panic, and turns its outcome into a `TestResult` depending
+/// on whether it was expected to panic or to fail.
+pub fn run_test_fn<T: TestTermination>(
+    function: impl FnOnce() -> T,
+    should_panic: bool,
+    should_fail: bool,
+) -> TestResult {
+    // A test function can run another one, as a nested run does, whose output is also written
+    // while the outer one runs, so it's added to the outer output once it's captured.
+    let outer = TEST_OUTPUT.replace(Some(String::new()));
+    let mut result = check_test_fn(function, should_panic, should_fail);
+    let output = TEST_OUTPUT.replace(outer).filter(|output| !output.is_empty());
+
+    if let Some(output) = &output {
+        TEST_OUTPUT.with_borrow_mut(|outer| {
+            if let Some(outer) = outer {
+                outer.push_str(output);
+            }
+        });
+    }
+    result.output = output;
+
+    result
+}
+
+/// Does the work of `run_test_fn`, other than capturing the test's output.
+fn check_test_fn<T: TestTermination>(
+    function: impl FnOnce() -> T,
+    should_panic: bool,
+    should_fail: bool,
+) -> TestResult {
+    PANIC_BACKTRACE.take();
+
+    let (success, message, value, detail) = match panic::catch_unwind(AssertUnwindSafe(function)) {
+        Ok(termination) if termination.success() => (true, None, None, termination.success_detail()),
+        Ok(termination) => (false, termination.failure_message(), termination.debug_value(), None),
+        Err(e) if e.is::<EarlyExit>() => match *e.downcast::<EarlyExit>().unwrap() {
+            EarlyExit::Pass => (true, None, None, None),
+            EarlyExit::Fail(message) => (false, message, None, None),
+        },
+        Err(e) => {
+            let status = if should_panic {
+                TestStatus::Passed
+            } else {
+                TestStatus::Panicked
+            };
+
+            return TestResult {
+                status,
+                message: panic_message(&*e),
+                backtrace: PANIC_BACKTRACE.take().map(|backtrace| backtrace.to_string()),
+                value: None,
+                detail: None,
+                output: None,
+                bench: None,
+            };
+        }
+    };
+
+    let status = if should_panic {
+        TestStatus::NotPanicked
+    } else if should_fail {
+        if success {
+            TestStatus::NotFailed
+        } else {
+            TestStatus::Passed
+        }
+    } else if success {
+        TestStatus::Passed
+    } else {
+        TestStatus::Failed
+    };
+
+    TestResult {
+        status,
+        message,
+        backtrace: None,
+        value,
+        detail,
+        output: None,
+        bench: None,
+    }
+}
+
+/// Runs a benchmark's body `iterations` times, timing each of them, and adds the stats to its
+/// result. A panic stops the benchmark, which fails like a test would.
+pub fn run_bench(function: impl Fn(), iterations: u32) -> TestResult {
+    let mut timings = Vec::with_capacity(iterations as usize);
+
+    let mut result = run_test_fn(
+        || {
+            for _ in 0..iterations {
+                let start = Instant::now();
+                function();
+                timings.push(start.elapsed());
+            }
+        },
+        false,
+        false,
+    );
+
+    if result.status == TestStatus::Passed && !timings.is_empty() {
+        result.bench = Some(BenchStats::new(timings));
+    }
+
+    result
+}
+
+/// Whether the binary calls `register_test!` anywhere, set before `main` by every call site.
+static REGISTERS_AT_RUNTIME: AtomicBool = AtomicBool::new(false);
+
+/// Records that the binary registers tests at runtime. It's called before `main` by every
+/// `register_test!`, whether it runs or not.
+#[doc(hidden)]
+pub fn mark_runtime_registration() {
+    REGISTERS_AT_RUNTIME.store(true, Ordering::Relaxed);
+}
+
+/// Whether the binary may register tests at runtime, such as from a setup, so that having no
+/// tests before the setup runs doesn't mean there won't be any.
+pub(crate) fn registers_at_runtime() -> bool {
+    REGISTERS_AT_RUNTIME.load(Ordering::Relaxed)
+}
+
+/// Registers a test at runtime. Use the `register_test!` macro instead, which also records where
+/// the test was registered.
+pub fn register_test<T: TestTermination>(
+    name: impl Into<String>,
+    module_path: &str,
+    file: &str,
+    line: u32,
+    function: impl Fn() -> T + Send + Sync + 'static,
+) {
+    TESTS.lock().unwrap().push(Test {
+        name: name.into(),
+        case: None,
+        description: None,
+        suite: None,
+        tags: Vec::new(),
+        function: Arc::new(move || run_test_fn(&function, false, false)),
+        min_time: None,
+        max_time: None,
+        priority: 0,
+        should_panic: false,
+        should_fail: None,
+        kind: TestKind::Test,
+        is_async: false,
+        retries: None,
+        retry_backoff: None,
+        timeout: None,
+        no_captured_output: false,
+        depends_on: Vec::new(),
+        slow: false,
+        source_hash: None,
+        module_path: module_path.to_string(),
+        file: file.to_string(),
+        line,
+    });
+}
+
+/// The name of a test that wasn't given one, which is its function's path relative to the crate's
+/// root, such as `auth::works`, so that functions with the same name in different modules can be
+/// told apart.
+pub fn default_name(module_path: &str, fn_name: &str) -> String {
+    match module_path.split_once("::") {
+        Some((_, path)) => format!("{path}::{fn_name}"),
+        None => fn_name.to_string(),
+    }
+}
+
+/// Parses a duration with a unit, such as `100ms` or `1.5s`, as given to the test macro's
+/// `timeout` or to `cargo testify --timeout`. The supported units are `ns`, `us` (or `µs`), `ms`,
+/// `s` and `m`.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let split_at = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split_at);
+
+    let seconds_per_unit = match unit.trim() {
+        "ns" => 1e-9,
+        "us" | "µs" => 1e-6,
+        "ms" => 1e-3,
+        "s" => 1.0,
+        "m" => 60.0,
+        _ => {
+            return Err(format!(
+                "expected a duration with a unit (`ns`, `us`, `ms`, `s` or `m`), such as `10s`, got `{value}`"
+            ));
+        }
+    };
+
+    amount
+        .parse::<f64>()
+        .ok()
+        .and_then(|amount| Duration::try_from_secs_f64(amount * seconds_per_unit).ok())
+        .ok_or_else(|| format!("expected a number before the duration's unit, got `{value}`"))
+}
+
+/// Extracts the message from a panic's payload, if it was raised with a string message.
+pub fn panic_message(payload: &(dyn Any + Send)) -> Option<String> {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        Some(message.to_string())
+    } else {
+        payload.downcast_ref::<String>().cloned()
+    }
 }
 
 pub trait TestTermination {
     fn success(&self) -> bool;
+
+    /// A message explaining why the test failed, displayed next to the failure.
+    fn failure_message(&self) -> Option<String> {
+        None
+    }
+
+    /// The `Debug` representation of the whole returned value, printed under the failing test
+    /// with `-vv`. It's not required so that tests can return types that don't implement `Debug`,
+    /// which aren't printed.
+    fn debug_value(&self) -> Option<String> {
+        None
+    }
+
+    /// Something worth knowing about a passing test, such as how many records it validated,
+    /// displayed next to its result.
+    fn success_detail(&self) -> Option<String> {
+        None
+    }
 }
 
 impl TestTermination for () {
@@ -32,13 +551,118 @@ impl TestTermination for () {
     }
 }
 
-impl<T: TestTermination, E> TestTermination for Result<T, E> {
+impl TestTermination for bool {
+    fn success(&self) -> bool {
+        *self
+    }
+
+    fn failure_message(&self) -> Option<String> {
+        (!self).then(|| "The test returned `false`.".to_string())
+    }
+
+    fn debug_value(&self) -> Option<String> {
+        Some(format!("{self:?}"))
+    }
+}
+
+impl TestTermination for ExitCode {
+    fn success(&self) -> bool {
+        *self == ExitCode::SUCCESS
+    }
+
+    fn failure_message(&self) -> Option<String> {
+        (!self.success()).then(|| "The test returned an unsuccessful `ExitCode`.".to_string())
+    }
+
+    fn debug_value(&self) -> Option<String> {
+        Some(format!("{self:?}"))
+    }
+}
+
+/// Implements `TestTermination` for tuples, which succeed if all of their elements succeed.
+macro_rules! impl_tuple_termination {
+    ($($name:ident),+) => {
+        impl<$($name: TestTermination),+> TestTermination for ($($name,)+) {
+            fn success(&self) -> bool {
+                #[allow(non_snake_case)]
+                let ($($name,)+) = self;
+                $($name.success())&&+
+            }
+
+            fn failure_message(&self) -> Option<String> {
+                #[allow(non_snake_case)]
+                let ($($name,)+) = self;
+                None$(.or_else(|| (!$name.success()).then(|| $name.failure_message()).flatten()))+
+            }
+
+            fn debug_value(&self) -> Option<String> {
+                #[allow(non_snake_case)]
+                let ($($name,)+) = self;
+                let values = [$($name.debug_value()?),+];
+
+                if values.len() == 1 {
+                    Some(format!("({},)", values[0]))
+                } else {
+                    Some(format!("({})", values.join(", ")))
+                }
+            }
+
+            fn success_detail(&self) -> Option<String> {
+                #[allow(non_snake_case)]
+                let ($($name,)+) = self;
+                let details: Vec<String> = [$($name.success_detail()),+].into_iter().flatten().collect();
+
+                (!details.is_empty()).then(|| details.join(", "))
+            }
+        }
+    };
+}
+
+impl_tuple_termination!(A);
+impl_tuple_termination!(A, B);
+impl_tuple_termination!(A, B, C);
+impl_tuple_termination!(A, B, C, D);
+
+impl<T: TestTermination, E: Debug + 'static> TestTermination for Result<T, E> {
     fn success(&self) -> bool {
         match self {
             Ok(r) => r.success(),
             Err(_) => false,
         }
     }
+
+    fn failure_message(&self) -> Option<String> {
+        match self {
+            Ok(r) => r.failure_message(),
+            Err(e) => {
+                // The `Display` of an `io::Error` has the OS's description of the error, which its
+                // `Debug` only has for some errors.
+                if let Some(e) = (e as &dyn Any).downcast_ref::<io::Error>() {
+                    return Some(format!("{:?}: {e}", e.kind()));
+                }
+
+                let message = format!("{e:?}");
+
+                // Errors such as `()` or an empty string don't explain anything on their own.
+                if matches!(message.as_str(), "" | "()" | "\"\"") {
+                    Some("The test returned `Err` with no message.".to_string())
+                } else {
+                    Some(message)
+                }
+            }
+        }
+    }
+
+    fn debug_value(&self) -> Option<String> {
+        match self {
+            Ok(r) => r.debug_value().map(|value| format!("Ok({value})")),
+            Err(e) => Some(format!("Err({e:#?})")),
+        }
+    }
+
+    fn success_detail(&self) -> Option<String> {
+        self.as_ref().ok().and_then(T::success_detail)
+    }
 }
 
 impl<T: TestTermination> TestTermination for Option<T> {
@@ -48,4 +672,22 @@ impl<T: TestTermination> TestTermination for Option<T> {
             None => false
         }
     }
+
+    fn failure_message(&self) -> Option<String> {
+        match self {
+            Some(r) => r.failure_message(),
+            None => Some("The test returned `None`.".to_string()),
+        }
+    }
+
+    fn debug_value(&self) -> Option<String> {
+        match self {
+            Some(r) => r.debug_value().map(|value| format!("Some({value})")),
+            None => Some("None".to_string()),
+        }
+    }
+
+    fn success_detail(&self) -> Option<String> {
+        self.as_ref().and_then(T::success_detail)
+    }
 }
\ No newline at end of file
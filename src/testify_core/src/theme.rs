@@ -0,0 +1,178 @@
+use colored::{Color, ColoredString, Colorize};
+use serde::{Deserialize, Serialize};
+
+/// The colors a theme can use for successes, failures and skipped tests.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThemeColor {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Orange,
+}
+
+impl From<ThemeColor> for Color {
+    fn from(color: ThemeColor) -> Self {
+        match color {
+            ThemeColor::Red => Color::Red,
+            ThemeColor::Green => Color::Green,
+            ThemeColor::Yellow => Color::Yellow,
+            ThemeColor::Blue => Color::BrightBlue,
+            ThemeColor::Orange => Color::TrueColor {
+                r: 255,
+                g: 140,
+                b: 0,
+            },
+        }
+    }
+}
+
+/// A set of colors and symbols for successes and failures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorScheme {
+    /// Green for successes and red for failures.
+    #[default]
+    Default,
+    /// Blue for successes and orange for failures, which colorblind people can tell apart, along
+    /// with symbols so that the color isn't the only difference.
+    Colorblind,
+}
+
+impl ColorScheme {
+    /// Builds a theme with the scheme's colors and symbols, without non-ASCII characters if
+    /// `ascii` is set.
+    pub fn theme(self, ascii: bool) -> OutputTheme {
+        let theme = if ascii {
+            OutputTheme::ascii()
+        } else {
+            OutputTheme::default()
+        };
+
+        match self {
+            Self::Default => theme,
+            Self::Colorblind => OutputTheme {
+                pass_symbol: if ascii { "+ " } else { "✓ " }.to_string(),
+                fail_symbol: if ascii { "x " } else { "✗ " }.to_string(),
+                success_color: ThemeColor::Blue,
+                failure_color: ThemeColor::Orange,
+                ..theme
+            },
+        }
+    }
+}
+
+/// The symbols and indentation used in the runner's output. Symbols are printed right before the
+/// text they decorate, so they include the space separating them from it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct OutputTheme {
+    /// Printed before the first line of the output.
+    pub start: String,
+    /// Printed before the final summary.
+    pub finish: String,
+    /// Printed before the recap of the failed tests.
+    pub failures: String,
+    /// Printed before the list of flaky tests.
+    pub flaky: String,
+    /// Printed before the list of tests slower than the timings baseline.
+    pub regressions: String,
+    /// Printed before the number of warnings.
+    pub warnings: String,
+    /// Printed before the per-tag summary.
+    pub tags: String,
+    /// Printed before the per-file summary.
+    pub files: String,
+    /// Printed before the list of new tests.
+    pub new_tests: String,
+    /// Printed on both sides of a suite's name in its header.
+    pub suite_rule: String,
+    /// Printed on both sides of a group's tags in its header.
+    pub group_rule: String,
+    /// The unit used for microseconds in durations.
+    pub micros: String,
+    /// Printed before the `Ok.` of a test that passed.
+    pub pass_symbol: String,
+    /// Printed before the `Failed!` of a test that failed.
+    pub fail_symbol: String,
+    /// The color of successes, such as passed tests.
+    pub success_color: ThemeColor,
+    /// The color of failures, such as failed tests.
+    pub failure_color: ThemeColor,
+    /// The color of skipped tests.
+    pub skip_color: ThemeColor,
+    /// The number of spaces of each level of indentation.
+    pub indent: usize,
+    /// The number of decimals shown in durations. If not set, durations under a second are
+    /// rounded to their unit, and longer ones to hundredths of a second.
+    pub duration_precision: Option<usize>,
+}
+
+impl OutputTheme {
+    /// A theme without emoji or any other non-ASCII character, for terminals that can't display
+    /// them.
+    pub fn ascii() -> Self {
+        Self {
+            start: String::new(),
+            finish: String::new(),
+            failures: String::new(),
+            flaky: String::new(),
+            regressions: String::new(),
+            warnings: String::new(),
+            tags: String::new(),
+            files: String::new(),
+            new_tests: String::new(),
+            micros: "us".to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Colors text reporting a success.
+    pub fn success(&self, text: &str) -> ColoredString {
+        text.color(self.success_color)
+    }
+
+    /// Colors text reporting a failure.
+    pub fn failure(&self, text: &str) -> ColoredString {
+        text.color(self.failure_color)
+    }
+
+    /// Colors text reporting a skipped test.
+    pub fn skipped(&self, text: &str) -> ColoredString {
+        text.color(self.skip_color)
+    }
+
+    /// The `Ok.` shown after a test that passed.
+    pub fn ok(&self) -> ColoredString {
+        self.success(&format!("{}Ok.", self.pass_symbol))
+    }
+
+    /// The `Failed!` shown after a test that failed.
+    pub fn failed(&self) -> ColoredString {
+        self.failure(&format!("{}Failed!", self.fail_symbol))
+    }
+}
+
+impl Default for OutputTheme {
+    fn default() -> Self {
+        Self {
+            start: "✨ ".to_string(),
+            finish: "✅ ".to_string(),
+            failures: "❌ ".to_string(),
+            flaky: "🔁 ".to_string(),
+            regressions: "🐢 ".to_string(),
+            warnings: "⚠️  ".to_string(),
+            tags: "🏷️  ".to_string(),
+            files: "📁 ".to_string(),
+            new_tests: "🆕 ".to_string(),
+            suite_rule: "====".to_string(),
+            group_rule: "----".to_string(),
+            micros: "µs".to_string(),
+            pass_symbol: String::new(),
+            fail_symbol: String::new(),
+            success_color: ThemeColor::Green,
+            failure_color: ThemeColor::Red,
+            skip_color: ThemeColor::Yellow,
+            indent: 3,
+            duration_precision: None,
+        }
+    }
+}
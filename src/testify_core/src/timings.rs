@@ -0,0 +1,124 @@
+use std::{collections::BTreeMap, path::Path, time::Duration};
+
+use serde::Serialize;
+
+use crate::{
+    runner::{self, GroupBy, TestOutcome},
+    test::TestStatus,
+};
+
+/// Slowdowns shorter than this aren't regressions, since such short differences are mostly noise.
+const MIN_REGRESSION: Duration = Duration::from_millis(1);
+
+/// A test that was slower than in the timings baseline, by more than the regression threshold.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TimingRegression {
+    /// The ID of the test, as given by `Test::id`.
+    pub id: String,
+    /// How long the test took in this run.
+    pub duration: Duration,
+    /// The test's duration in the baseline.
+    pub baseline: Duration,
+}
+
+impl TimingRegression {
+    /// How much slower the test was than in the baseline, as a percentage.
+    pub fn slowdown(&self) -> f64 {
+        (self.duration.as_secs_f64() / self.baseline.as_secs_f64() - 1.0) * 100.0
+    }
+}
+
+/// Writes the durations of the passed tests to `path` as a JSON object, mapping their IDs to their
+/// durations in nanoseconds.
+pub(crate) fn save(path: &Path, outcomes: &[TestOutcome]) -> Result<(), String> {
+    let timings: BTreeMap<String, u64> = outcomes
+        .iter()
+        .filter(|outcome| outcome.result.status == TestStatus::Passed)
+        .map(|outcome| {
+            let nanos = u64::try_from(outcome.duration.as_nanos()).unwrap_or(u64::MAX);
+            (outcome.test.id(), nanos)
+        })
+        .collect();
+
+    crate::write_file(path, serde_json::to_string_pretty(&timings).unwrap())
+        .map_err(|e| format!("Could not write the timings to {}: {e}.", path.display()))
+}
+
+/// Writes the durations of the tests that ran to `path` as folded stacks, one test case per line,
+/// formatted as `<suite>;<tags>;<name>[;<case>...] <microseconds>`:
+///
+/// - `<suite>` is the test's suite, or `(no suite)`.
+/// - `<tags>` are the tags of the test's group as given by `group_by`, or all of its tags if the
+///   tests aren't grouped, separated by commas. It's `(no tags)` if there are none.
+/// - Each level of a nested case is a frame of its own.
+///
+/// Semicolons in the frames are replaced with commas, since they separate them.
+pub(crate) fn save_folded(
+    path: &Path,
+    outcomes: &[TestOutcome],
+    group_by: GroupBy,
+) -> Result<(), String> {
+    let frame = |frame: &str| frame.replace(';', ",").replace('\n', " ");
+
+    let lines: String = outcomes
+        .iter()
+        .map(|outcome| {
+            let test = &outcome.test;
+            let tags = runner::group_tags(test, group_by).unwrap_or_else(|| test.tags.clone());
+
+            let mut frames = vec![
+                test.suite.as_deref().map_or("(no suite)".to_string(), frame),
+                if tags.is_empty() {
+                    "(no tags)".to_string()
+                } else {
+                    frame(&tags.join(","))
+                },
+                frame(&test.name),
+            ];
+            frames.extend(test.case_path().into_iter().map(frame));
+
+            format!("{} {}\n", frames.join(";"), outcome.duration.as_micros())
+        })
+        .collect();
+
+    crate::write_file(path, lines)
+        .map_err(|e| format!("Could not write the folded timings to {}: {e}.", path.display()))
+}
+
+/// Reads the durations written by `save`.
+pub(crate) fn load(path: &Path) -> Result<BTreeMap<String, Duration>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Could not read the timings baseline {}: {e}.", path.display()))?;
+
+    let timings: BTreeMap<String, u64> = serde_json::from_str(&contents)
+        .map_err(|e| format!("Could not parse the timings baseline {}: {e}.", path.display()))?;
+
+    Ok(timings
+        .into_iter()
+        .map(|(id, nanos)| (id, Duration::from_nanos(nanos)))
+        .collect())
+}
+
+/// Returns the passed tests that were more than `threshold` percent slower than in the baseline.
+/// Tests that aren't in the baseline, such as new ones, are skipped.
+pub(crate) fn find_regressions(
+    outcomes: &[TestOutcome],
+    baseline: &BTreeMap<String, Duration>,
+    threshold: f64,
+) -> Vec<TimingRegression> {
+    outcomes
+        .iter()
+        .filter(|outcome| outcome.result.status == TestStatus::Passed)
+        .filter_map(|outcome| {
+            let id = outcome.test.id();
+            let baseline = *baseline.get(&id)?;
+            let limit = baseline.mul_f64(1.0 + threshold / 100.0).max(baseline + MIN_REGRESSION);
+
+            (outcome.duration > limit).then_some(TimingRegression {
+                id,
+                duration: outcome.duration,
+                baseline,
+            })
+        })
+        .collect()
+}
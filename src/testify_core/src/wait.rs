@@ -0,0 +1,118 @@
+use std::{
+    fmt,
+    future::Future,
+    time::{Duration, Instant},
+};
+
+/// How long `wait_until` and `wait_until_async` wait in between calls to the predicate.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The error returned when a predicate didn't return `true` in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaitTimeout {
+    /// How long the predicate was polled for.
+    pub timeout: Duration,
+    /// How many times the predicate was called.
+    pub attempts: u32,
+}
+
+impl fmt::Display for WaitTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The condition wasn't met within {:?} ({} attempts)",
+            self.timeout, self.attempts
+        )
+    }
+}
+
+impl std::error::Error for WaitTimeout {}
+
+/// Calls `predicate` until it returns `true`, waiting `DEFAULT_INTERVAL` in between calls, or
+/// until `timeout` passes. It's meant for setups and tests that must wait for something to be
+/// ready, such as a server started by a setup.
+///
+/// The predicate is always called at least once, and once more after the timeout passes, so a
+/// slow predicate doesn't make it time out if the condition was met while it ran.
+///
+/// ```ignore
+/// #[testify::setup]
+/// fn setup() {
+///     start_server();
+///     testify::wait_until(|| TcpStream::connect("127.0.0.1:8080").is_ok(), Duration::from_secs(5))
+///         .expect("The server didn't start");
+/// }
+/// ```
+pub fn wait_until(predicate: impl FnMut() -> bool, timeout: Duration) -> Result<(), WaitTimeout> {
+    wait_until_every(predicate, timeout, DEFAULT_INTERVAL)
+}
+
+/// Like `wait_until`, but waiting `interval` in between calls to the predicate.
+pub fn wait_until_every(
+    mut predicate: impl FnMut() -> bool,
+    timeout: Duration,
+    interval: Duration,
+) -> Result<(), WaitTimeout> {
+    let deadline = Instant::now() + timeout;
+    let mut attempts = 0;
+
+    loop {
+        let timed_out = Instant::now() >= deadline;
+        attempts += 1;
+
+        if predicate() {
+            return Ok(());
+        }
+
+        if timed_out {
+            return Err(WaitTimeout { timeout, attempts });
+        }
+
+        std::thread::sleep(interval.min(deadline.saturating_duration_since(Instant::now())));
+    }
+}
+
+/// The async version of `wait_until`, for predicates that must be awaited. It waits in between
+/// calls without blocking the runtime, with `AsyncRuntime::sleep` of `async_runtime()`.
+///
+/// ```ignore
+/// #[testify::setup]
+/// async fn setup() {
+///     testify::spawn_service(serve());
+///     testify::wait_until_async(|| async { client().health().await.is_ok() }, Duration::from_secs(5))
+///         .await
+///         .expect("The server didn't start");
+/// }
+/// ```
+pub async fn wait_until_async<F: Future<Output = bool>>(
+    predicate: impl FnMut() -> F,
+    timeout: Duration,
+) -> Result<(), WaitTimeout> {
+    wait_until_every_async(predicate, timeout, DEFAULT_INTERVAL).await
+}
+
+/// Like `wait_until_async`, but waiting `interval` in between calls to the predicate.
+pub async fn wait_until_every_async<F: Future<Output = bool>>(
+    mut predicate: impl FnMut() -> F,
+    timeout: Duration,
+    interval: Duration,
+) -> Result<(), WaitTimeout> {
+    let deadline = Instant::now() + timeout;
+    let mut attempts = 0;
+
+    loop {
+        let timed_out = Instant::now() >= deadline;
+        attempts += 1;
+
+        if predicate().await {
+            return Ok(());
+        }
+
+        if timed_out {
+            return Err(WaitTimeout { timeout, attempts });
+        }
+
+        let wait = interval.min(deadline.saturating_duration_since(Instant::now()));
+        crate::async_runtime().sleep(wait).await;
+    }
+}
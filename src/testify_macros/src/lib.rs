@@ -2,26 +2,148 @@
 //! extended API.
 
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{ExprArray, ItemFn, LitStr, parse_macro_input};
+use proc_macro2::{Delimiter, Span, TokenStream as TokenStream2, TokenTree};
+use quote::{quote, quote_spanned};
+use syn::{
+    Attribute, Expr, ExprArray, Ident, ImplItem, ItemFn, ItemImpl, LitInt, LitStr, Meta, ReturnType,
+    Signature,
+    meta::ParseNestedMeta, parse_macro_input, spanned::Spanned,
+};
+
+/// Parses an array of tags, such as the one passed to the `tags` argument. Each tag is a string
+/// literal, or an expression whose value is only known at runtime, such as a path to a `&str`
+/// constant or a `format!` call.
+fn parse_tags(array: ExprArray) -> syn::Result<Vec<Expr>> {
+    array.elems.into_iter().map(parse_string_expr).collect()
+}
+
+/// Checks an argument that's turned into a `String` at runtime, such as a name or a tag. Any
+/// expression is accepted, but literals have to be strings.
+fn parse_string_expr(expr: Expr) -> syn::Result<Expr> {
+    match &expr {
+        Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(_),
+            ..
+        }) => Ok(expr),
+        Expr::Lit(_) => Err(syn::Error::new_spanned(
+            expr,
+            "Expected a string literal, or an expression evaluating to a `String` or a `&str`.",
+        )),
+        _ => Ok(expr),
+    }
+}
+
+/// Generates the code turning an argument checked by `parse_string_expr` into a `String`.
+/// Expressions that could bind looser than the method call, such as `a + b`, are parenthesized.
+fn to_string_tokens(expr: &Expr) -> TokenStream2 {
+    match expr {
+        Expr::Lit(_) | Expr::Path(_) | Expr::Macro(_) | Expr::Call(_) | Expr::MethodCall(_) => {
+            quote! { #expr.to_string() }
+        }
+        _ => quote! { (#expr).to_string() },
+    }
+}
+
+/// Hashes the source of a test, given as its attribute's arguments followed by the function, with
+/// FNV-1a so that the hash doesn't depend on the compiler's version. Each token is hashed by its
+/// text rather than by how the whole stream is printed, which can change between compilers, so
+/// only changes to the tokens change the hash. Doc comments are skipped, along with whitespace and
+/// comments.
+fn source_hash(source: TokenStream2) -> u64 {
+    let mut hash = 0xcbf29ce484222325;
+    hash_tokens(source, &mut hash);
+
+    hash
+}
+
+/// Adds the text of every token in `tokens` to an FNV-1a `hash`, skipping doc attributes.
+fn hash_tokens(tokens: TokenStream2, hash: &mut u64) {
+    let mut tokens = tokens.into_iter().peekable();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            // Doc comments are turned into `#[doc = "..."]` attributes, or `#![doc = "..."]` for
+            // inner ones.
+            TokenTree::Punct(punct) if punct.as_char() == '#' => {
+                let mut rest = tokens.clone();
+
+                if matches!(rest.peek(), Some(TokenTree::Punct(bang)) if bang.as_char() == '!') {
+                    rest.next();
+                }
+
+                if rest.next().as_ref().is_some_and(is_doc_attribute) {
+                    tokens = rest;
+                } else {
+                    hash_text(hash, "#");
+                }
+            }
+            TokenTree::Punct(punct) => hash_text(hash, &punct.as_char().to_string()),
+            TokenTree::Ident(ident) => hash_text(hash, &ident.to_string()),
+            TokenTree::Literal(literal) => hash_text(hash, &literal.to_string()),
+            TokenTree::Group(group) => {
+                let (open, close) = match group.delimiter() {
+                    Delimiter::Parenthesis => ("(", ")"),
+                    Delimiter::Brace => ("{", "}"),
+                    Delimiter::Bracket => ("[", "]"),
+                    Delimiter::None => ("", ""),
+                };
+
+                hash_text(hash, open);
+                hash_tokens(group.stream(), hash);
+                hash_text(hash, close);
+            }
+        }
+    }
+}
+
+/// Adds `text` to an FNV-1a `hash`, followed by a separator so that the tokens `ab` and `a b` hash
+/// differently.
+fn hash_text(hash: &mut u64, text: &str) {
+    for byte in text.bytes().chain([0]) {
+        *hash = (*hash ^ u64::from(byte)).wrapping_mul(0x100000001b3);
+    }
+}
+
+/// Whether `token` is the bracketed part of a `#[doc ...]` attribute.
+fn is_doc_attribute(token: &TokenTree) -> bool {
+    let TokenTree::Group(group) = token else {
+        return false;
+    };
+
+    let first = group.stream().into_iter().next();
+
+    group.delimiter() == Delimiter::Bracket
+        && matches!(first, Some(TokenTree::Ident(ident)) if ident == "doc")
+}
+
+/// Parses a duration literal such as `"10ms"` or `"1.5s"` into nanoseconds, with
+/// `testify_core::test::parse_duration`.
+fn parse_duration(lit: &LitStr) -> syn::Result<u64> {
+    testify_core::test::parse_duration(&lit.value())
+        .map(|duration| duration.as_nanos() as u64)
+        .map_err(|e| syn::Error::new_spanned(lit, format!("Invalid duration: {e}.")))
+}
 
 /// Wraps your program's main function and adds the necessary code to run the tests.
 #[proc_macro_attribute]
 pub fn main(_attr: TokenStream, item: TokenStream) -> TokenStream {
     let item = parse_macro_input!(item as ItemFn);
 
-    let fn_name = item.sig.ident.to_string();
+    let fn_name = &item.sig.ident;
     let fn_block = &item.block;
 
     if fn_name != "main" {
-        return quote! {
-            compile_error!(stringify!(#[testify::main] can only be used on the main function, but it was used on #fn_name.));
-        }.into();
+        return syn::Error::new_spanned(
+            fn_name,
+            format!("#[testify::main] can only be used on the `main` function, but it was used on `{fn_name}`."),
+        )
+        .to_compile_error()
+        .into();
     }
 
     quote! {
         fn main() {
-            if std::env::var(testify::TEST_RUNNER_TOGGLE_ENV_VAR_NAME).is_ok() {
+            if testify::is_test_run() {
                 testify::run();
             } else #fn_block
         }
@@ -29,105 +151,361 @@ pub fn main(_attr: TokenStream, item: TokenStream) -> TokenStream {
     .into()
 }
 
-/// Marks a function as a test function.
-#[proc_macro_attribute]
-pub fn test(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let item = parse_macro_input!(item as ItemFn);
-
-    let fn_name = &item.sig.ident;
-    let fn_args_count = item.sig.inputs.len();
-    let fn_block = &item.block;
-    let fn_return_type = &item.sig.output;
-
-    if fn_args_count != 0 {
-        return quote! {
-            compile_error!(stringify!(#[testify::test] can only be used on functions with no arguments, but it was used on #fn_name which has #fn_args_count arguments.));
-        }.into();
-    }
-
-    let is_async = item.sig.asyncness.is_some();
-
-    if is_async && !cfg!(feature = "async-tokio") {
-        return quote! {
-            compile_error!("This function is async but the `async-tokio` feature is not enabled. Enable it to use async tests.");
-        }.into();
-    }
-
-    let mut should_panic = false;
-    let mut should_fail = false;
-    let mut name: Option<String> = None;
-    let mut case: Option<String> = None;
-    let mut tags: Vec<String> = Vec::new();
+/// The arguments of `#[testify::test]`.
+#[derive(Default)]
+struct TestArgs {
+    should_panic: bool,
+    /// Set if the test should fail, with a description of the expected failure, if any.
+    should_fail: Option<String>,
+    name: Option<Expr>,
+    case: Option<Expr>,
+    description: Option<String>,
+    suite: Option<String>,
+    min_time: Option<u64>,
+    max_time: Option<u64>,
+    priority: i32,
+    tags: Vec<Expr>,
+    /// How many times the body of a benchmark runs. It's only set for `#[testify::bench]`.
+    iterations: Option<u32>,
+    /// Whether the async test runs on a tokio runtime of its own.
+    fresh_runtime: bool,
+    /// Whether the async test runs on a current-thread runtime, set with
+    /// `flavor = "current_thread"`.
+    current_thread: bool,
+    retries: Option<u32>,
+    /// The delay before the first retry, in nanoseconds.
+    retry_backoff: Option<u64>,
+    /// The hash of the test's source, as given by `source_hash`.
+    source_hash: u64,
+    /// Whether the test's function is async.
+    is_async: bool,
+    /// How long the test can run, in nanoseconds.
+    timeout: Option<u64>,
+    no_captured_output: bool,
+    /// The names of the tests that must pass before this one runs.
+    depends_on: Vec<Expr>,
+    slow: bool,
+}
 
-    let test_parser = syn::meta::parser(|meta| {
+impl TestArgs {
+    /// Parses one of the arguments.
+    fn parse_meta(&mut self, meta: ParseNestedMeta) -> syn::Result<()> {
         if meta.path.is_ident("name") {
-            name = Some(meta.value()?.parse::<LitStr>()?.value());
+            self.name = Some(parse_string_expr(meta.value()?.parse::<Expr>()?)?);
             Ok(())
         } else if meta.path.is_ident("case") {
-            case = Some(meta.value()?.parse::<LitStr>()?.value());
+            self.case = Some(parse_string_expr(meta.value()?.parse::<Expr>()?)?);
+            Ok(())
+        } else if meta.path.is_ident("description") {
+            self.description = Some(meta.value()?.parse::<LitStr>()?.value());
+            Ok(())
+        } else if meta.path.is_ident("suite") {
+            self.suite = Some(meta.value()?.parse::<LitStr>()?.value());
+            Ok(())
+        } else if meta.path.is_ident("min_time") {
+            self.min_time = Some(parse_duration(&meta.value()?.parse::<LitStr>()?)?);
+            Ok(())
+        } else if meta.path.is_ident("max_time") {
+            self.max_time = Some(parse_duration(&meta.value()?.parse::<LitStr>()?)?);
+            Ok(())
+        } else if meta.path.is_ident("priority") {
+            self.priority = parse_i32(&meta)?;
             Ok(())
         } else if meta.path.is_ident("tags") {
-            let array = meta.value()?.parse::<ExprArray>()?;
-
-            for item in array.elems {
-                if let syn::Expr::Lit(lit) = item {
-                    if let syn::Lit::Str(lit_str) = lit.lit {
-                        tags.push(lit_str.value());
-                    } else {
-                        return Err(meta.error("Expected string literal."));
-                    }
-                } else {
-                    return Err(meta.error("Expected string literal."));
-                }
+            self.tags.extend(parse_tags(meta.value()?.parse::<ExprArray>()?)?);
+            Ok(())
+        } else if meta.path.is_ident("should_panic") {
+            self.should_panic = true;
+            Ok(())
+        } else if meta.path.is_ident("fresh_runtime") {
+            self.fresh_runtime = true;
+            Ok(())
+        } else if meta.path.is_ident("timeout") {
+            self.timeout = Some(parse_duration(&meta.value()?.parse::<LitStr>()?)?);
+            Ok(())
+        } else if meta.path.is_ident("no_captured_output") {
+            self.no_captured_output = true;
+            Ok(())
+        } else if meta.path.is_ident("slow") {
+            self.slow = true;
+            Ok(())
+        } else if meta.path.is_ident("depends_on") {
+            match meta.value()?.parse::<Expr>()? {
+                Expr::Array(array) => self.depends_on.extend(parse_tags(array)?),
+                expr => self.depends_on.push(parse_string_expr(expr)?),
             }
+            Ok(())
+        } else if meta.path.is_ident("flavor") {
+            let flavor = meta.value()?.parse::<LitStr>()?;
 
+            self.current_thread = match flavor.value().as_str() {
+                "current_thread" => true,
+                "multi_thread" => false,
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        flavor,
+                        "Expected `\"current_thread\"` or `\"multi_thread\"`.",
+                    ));
+                }
+            };
             Ok(())
-        } else if meta.path.is_ident("should_panic") {
-            should_panic = true;
+        } else if meta.path.is_ident("retries") {
+            self.retries = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+            Ok(())
+        } else if meta.path.is_ident("retry_backoff") {
+            self.retry_backoff = Some(parse_duration(&meta.value()?.parse::<LitStr>()?)?);
             Ok(())
         } else if meta.path.is_ident("should_fail") {
-            should_fail = true;
+            self.should_fail = if meta.input.peek(syn::Token![=]) {
+                Some(meta.value()?.parse::<LitStr>()?.value())
+            } else {
+                Some(String::new())
+            };
             Ok(())
         } else {
             Err(meta.error(
-                "Allowed attributes are `name`, `case`, `tags`, `should_panic`, and `should_fail`.",
+                "Allowed attributes are `name`, `case`, `description`, `suite`, `tags`, `min_time`, `max_time`, `priority`, `should_panic`, `should_fail`, `fresh_runtime`, `flavor`, `retries`, `retry_backoff`, `timeout`, `no_captured_output`, `depends_on`, and `slow`.",
             ))
         }
-    });
+    }
 
-    parse_macro_input!(attr with test_parser);
+    /// Checks that the arguments don't contradict each other, or the function they're used on.
+    fn validate(&self, span: Span) -> syn::Result<()> {
+        if (self.fresh_runtime || self.current_thread) && !self.is_async {
+            return Err(syn::Error::new(
+                span,
+                "`fresh_runtime` and `flavor` can only be used on async functions.",
+            ));
+        }
 
-    if should_fail && should_panic {
-        return quote! {
-            compile_error!("You cannot set both `should_panic` and `should_fail`.");
+        if (self.fresh_runtime || self.current_thread) && !cfg!(feature = "async-tokio") {
+            return Err(syn::Error::new(
+                span,
+                "`fresh_runtime` and `flavor` build tokio runtimes, so they need the `async-tokio` feature.",
+            ));
         }
-        .into();
+
+        if self.should_fail.is_some() && self.should_panic {
+            return Err(syn::Error::new(
+                span,
+                "You cannot set both `should_panic` and `should_fail`.",
+            ));
+        }
+
+        if let (Some(min), Some(max)) = (self.min_time, self.max_time)
+            && min > max
+        {
+            return Err(syn::Error::new(
+                span,
+                "`min_time` cannot be greater than `max_time`.",
+            ));
+        }
+
+        Ok(())
     }
 
-    let case_tokens = if let Some(case_str) = case {
-        quote! { Some(#case_str.to_string()) }
-    } else {
-        quote! { None }
-    };
+    /// Generates the call running an async test's `future` to completion, on the runtime its
+    /// arguments ask for.
+    fn block_on(&self, future: TokenStream2) -> TokenStream2 {
+        let fresh_runtime = self.fresh_runtime;
 
-    let name_tokens = if let Some(name_str) = name {
-        quote! { #name_str.to_string() }
-    } else {
-        quote! { stringify!(#fn_name).to_string() }
+        if self.current_thread {
+            quote! { testify::runtime::block_on_current_thread(#future) }
+        } else {
+            quote! { testify::runtime::block_on_test(#future, #fresh_runtime) }
+        }
+    }
+
+    /// Generates the `Test` to register. `function` must evaluate to a `TestFn`, and the test is
+    /// named after `fn_name` unless a name was given.
+    fn test_tokens(&self, fn_name: &Ident, function: TokenStream2) -> TokenStream2 {
+        let case_tokens = if let Some(case) = &self.case {
+            let case = to_string_tokens(case);
+            quote! { Some(#case) }
+        } else {
+            quote! { None }
+        };
+
+        let description_tokens = if let Some(description_str) = &self.description {
+            quote! { Some(#description_str.to_string()) }
+        } else {
+            quote! { None }
+        };
+
+        let suite_tokens = if let Some(suite_str) = &self.suite {
+            quote! { Some(#suite_str.to_string()) }
+        } else {
+            quote! { None }
+        };
+
+        let min_time_tokens = match self.min_time {
+            Some(nanos) => quote! { Some(std::time::Duration::from_nanos(#nanos)) },
+            None => quote! { None },
+        };
+
+        let max_time_tokens = match self.max_time {
+            Some(nanos) => quote! { Some(std::time::Duration::from_nanos(#nanos)) },
+            None => quote! { None },
+        };
+
+        let retries_tokens = match self.retries {
+            Some(retries) => quote! { Some(#retries) },
+            None => quote! { None },
+        };
+
+        let retry_backoff_tokens = match self.retry_backoff {
+            Some(nanos) => quote! { Some(std::time::Duration::from_nanos(#nanos)) },
+            None => quote! { None },
+        };
+
+        let timeout_tokens = match self.timeout {
+            Some(nanos) => quote! { Some(std::time::Duration::from_nanos(#nanos)) },
+            None => quote! { None },
+        };
+
+        let name_tokens = if let Some(name) = &self.name {
+            to_string_tokens(name)
+        } else {
+            quote! { testify::test::default_name(module_path!(), stringify!(#fn_name)) }
+        };
+
+        let line_tokens = quote_spanned! {fn_name.span()=> line!() };
+        let tags = self.tags.iter().map(to_string_tokens);
+        let depends_on = self.depends_on.iter().map(to_string_tokens);
+        let priority = self.priority;
+        let should_panic = self.should_panic;
+        let no_captured_output = self.no_captured_output;
+        let slow = self.slow;
+        let is_async = self.is_async;
+        let source_hash = self.source_hash;
+        let should_fail = match &self.should_fail {
+            Some(description) => quote! { Some(#description.to_string()) },
+            None => quote! { None },
+        };
+        let kind = if self.iterations.is_some() {
+            quote! { testify::test::TestKind::Bench }
+        } else {
+            quote! { testify::test::TestKind::Test }
+        };
+
+        quote! {
+            testify::test::Test {
+                name: #name_tokens,
+                case: #case_tokens,
+                description: #description_tokens,
+                suite: #suite_tokens,
+                tags: vec![#(#tags),*],
+                function: #function,
+                min_time: #min_time_tokens,
+                max_time: #max_time_tokens,
+                priority: #priority,
+                should_panic: #should_panic,
+                should_fail: #should_fail,
+                kind: #kind,
+                is_async: #is_async,
+                retries: #retries_tokens,
+                retry_backoff: #retry_backoff_tokens,
+                timeout: #timeout_tokens,
+                no_captured_output: #no_captured_output,
+                depends_on: vec![#(#depends_on),*],
+                slow: #slow,
+                source_hash: Some(#source_hash),
+                module_path: module_path!().to_string(),
+                file: file!().to_string(),
+                line: #line_tokens,
+            }
+        }
+    }
+}
+
+/// Checks that a test function's signature is supported, other than its arguments.
+fn check_test_signature(sig: &Signature) -> syn::Result<()> {
+    if let Some(unsafety) = &sig.unsafety {
+        return Err(syn::Error::new_spanned(
+            unsafety,
+            "#[testify::test] cannot be used on `unsafe` functions. Use an `unsafe` block inside the test instead.",
+        ));
+    }
+
+    if let Some(constness) = &sig.constness {
+        return Err(syn::Error::new_spanned(
+            constness,
+            "#[testify::test] cannot be used on `const` functions.",
+        ));
+    }
+
+    if let Some(abi) = &sig.abi {
+        return Err(syn::Error::new_spanned(
+            abi,
+            "#[testify::test] cannot be used on `extern` functions. Call the `extern` function from a plain test function instead.",
+        ));
+    }
+
+    Ok(())
+}
+
+/// The error for a function marked with `attribute` that takes `count` arguments, pointing at its
+/// arguments. `allowed` describes the arguments it may take.
+fn arguments_error(sig: &Signature, attribute: &str, allowed: &str, count: usize) -> syn::Error {
+    let arguments = if count == 1 { "argument" } else { "arguments" };
+
+    syn::Error::new_spanned(
+        &sig.inputs,
+        format!(
+            "#[testify::{attribute}] can only be used on {allowed}, but it was used on `{}` which has {count} {arguments}.",
+            sig.ident
+        ),
+    )
+}
+
+/// Marks a function as a test function.
+#[proc_macro_attribute]
+pub fn test(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as ItemFn);
+
+    let fn_name = &item.sig.ident;
+    let fn_args_count = item.sig.inputs.len();
+    let fn_block = &item.block;
+    let fn_return_type = &item.sig.output;
+
+    if fn_args_count != 0 {
+        return arguments_error(&item.sig, "test", "functions with no arguments", fn_args_count)
+            .to_compile_error()
+            .into();
+    }
+
+    if let Err(e) = check_test_signature(&item.sig) {
+        return e.to_compile_error().into();
+    }
+
+    let is_async = item.sig.asyncness.is_some();
+
+    let attr_tokens = TokenStream2::from(attr.clone());
+    let mut args = TestArgs {
+        source_hash: source_hash(quote! { #attr_tokens #item }),
+        is_async,
+        ..Default::default()
     };
+    let test_parser = syn::meta::parser(|meta| args.parse_meta(meta));
+
+    parse_macro_input!(attr with test_parser);
+
+    if let Err(e) = args.validate(Span::call_site()) {
+        return e.to_compile_error().into();
+    }
 
     let registration_fn_name =
         syn::Ident::new(&format!("__testify_register_{fn_name}"), fn_name.span());
 
     let test_fn = if is_async {
+        let block_on = args.block_on(quote! { async { #fn_block } });
+
         quote! {
             #[doc(hidden)]
             fn __testify_test_fn() -> impl testify::test::TestTermination {
                 #[inline(always)]
                 fn __testify_inner() #fn_return_type {
-                    let __testify_result = testify::ASYNC_RT.block_on(async {
-                        #fn_block
-                    });
+                    let __testify_result = #block_on;
                     __testify_result
                 }
                 __testify_inner()
@@ -144,41 +522,15 @@ pub fn test(attr: TokenStream, item: TokenStream) -> TokenStream {
         }
     };
 
+    let should_panic = args.should_panic;
+    let should_fail = args.should_fail.is_some();
+    let test = args.test_tokens(fn_name, quote! { std::sync::Arc::new(#fn_name) });
+
     quote! {
-        fn #fn_name() -> testify::test::TestStatus {
-            use std::panic;
-            use testify::test::{TestStatus, TestTermination};
-
-            let __testify_result = panic::catch_unwind(|| {
-                // The test is recreated so that the compiler can infer the return type.
-                #test_fn
-                __testify_test_fn()
-                // termination_bound(test_fn())
-            });
-
-            match __testify_result {
-                Err(e) => {
-                    if #should_panic {
-                        return TestStatus::Passed;
-                    } else {
-                        return TestStatus::Panicked;
-                    }
-                },
-                // testify::utils::termination_to_test_result(r, #should_fail)
-                Ok(r) => {
-                    let success = r.success();
-
-                    if #should_panic {
-                        return TestStatus::NotPanicked;
-                    }
-
-                    if #should_fail {
-                        if success { TestStatus::NotFailed } else { TestStatus::Passed }
-                    } else {
-                        if success { TestStatus::Passed } else { TestStatus::Failed }
-                    }
-                },
-            }
+        fn #fn_name() -> testify::test::TestResult {
+            // The test is recreated so that the compiler can infer the return type.
+            #test_fn
+            testify::test::run_test_fn(__testify_test_fn, #should_panic, #should_fail)
         }
 
         #[doc(hidden)]
@@ -186,138 +538,531 @@ pub fn test(attr: TokenStream, item: TokenStream) -> TokenStream {
             crate_path = testify::ctor
         )]
         fn #registration_fn_name() {
-            use testify::{TESTS, test::Test};
+            use testify::TESTS;
 
             let mut tests = TESTS.lock().unwrap();
 
-            tests.push(Test {
-                name: #name_tokens,
-                case: #case_tokens,
-                tags: vec![#(#tags.to_string()),*],
-                function: #fn_name,
-            });
+            tests.push(#test);
         }
     }
     .into()
 }
 
-/// Runs the test environment setup before the execution of the tests.
+/// Marks a function as a benchmark, which runs its body `iterations` times (100 by default) and
+/// reports the mean and median time per iteration instead of passing, leaving out the slowest and
+/// the fastest iterations. It takes the same arguments as `#[testify::test]`, other than the ones
+/// about failing or taking too long.
 #[proc_macro_attribute]
-pub fn setup(_attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn bench(attr: TokenStream, item: TokenStream) -> TokenStream {
     let item = parse_macro_input!(item as ItemFn);
 
     let fn_name = &item.sig.ident;
     let fn_block = &item.block;
 
-    let is_async = item.sig.asyncness.is_some();
+    if let Err(e) = check_test_signature(&item.sig) {
+        return e.to_compile_error().into();
+    }
+
+    if !item.sig.inputs.is_empty() {
+        let count = item.sig.inputs.len();
 
-    if is_async && !cfg!(feature = "async-tokio") {
-        return quote! {
-            compile_error!("This function is async but the `async-tokio` feature is not enabled. Enable it to use async tests.");
-        }.into();
+        return arguments_error(&item.sig, "bench", "functions with no arguments", count)
+            .to_compile_error()
+            .into();
     }
 
-    let setup_runner_fn = if is_async {
-        quote! {
-            #[doc(hidden)]
-            fn __testify_async_setup_runner() {
-                testify::ASYNC_RT.block_on(async {
-                    #fn_block
-                });
+    if let ReturnType::Type(_, ty) = &item.sig.output {
+        return syn::Error::new_spanned(ty, "Benchmarks cannot return a value.")
+            .to_compile_error()
+            .into();
+    }
+
+    let is_async = item.sig.asyncness.is_some();
+
+    let attr_tokens = TokenStream2::from(attr.clone());
+    let mut args = TestArgs {
+        source_hash: source_hash(quote! { #attr_tokens #item }),
+        is_async,
+        ..Default::default()
+    };
+    let mut iterations = 100;
+    let bench_parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("iterations") {
+            let lit = meta.value()?.parse::<LitInt>()?;
+            iterations = lit.base10_parse::<u32>()?;
+
+            if iterations == 0 {
+                return Err(syn::Error::new_spanned(
+                    lit,
+                    "A benchmark needs at least one iteration.",
+                ));
             }
 
-            #[doc(hidden)]
-            #[testify::ctor::ctor(
-                crate_path = testify::ctor
-            )]
-            fn __testify_register_setup() {
-                use testify::SETUP;
+            Ok(())
+        } else {
+            args.parse_meta(meta)
+        }
+    });
+
+    parse_macro_input!(attr with bench_parser);
+
+    // A benchmark always passes unless it panics, and its time is measured, not bounded.
+    let checks_outcome = args.should_panic || args.should_fail.is_some();
+    let bounds_time = args.min_time.is_some() || args.max_time.is_some();
+
+    if checks_outcome || bounds_time {
+        return syn::Error::new(
+            Span::call_site(),
+            "Benchmarks cannot use `should_panic`, `should_fail`, `min_time` or `max_time`.",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    args.iterations = Some(iterations);
+    let registration_fn_name =
+        syn::Ident::new(&format!("__testify_register_{fn_name}"), fn_name.span());
 
-                let mut __testify_setup = SETUP.lock().unwrap();
+    if let Err(e) = args.validate(Span::call_site()) {
+        return e.to_compile_error().into();
+    }
+
+    let bench_fn = if is_async {
+        let block_on = args.block_on(quote! { async #fn_block });
 
-                *__testify_setup = Some(__testify_async_setup_runner);
+        quote! {
+            #[doc(hidden)]
+            #[inline(always)]
+            fn __testify_bench_fn() {
+                #block_on
             }
         }
     } else {
         quote! {
+            #[doc(hidden)]
+            #[inline(always)]
+            fn __testify_bench_fn() #fn_block
+        }
+    };
+
+    let test = args.test_tokens(fn_name, quote! { std::sync::Arc::new(#fn_name) });
+
+    quote! {
+        fn #fn_name() -> testify::test::TestResult {
+            #bench_fn
+            testify::test::run_bench(__testify_bench_fn, #iterations)
+        }
+
+        #[doc(hidden)]
+        #[testify::ctor::ctor(
+            crate_path = testify::ctor
+        )]
+        fn #registration_fn_name() {
+            use testify::TESTS;
+
+            let mut tests = TESTS.lock().unwrap();
+
+            tests.push(#test);
+        }
+    }
+    .into()
+}
+
+/// Returns whether an attribute is `#[testify::test]`, also when it's imported (`#[test]`). `std`'s
+/// `#[test]` can't be used on methods, so it's taken for testify's too, including when it's written
+/// with its full path (`#[std::prelude::rust_2024::test]`). Other attributes named `test`, such as
+/// `#[tokio::test]`, aren't.
+fn is_test_attribute(attr: &Attribute) -> bool {
+    let segments: Vec<String> = attr
+        .path()
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+    matches!(
+        segments[..],
+        ["test"] | ["testify", "test"] | ["core" | "std", "prelude", _, "test"]
+    )
+}
+
+/// Registers the methods of an `impl` block marked with `#[testify::test]` as tests. A new instance
+/// of the type is created with `Default::default()` for every test that takes `self`.
+#[proc_macro_attribute]
+pub fn tests(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut item = parse_macro_input!(item as ItemImpl);
+
+    if !item.generics.params.is_empty() {
+        return syn::Error::new_spanned(
+            &item.generics,
+            "#[testify::tests] cannot be used on generic `impl` blocks.",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    let self_ty = item.self_ty.clone();
+    let mut registrations = Vec::new();
+
+    for impl_item in item.items.iter_mut() {
+        let ImplItem::Fn(method) = impl_item else {
+            continue;
+        };
+
+        let Some(attr_i) = method.attrs.iter().position(is_test_attribute) else {
+            continue;
+        };
+
+        let attr = method.attrs.remove(attr_i);
+        let method_name = &method.sig.ident;
+
+        let is_async = method.sig.asyncness.is_some();
+        let mut args = TestArgs {
+            source_hash: source_hash(quote! { #attr #method }),
+            is_async,
+            ..Default::default()
+        };
+
+        if let Meta::List(_) = attr.meta
+            && let Err(e) = attr.parse_nested_meta(|meta| args.parse_meta(meta))
+        {
+            return e.to_compile_error().into();
+        }
+
+        if let Err(e) = check_test_signature(&method.sig).and(args.validate(attr.span()))
+        {
+            return e.to_compile_error().into();
+        }
+
+        let receiver = method.sig.receiver();
+        let args_count = method.sig.inputs.len() - usize::from(receiver.is_some());
+
+        if args_count != 0 {
+            let allowed = "methods with no arguments other than `self`";
+
+            return arguments_error(&method.sig, "test", allowed, args_count)
+                .to_compile_error()
+                .into();
+        }
+
+        let call = match receiver {
+            Some(receiver) if receiver.reference.is_some() && receiver.mutability.is_some() => {
+                quote! {
+                    let mut __testify_instance = <#self_ty as Default>::default();
+                    __testify_instance.#method_name()
+                }
+            }
+            Some(_) => quote! {
+                let __testify_instance = <#self_ty as Default>::default();
+                __testify_instance.#method_name()
+            },
+            None => quote! { <#self_ty>::#method_name() },
+        };
+
+        let call = if is_async {
+            args.block_on(quote! { async { #call.await } })
+        } else {
+            call
+        };
+
+        let should_panic = args.should_panic;
+        let should_fail = args.should_fail.is_some();
+        let test = args.test_tokens(
+            method_name,
+            quote! {
+                std::sync::Arc::new(|| {
+                    testify::test::run_test_fn(|| { #call }, #should_panic, #should_fail)
+                })
+            },
+        );
+
+        let registration_fn_name = syn::Ident::new(
+            &format!("__testify_register_{method_name}"),
+            method_name.span(),
+        );
+
+        registrations.push(quote! {
             #[doc(hidden)]
             #[testify::ctor::ctor(
                 crate_path = testify::ctor
             )]
-            fn __testify_register_setup() {
-                use testify::SETUP;
+            fn #registration_fn_name() {
+                use testify::TESTS;
 
-                let mut __testify_setup = SETUP.lock().unwrap();
+                let mut tests = TESTS.lock().unwrap();
 
-                *__testify_setup = Some(#fn_name);
+                tests.push(#test);
             }
-        }
-    };
+        });
+    }
 
     quote! {
-        fn #fn_name() #fn_block
+        #item
 
-        #setup_runner_fn
+        const _: () = {
+            #(#registrations)*
+        };
     }
     .into()
 }
 
-/// Runs the test environment cleanup after the execution of the tests.
-#[proc_macro_attribute]
-pub fn cleanup(_attr: TokenStream, item: TokenStream) -> TokenStream {
-    let item = parse_macro_input!(item as ItemFn);
+/// Whether a function returns `impl Future` or `impl IntoFuture` without being `async`.
+fn returns_future(sig: &Signature) -> bool {
+    let syn::ReturnType::Type(_, ty) = &sig.output else {
+        return false;
+    };
 
+    let syn::Type::ImplTrait(impl_trait) = &**ty else {
+        return false;
+    };
+
+    impl_trait.bounds.iter().any(|bound| match bound {
+        syn::TypeParamBound::Trait(bound) => bound
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Future" || segment.ident == "IntoFuture"),
+        _ => false,
+    })
+}
+
+/// Checks that a setup or cleanup function can be run as a `fn()`: it can't take arguments, and
+/// it can only return a future, which is awaited, if it's not `async`.
+fn check_lifecycle_signature(sig: &Signature) -> syn::Result<()> {
+    if !sig.inputs.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &sig.inputs,
+            "Setup and cleanup functions cannot take arguments.",
+        ));
+    }
+
+    let is_unit = match &sig.output {
+        syn::ReturnType::Default => true,
+        syn::ReturnType::Type(_, ty) => matches!(&**ty, syn::Type::Tuple(tuple) if tuple.elems.is_empty()),
+    };
+
+    if !is_unit && (sig.asyncness.is_some() || !returns_future(sig)) {
+        return Err(syn::Error::new_spanned(
+            &sig.output,
+            "Setup and cleanup functions cannot return a value. They can only return `impl Future<Output = ()>`, which is awaited, if they're not `async`.",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Returns a setup or cleanup function as it's declared, and an expression that runs it as a
+/// `fn()`, blocking on it if it's async or returns a future.
+fn lifecycle_function(item: &ItemFn) -> (TokenStream2, TokenStream2) {
     let fn_name = &item.sig.ident;
     let fn_block = &item.block;
+    let output = &item.sig.output;
 
-    let is_async = item.sig.asyncness.is_some();
-
-    if is_async && !cfg!(feature = "async-tokio") {
-        return quote! {
-            compile_error!("This function is async but the `async-tokio` feature is not enabled. Enable it to use async tests.");
-        }.into();
+    if item.sig.asyncness.is_some() {
+        (
+            quote! { async fn #fn_name() #fn_block },
+            quote! { || testify::async_runtime().block_on(#fn_name()) },
+        )
+    } else if returns_future(&item.sig) {
+        (
+            quote! { fn #fn_name() #output #fn_block },
+            quote! {
+                || testify::async_runtime().block_on(std::future::IntoFuture::into_future(#fn_name()))
+            },
+        )
+    } else {
+        (quote! { fn #fn_name() #fn_block }, quote! { #fn_name })
     }
+}
 
-    let cleanup_runner_fn = if is_async {
-        quote! {
-            #[doc(hidden)]
-            fn __testify_async_cleanup_runner() {
-                testify::ASYNC_RT.block_on(async {
-                    #fn_block
-                });
-            }
+/// Registers a setup or cleanup function that runs for the whole run in `registry`.
+fn lifecycle_registration(item: &ItemFn, order: i32, registry: Ident) -> TokenStream2 {
+    let (function, runner) = lifecycle_function(item);
 
+    quote! {
+        #function
+
+        const _: () = {
             #[doc(hidden)]
             #[testify::ctor::ctor(
                 crate_path = testify::ctor
             )]
-            fn __testify_register_cleanup() {
-                use testify::CLEANUP;
+            fn __testify_register() {
+                testify::#registry.lock().unwrap().push((#order, #runner));
+            }
+        };
+    }
+}
 
-                let mut __testify_cleanup = CLEANUP.lock().unwrap();
+/// Registers a setup or cleanup function scoped to some of the tests into the `registry` static,
+/// such as `TAG_SETUPS` or `SUITE_CLEANUPS`. `scope` is the expression identifying those tests,
+/// which is stored next to the function.
+fn scoped_registration(
+    item: &ItemFn,
+    scope: TokenStream2,
+    order: i32,
+    registry: Ident,
+) -> TokenStream2 {
+    let (function, runner) = lifecycle_function(item);
 
-                *__testify_cleanup = Some(__testify_async_cleanup_runner);
-            }
-        }
-    } else {
-        quote! {
+    quote! {
+        #function
+
+        const _: () = {
+            #[doc(hidden)]
             #[testify::ctor::ctor(
                 crate_path = testify::ctor
             )]
-            fn __testify_register_cleanup() {
-                use testify::CLEANUP;
+            fn __testify_register() {
+                testify::#registry
+                    .lock()
+                    .unwrap()
+                    .push((#order, #scope, #runner));
+            }
+        };
+    }
+}
 
-                let mut __testify_cleanup = CLEANUP.lock().unwrap();
+/// Parses the value of an argument that's an integer, which may be negative.
+fn parse_i32(meta: &ParseNestedMeta) -> syn::Result<i32> {
+    let value = meta.value()?;
 
-                *__testify_cleanup = Some(#fn_name);
-            }
+    // Negative numbers are parsed as a `-` followed by the literal. The literal is parsed as an
+    // `i64` before negating it, so that `i32::MIN` doesn't overflow.
+    let negative = value.parse::<Option<syn::Token![-]>>()?.is_some();
+    let literal = value.parse::<LitInt>()?;
+    let parsed = literal.base10_parse::<i64>()?;
+
+    i32::try_from(if negative { -parsed } else { parsed })
+        .map_err(|_| syn::Error::new_spanned(&literal, "The number doesn't fit in an `i32`."))
+}
+
+/// The tests a setup or cleanup function is scoped to.
+enum LifecycleScope {
+    /// All of them, running before the first test or after the last one.
+    All,
+    Tags(Vec<Expr>),
+    Suite(LitStr),
+}
+
+/// Parses the arguments of `#[testify::setup]` and `#[testify::cleanup]`, returning their scope and
+/// their order.
+fn parse_lifecycle_args(attr: TokenStream) -> syn::Result<(LifecycleScope, i32)> {
+    let mut tags = Vec::new();
+    let mut suite: Option<LitStr> = None;
+    let mut order = 0;
+
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("tags") {
+            tags.extend(parse_tags(meta.value()?.parse::<ExprArray>()?)?);
+            Ok(())
+        } else if meta.path.is_ident("suite") {
+            suite = Some(meta.value()?.parse::<LitStr>()?);
+            Ok(())
+        } else if meta.path.is_ident("order") {
+            order = parse_i32(&meta)?;
+            Ok(())
+        } else {
+            Err(meta.error("Allowed attributes are `tags`, `suite` and `order`."))
+        }
+    });
+
+    syn::parse::Parser::parse(parser, attr)?;
+
+    let scope = match (suite, tags.is_empty()) {
+        (Some(suite), true) => LifecycleScope::Suite(suite),
+        (Some(suite), false) => {
+            return Err(syn::Error::new_spanned(
+                suite,
+                "A setup or cleanup function can be scoped to `tags` or to a `suite`, but not both.",
+            ));
         }
+        (None, true) => LifecycleScope::All,
+        (None, false) => LifecycleScope::Tags(tags),
+    };
+
+    Ok((scope, order))
+}
+
+/// Registers a setup or cleanup function in the registry matching its scope, whose names start with
+/// `TAG_`, `SUITE_` or nothing, followed by `kind` (`SETUP` or `CLEANUP`, in plural if scoped).
+fn register_lifecycle(attr: TokenStream, item: &ItemFn, kind: &str) -> TokenStream2 {
+    let registry = |name: String| Ident::new(&name, Span::call_site());
+
+    match parse_lifecycle_args(attr) {
+        Ok((LifecycleScope::All, order)) => lifecycle_registration(item, order, registry(kind.to_string())),
+        Ok((LifecycleScope::Tags(tags), order)) => scoped_registration(
+            item,
+            {
+                let tags = tags.iter().map(to_string_tokens);
+                quote! { vec![#(#tags),*] }
+            },
+            order,
+            registry(format!("TAG_{kind}S")),
+        ),
+        Ok((LifecycleScope::Suite(suite), order)) => scoped_registration(
+            item,
+            quote! { #suite.to_string() },
+            order,
+            registry(format!("SUITE_{kind}S")),
+        ),
+        Err(e) => e.to_compile_error(),
+    }
+}
+
+/// Runs the test environment setup before the execution of the tests. With `tags`, it runs before
+/// the first test with any of them instead, and with `suite`, before the first test of the suite.
+/// Setups run sorted by their `order`, lowest first.
+#[proc_macro_attribute]
+pub fn setup(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as ItemFn);
+
+    if let Err(e) = check_lifecycle_signature(&item.sig) {
+        return e.to_compile_error().into();
+    }
+
+    register_lifecycle(attr, &item, "SETUP").into()
+}
+
+/// Runs the test environment cleanup after the execution of the tests. With `tags`, it runs after
+/// the last test with any of them instead, and with `suite`, after the last test of the suite.
+/// Cleanups run sorted by their `order`, lowest first.
+#[proc_macro_attribute]
+pub fn cleanup(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as ItemFn);
+
+    if let Err(e) = check_lifecycle_signature(&item.sig) {
+        return e.to_compile_error().into();
+    }
+
+    register_lifecycle(attr, &item, "CLEANUP").into()
+}
+
+/// Declares the tags used in the project, so that the runner can warn about typos in them.
+#[proc_macro]
+pub fn register_tags(item: TokenStream) -> TokenStream {
+    let array = parse_macro_input!(item as ExprArray);
+
+    let tags = match parse_tags(array) {
+        Ok(tags) => tags.iter().map(to_string_tokens).collect::<Vec<_>>(),
+        Err(e) => return e.to_compile_error().into(),
     };
 
     quote! {
-        fn #fn_name() #fn_block
+        const _: () = {
+            #[doc(hidden)]
+            #[testify::ctor::ctor(
+                crate_path = testify::ctor
+            )]
+            fn __testify_register_tags() {
+                use testify::KNOWN_TAGS;
 
-        #cleanup_runner_fn
+                let mut known_tags = KNOWN_TAGS.lock().unwrap();
+
+                known_tags.extend([#(#tags),*]);
+            }
+        };
     }
     .into()
 }
@@ -1,31 +1,754 @@
-use clap::Parser;
-use std::process::Command;
-use testify_core::runner::TestifyConfig;
+use clap::{ArgAction, CommandFactory, Parser, Subcommand, ValueEnum, error::ErrorKind};
+use clap_complete::Shell;
+use std::{
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+use testify_core::{
+    changes::{self, DEFAULT_TEST_HASHES_FILE},
+    last_run::{self, DEFAULT_LAST_RUN_FILE, LastStatus},
+    reports::ReportKind,
+    runner::{GroupBy, OutputFormat, ParallelBy, TestOrder, TestifyConfig},
+    test::parse_duration,
+    theme::{ColorScheme, OutputTheme},
+};
 
 #[derive(Parser)]
 #[command(
     name = "Testify",
     about = "Run testify's tests for a project.",
-    long_about = None
+    long_about = None,
+    args_conflicts_with_subcommands = true
 )]
 struct CommandArgs {
-    #[arg(help = "A glob pattern to filter the tests' names by")]
-    test_name: Option<String>,
+    #[arg(help = "Glob patterns to filter the tests' names by, matching any of them")]
+    test_names: Vec<String>,
 
-    #[arg(short, long, help = "Filter tests by tag")]
+    #[arg(long, help = "Match the test names or paths exactly instead of as glob patterns")]
+    exact: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "exact",
+        help = "Match the test names against regular expressions instead of glob patterns"
+    )]
+    regex: bool,
+
+    #[arg(short, long, help = "Filter tests by tag, in addition to the ones in TESTIFY_TAGS")]
     tag: Vec<String>,
 
-    #[arg(short, long, help = "Exclude tests with tag")]
+    #[arg(
+        short,
+        long,
+        help = "Exclude tests with tag, in addition to the ones in TESTIFY_EXCLUDE_TAGS"
+    )]
     exclude_tag: Vec<String>,
 
+    #[arg(
+        long,
+        value_name = "ALIAS=TAG",
+        value_parser = parse_tag_alias,
+        help = "Make the tag filters matching ALIAS also match the tests tagged TAG"
+    )]
+    tag_alias: Vec<(String, String)>,
+
+    #[arg(long, help = "Skip the tests marked with `should_panic` or `should_fail`")]
+    skip_negative: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "skip_negative",
+        help = "Only run the tests marked with `should_panic` or `should_fail`"
+    )]
+    only_negative: bool,
+
+    #[arg(
+        long,
+        help = "Fail if any test was ignored by .testifyignore, didn't run because the run stopped early, or was skipped because of a failed dependency"
+    )]
+    no_skips: bool,
+
+    #[arg(long, help = "Fail if no tests are registered, instead of exiting successfully")]
+    error_on_empty: bool,
+
+    #[arg(
+        short,
+        long,
+        help = "Only run the tests in a suite, in addition to the ones in TESTIFY_SUITES"
+    )]
+    suite: Vec<String>,
+
     #[arg(short, long, help = "Stop the tests after the first failure")]
     fail_fast: bool,
 
+    #[arg(long, default_value_t = 0, help = "Run failing tests again up to this many times")]
+    retries: u32,
+
+    #[arg(long, help = "Fail the tests that only pass after being retried")]
+    strict_flaky: bool,
+
+    #[arg(
+        short,
+        long,
+        help = "The number of tests to run in parallel [default: $RUST_TEST_THREADS or the number of CPUs]"
+    )]
+    jobs: Option<NonZeroUsize>,
+
+    #[arg(
+        long,
+        help = "The maximum number of worker threads of the async runtime [default: $TESTIFY_RT_THREADS or the number of CPUs]"
+    )]
+    max_threads: Option<NonZeroUsize>,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "The output format [default: github on GitHub Actions, human otherwise]"
+    )]
+    format: Option<Format>,
+
+    #[arg(
+        long,
+        value_name = "KIND:PATH",
+        value_parser = parse_report,
+        help = "Also write a report to a file when the run finishes, where KIND is human, junit, json or tap. Can be passed several times"
+    )]
+    report: Vec<(ReportKind, PathBuf)>,
+
+    #[arg(
+        long,
+        overrides_with = "json_pretty",
+        help = "Write the JSON reports on a single line"
+    )]
+    json_compact: bool,
+
+    #[arg(
+        long,
+        overrides_with = "json_compact",
+        help = "Write the JSON reports indented, which is the default"
+    )]
+    json_pretty: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Grouping::Tagset,
+        help = "How to group the tests in the output"
+    )]
+    group_by: Grouping,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Order::Default,
+        help = "The order in which to run the tests"
+    )]
+    order: Order,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Parallelism::Case,
+        help = "How the tests are split between threads when running in parallel"
+    )]
+    parallel_by: Parallelism,
+
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        help = "Fail the tests that run for longer than a duration, such as 10s"
+    )]
+    timeout: Option<Duration>,
+
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        help = "Warn about the tests that run for longer than a duration, unless they're marked as slow"
+    )]
+    warn_slow: Option<Duration>,
+
+    #[arg(
+        long,
+        value_name = "TAG=DURATION",
+        value_parser = parse_tag_timeout,
+        help = "Set the timeout of the tests with a tag, overriding --timeout"
+    )]
+    tag_timeout: Vec<(String, Duration)>,
+
+    #[arg(long, help = "Print a single character for each test, followed by the failures")]
+    dots: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "0",
+        help = "Run the tests repeatedly until they fail, up to N times [default: forever]"
+    )]
+    repeat_until_failure: Option<u32>,
+
+    #[arg(long, help = "Run the setup and cleanup for every repetition instead of once")]
+    repeat_setup: bool,
+
+    #[arg(long, help = "Don't run the setup functions, to iterate faster")]
+    no_setup: bool,
+
+    #[arg(long, help = "Don't run the cleanup functions, to iterate faster")]
+    no_cleanup: bool,
+
+    #[arg(long, help = "Run each test in its own process, so that aborts and crashes only fail that test")]
+    isolate: bool,
+
+    #[arg(long, help = "Print the backtrace of the tests that panic")]
+    backtrace: bool,
+
+    #[arg(long, help = "Don't use emoji or other non-ASCII symbols in the output")]
+    ascii: bool,
+
+    #[arg(
+        long,
+        value_name = "DECIMALS",
+        help = "The number of decimals shown in durations, such as 3 for 1.234ms"
+    )]
+    duration_precision: Option<usize>,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Scheme::Default,
+        help = "The colors and symbols of passed and failed tests"
+    )]
+    color_scheme: Scheme,
+
+    #[arg(long, help = "Fail if any warning is emitted, even if all tests pass")]
+    fail_on_warnings: bool,
+
+    #[arg(short, long, action = ArgAction::Count, help = "Show more details about each test")]
+    verbose: u8,
+
+    #[arg(
+        long,
+        help = "Show every detail about the failing tests, without -v's details about the passing ones [implied by --backtrace]"
+    )]
+    verbose_failures: bool,
+
+    #[arg(long, help = "Print the number of passed and failed tests for each tag after the run")]
+    tag_summary: bool,
+
+    #[arg(
+        long = "by-file",
+        help = "Print the number of passed and failed tests for each source file after the run"
+    )]
+    file_summary: bool,
+
+    #[arg(long, help = "List all the tags used by the tests, with their test count, and exit")]
+    list_tags: bool,
+
+    #[arg(
+        long,
+        help = "The directory to write the tests' artifacts to [default: a new directory in the system's temporary directory]"
+    )]
+    output_dir: Option<PathBuf>,
+
+    #[arg(long, value_name = "PATH", help = "Save the durations of the passed tests to a file")]
+    save_timings: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Fail if any test is slower than in a file saved with --save-timings"
+    )]
+    timings_baseline: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        default_value = "20%",
+        value_parser = parse_percentage,
+        help = "How much slower than in the --timings-baseline a test can be"
+    )]
+    regression_threshold: f64,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Write the durations of the tests to a file as folded stacks, for flamegraph tools"
+    )]
+    fold_timings: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Write the results of the run to a file as Prometheus metrics"
+    )]
+    metrics: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Run every async test on a new tokio runtime, so that tests don't share tasks or timers"
+    )]
+    fresh_runtime: bool,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Only run the tests whose names or IDs are listed in a file, one per line"
+    )]
+    filter_file: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "SHARD/SHARDS",
+        value_parser = parse_shard,
+        help = "Only run one of several equal parts of the tests, such as 1/4, to split them between machines"
+    )]
+    shard: Option<(usize, usize)>,
+
+    #[arg(long, help = "List the tests that weren't registered in the previous run")]
+    new_tests: bool,
+
+    #[arg(
+        long,
+        help = "The file to track the registered tests in for --new-tests [default: .testify/known-tests.json]"
+    )]
+    known_tests_file: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "STATUS",
+        help = "Only run the tests that had a status in the previous run"
+    )]
+    rerun_status: Option<RerunStatus>,
+
+    #[arg(
+        long,
+        help = "Store the status of the tests that run, to be read by --rerun-status [implied by --rerun-status]"
+    )]
+    save_last_run: bool,
+
+    #[arg(
+        long,
+        help = "The file to store the status of every test in for --rerun-status [default: .testify/last-run.json]"
+    )]
+    last_run_file: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Only run the tests that are new or whose source changed since they last passed"
+    )]
+    changed_tests_only: bool,
+
+    #[arg(
+        long,
+        help = "The file to store the tests' source hashes in for --changed-tests-only [default: .testify/test-hashes.json]"
+    )]
+    test_hashes_file: Option<PathBuf>,
+
+    #[arg(long, help = "The binary with `#[testify::main]` to run, if the package has several")]
+    bin: Option<String>,
+
+    #[arg(short, long, help = "The package with `#[testify::main]` to run, in a workspace")]
+    package: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "COMMAND",
+        help = "The command to run the tests with, split on whitespace [env: TESTIFY_RUNNER] [default: cargo run]"
+    )]
+    runner: Option<String>,
+
     #[arg(
         last = true,
-        help = "The arguments to pass to your project's `cargo run`"
+        help = "The arguments to pass to your project's `cargo run`, or to the --runner command"
     )]
     cargo_args: Vec<String>,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Print the completion script for a shell to stdout, instead of running the tests
+    Completions {
+        #[arg(value_enum)]
+        shell: CompletionShell,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Human,
+    Github,
+}
+
+impl From<Format> for OutputFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Human => OutputFormat::Human,
+            Format::Github => OutputFormat::Github,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Report {
+    Human,
+    Junit,
+    Json,
+    Tap,
+}
+
+impl From<Report> for ReportKind {
+    fn from(report: Report) -> Self {
+        match report {
+            Report::Human => ReportKind::Human,
+            Report::Junit => ReportKind::Junit,
+            Report::Json => ReportKind::Json,
+            Report::Tap => ReportKind::Tap,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Grouping {
+    /// One group for every distinct set of tags
+    Tagset,
+    /// One group for every test's first tag
+    FirstTag,
+    /// A flat list of tests
+    None,
+}
+
+impl From<Grouping> for GroupBy {
+    fn from(grouping: Grouping) -> Self {
+        match grouping {
+            Grouping::Tagset => GroupBy::TagSet,
+            Grouping::FirstTag => GroupBy::FirstTag,
+            Grouping::None => GroupBy::None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Scheme {
+    /// Green for passed tests and red for failed ones
+    Default,
+    /// Blue for passed tests and orange for failed ones, marked with ✓ and ✗
+    Colorblind,
+}
+
+impl From<Scheme> for ColorScheme {
+    fn from(scheme: Scheme) -> Self {
+        match scheme {
+            Scheme::Default => ColorScheme::Default,
+            Scheme::Colorblind => ColorScheme::Colorblind,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Order {
+    /// By suite, tags, name and case
+    Default,
+    /// By priority, highest first
+    Priority,
+}
+
+impl From<Order> for TestOrder {
+    fn from(order: Order) -> Self {
+        match order {
+            Order::Default => TestOrder::Default,
+            Order::Priority => TestOrder::Priority,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Parallelism {
+    /// Any test can run at the same time as any other
+    Case,
+    /// The tests of each file run one after the other, while files run in parallel
+    File,
+}
+
+impl From<Parallelism> for ParallelBy {
+    fn from(parallelism: Parallelism) -> Self {
+        match parallelism {
+            Parallelism::Case => ParallelBy::Case,
+            Parallelism::File => ParallelBy::File,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum RerunStatus {
+    /// Passed on their first attempt
+    Passed,
+    /// Failed, even after being retried
+    Failed,
+    /// Didn't run
+    Skipped,
+    /// Passed only after being retried
+    Flaky,
+}
+
+impl From<RerunStatus> for LastStatus {
+    fn from(status: RerunStatus) -> Self {
+        match status {
+            RerunStatus::Passed => LastStatus::Passed,
+            RerunStatus::Failed => LastStatus::Failed,
+            RerunStatus::Skipped => LastStatus::Skipped,
+            RerunStatus::Flaky => LastStatus::Flaky,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// Prints the completion script of `cargo testify` for `shell`, which adds to the completion of
+/// `cargo` instead of replacing it. cargo's bash and zsh completions forward the arguments of
+/// `cargo testify` to the completion of `cargo-testify`, so the script is generated for it. Fish
+/// completions add to the ones already loaded for a command, so the script is generated for
+/// `cargo`, with `testify` as its subcommand, and its helper functions are renamed so that they
+/// don't replace the ones of cargo's completion.
+fn print_completions(shell: CompletionShell) {
+    let mut command = CommandArgs::command().name("cargo-testify");
+    let mut script = Vec::new();
+
+    let script = match shell {
+        CompletionShell::Bash => {
+            clap_complete::generate(Shell::Bash, &mut command, "cargo-testify", &mut script);
+
+            // The cases of the subcommands are named after the binary with its `-` replaced by
+            // `__subcmd__`, while the variable they're matched against replaces it with `__`.
+            String::from_utf8_lossy(&script).replace("cargo__subcmd__testify", "cargo__testify")
+        }
+        CompletionShell::Zsh => {
+            clap_complete::generate(Shell::Zsh, &mut command, "cargo-testify", &mut script);
+            String::from_utf8_lossy(&script).into_owned()
+        }
+        CompletionShell::Fish => {
+            let mut command = clap::Command::new("cargo")
+                .disable_help_flag(true)
+                .disable_help_subcommand(true)
+                .subcommand(command.name("testify"));
+
+            clap_complete::generate(Shell::Fish, &mut command, "cargo", &mut script);
+            String::from_utf8_lossy(&script).replace("__fish_cargo_", "__fish_cargo_testify_")
+        }
+    };
+
+    print!("{script}");
+}
+
+/// Exits with an error if the target selected by `--bin` or `--package` is also selected in the
+/// arguments passed to `cargo run`, since it's unclear which one should be used.
+fn check_cargo_args(args: &CommandArgs) {
+    let selections = [
+        (args.bin.is_some(), "--bin", &["--bin"][..]),
+        (args.package.is_some(), "--package", &["--package", "-p"][..]),
+    ];
+
+    for (is_set, flag, cargo_flags) in selections {
+        let conflicting = args.cargo_args.iter().find(|arg| {
+            cargo_flags.iter().any(|cargo_flag| {
+                arg.as_str() == *cargo_flag || arg.starts_with(&format!("{cargo_flag}="))
+            })
+        });
+
+        if let (true, Some(conflicting)) = (is_set, conflicting) {
+            CommandArgs::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    format!("`{flag}` was passed to both `cargo testify` and `cargo run` (as `{conflicting}`). Pass it only once."),
+                )
+                .exit();
+        }
+    }
+}
+
+/// Parses a non-negative percentage, with or without a trailing `%`.
+fn parse_percentage(value: &str) -> Result<f64, String> {
+    match value.strip_suffix('%').unwrap_or(value).parse::<f64>() {
+        Ok(percentage) if percentage.is_finite() && percentage >= 0.0 => Ok(percentage),
+        _ => Err(format!("expected a non-negative percentage, such as `20%`, got `{value}`")),
+    }
+}
+
+/// Parses a `--tag-alias` value, formatted as `<alias>=<tag>`.
+fn parse_tag_alias(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((alias, tag)) if !alias.is_empty() && !tag.is_empty() => {
+            Ok((alias.to_string(), tag.to_string()))
+        }
+        _ => Err(format!("expected `<alias>=<tag>`, got `{value}`")),
+    }
+}
+
+/// Parses a `--tag-timeout` value, formatted as `<tag>=<duration>`.
+fn parse_tag_timeout(value: &str) -> Result<(String, Duration), String> {
+    match value.split_once('=') {
+        Some((tag, duration)) if !tag.is_empty() => Ok((tag.to_string(), parse_duration(duration)?)),
+        _ => Err(format!("expected `<tag>=<duration>`, got `{value}`")),
+    }
+}
+
+/// Parses a `--report` value, formatted as `<kind>:<path>`.
+fn parse_report(value: &str) -> Result<(ReportKind, PathBuf), String> {
+    let report = value.split_once(':').and_then(|(kind, path)| {
+        let kind = Report::from_str(kind, true).ok()?;
+        Some((kind.into(), PathBuf::from(path))).filter(|_| !path.is_empty())
+    });
+
+    report.ok_or_else(|| {
+        format!("expected `<kind>:<path>`, with a kind of human, junit, json or tap, got `{value}`")
+    })
+}
+
+/// Parses a `--shard` value, formatted as `<shard>/<shards>`, where `shard` starts at 1.
+fn parse_shard(value: &str) -> Result<(usize, usize), String> {
+    let shard = value
+        .split_once('/')
+        .and_then(|(shard, shards)| Some((shard.parse().ok()?, shards.parse().ok()?)));
+
+    match shard {
+        Some((shard, shards)) if shard >= 1 && shard <= shards => Ok((shard, shards)),
+        _ => Err(format!(
+            "expected `<shard>/<shards>`, with a shard between 1 and the number of shards, got `{value}`"
+        )),
+    }
+}
+
+/// Adds the comma-separated values of the environment variable `name` to the values passed on the
+/// command line, skipping the ones already passed. Empty values are ignored, and so is the variable
+/// if it isn't set.
+fn merge_env_list(mut values: Vec<String>, name: &str) -> Vec<String> {
+    let Ok(list) = std::env::var(name) else {
+        return values;
+    };
+
+    for value in list.split(',').map(str::trim).filter(|value| !value.is_empty()) {
+        if !values.iter().any(|existing| existing == value) {
+            values.push(value.to_string());
+        }
+    }
+
+    values
+}
+
+/// Returns the program and arguments used to run the tests, from `--runner` or the
+/// `TESTIFY_RUNNER` environment variable, defaulting to `cargo run`. Exits with an error if the
+/// command is empty or its program can't be found.
+fn runner_command(runner: Option<String>) -> Vec<String> {
+    let runner = runner
+        .or_else(|| std::env::var("TESTIFY_RUNNER").ok())
+        .unwrap_or_else(|| "cargo run".to_string());
+
+    let runner: Vec<String> = runner.split_whitespace().map(str::to_string).collect();
+
+    let Some(program) = runner.first() else {
+        CommandArgs::command()
+            .error(ErrorKind::InvalidValue, "The runner command is empty.")
+            .exit();
+    };
+
+    if !program_exists(program) {
+        CommandArgs::command()
+            .error(
+                ErrorKind::InvalidValue,
+                format!("The runner program `{program}` was not found."),
+            )
+            .exit();
+    }
+
+    runner
+}
+
+/// Whether a program can be run, either because it's a path to an existing file or because it's
+/// in one of the `PATH` directories.
+fn program_exists(program: &str) -> bool {
+    let path = Path::new(program);
+
+    if path.components().count() > 1 {
+        return path.is_file();
+    }
+
+    let Some(paths) = std::env::var_os("PATH") else {
+        return false;
+    };
+
+    std::env::split_paths(&paths).any(|dir| {
+        let candidate = dir.join(program);
+        candidate.is_file() || (cfg!(windows) && candidate.with_extension("exe").is_file())
+    })
+}
+
+/// Reads the module path patterns in the `.testifyignore` file of the current directory, if
+/// there's one. Empty lines and lines starting with `#` are skipped.
+fn read_ignore_file() -> Vec<String> {
+    let contents = match std::fs::read_to_string(".testifyignore") {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Vec::new(),
+        Err(e) => CommandArgs::command()
+            .error(ErrorKind::Io, format!("Could not read `.testifyignore`: {e}"))
+            .exit(),
+    };
+
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Reads the test names or IDs in the file passed to `--filter-file`, one per line. Empty lines and
+/// lines starting with `#` are skipped.
+fn read_filter_file(path: &Path) -> Vec<String> {
+    let contents = std::fs::read_to_string(path).unwrap_or_else(|e| {
+        CommandArgs::command()
+            .error(ErrorKind::Io, format!("Could not read `{}`: {e}", path.display()))
+            .exit()
+    });
+
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Reads the IDs of the tests that had `status` in the previous run for `--rerun-status`.
+fn read_last_run(path: &Path, status: LastStatus) -> (LastStatus, Vec<String>) {
+    if !path.exists() {
+        CommandArgs::command()
+            .error(
+                ErrorKind::Io,
+                format!(
+                    "No previous run was found in `{}`. Run the tests once without --rerun-status first.",
+                    path.display()
+                ),
+            )
+            .exit()
+    }
+
+    match last_run::ids_with_status(path, status) {
+        Ok(ids) => (status, ids),
+        Err(message) => CommandArgs::command().error(ErrorKind::Io, message).exit(),
+    }
 }
 
 fn main() -> Result<(), ()> {
@@ -34,29 +757,155 @@ fn main() -> Result<(), ()> {
 
     let args = CommandArgs::parse_from(cli_args);
 
+    if let Some(Commands::Completions { shell }) = args.command {
+        print_completions(shell);
+        return Ok(());
+    }
+
+    check_cargo_args(&args);
+
+    // Backtraces are shown with the failures' details, but the details don't need backtraces.
+    let verbose_failures = args.verbose_failures || args.backtrace;
+
+    let last_run_file = args
+        .last_run_file
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_LAST_RUN_FILE));
+    let rerun = args
+        .rerun_status
+        .map(|status| read_last_run(&last_run_file, status.into()));
+
+    let changed_only = args.changed_tests_only.then(|| {
+        let path = args
+            .test_hashes_file
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_TEST_HASHES_FILE));
+
+        changes::load(&path)
+            .unwrap_or_else(|message| CommandArgs::command().error(ErrorKind::Io, message).exit())
+    });
+
     let config = serde_json::to_string(&TestifyConfig {
-        name_filter: args.test_name,
-        tags: args.tag,
-        exclude_tags: args.exclude_tag,
+        name_filters: args.test_names,
+        tags: merge_env_list(args.tag, "TESTIFY_TAGS"),
+        exclude_tags: merge_env_list(args.exclude_tag, "TESTIFY_EXCLUDE_TAGS"),
+        tag_aliases: args.tag_alias,
+        suites: merge_env_list(args.suite, "TESTIFY_SUITES"),
         fail_fast: args.fail_fast,
+        jobs: args.jobs,
+        list_tags: args.list_tags,
+        format: args.format.map(OutputFormat::from),
+        reports: args.report,
+        compact_json: args.json_compact,
+        verbose: args.verbose,
+        fail_on_warnings: args.fail_on_warnings,
+        tag_summary: args.tag_summary,
+        file_summary: args.file_summary,
+        output_dir: args.output_dir,
+        group_by: args.group_by.into(),
+        backtrace: args.backtrace,
+        verbose_failures,
+        order: args.order.into(),
+        exact: args.exact,
+        use_regex: args.regex,
+        new_tests: args.new_tests,
+        known_tests_file: args.known_tests_file,
+        theme: OutputTheme {
+            duration_precision: args.duration_precision,
+            ..ColorScheme::from(args.color_scheme).theme(args.ascii)
+        },
+        retries: args.retries,
+        strict_flaky: args.strict_flaky,
+        no_setup: args.no_setup,
+        no_cleanup: args.no_cleanup,
+        repeat_until_failure: args.repeat_until_failure,
+        repeat_setup: args.repeat_setup,
+        ignore_patterns: read_ignore_file(),
+        filter_list: args.filter_file.as_deref().map(read_filter_file),
+        shard: args.shard,
+        isolate: args.isolate,
+        skip_negative: args.skip_negative,
+        only_negative: args.only_negative,
+        no_skips: args.no_skips,
+        error_on_empty: args.error_on_empty,
+        save_timings: args.save_timings,
+        fold_timings: args.fold_timings,
+        fresh_runtime: args.fresh_runtime,
+        // Reruns keep the statuses up to date, so that rerunning the failed tests again only runs
+        // the ones that still fail.
+        last_run_file: (args.save_last_run || rerun.is_some()).then_some(last_run_file),
+        rerun,
+        metrics: args.metrics,
+        parallel_by: args.parallel_by.into(),
+        timeout: args.timeout,
+        tag_timeouts: args.tag_timeout,
+        warn_slow: args.warn_slow,
+        dots: args.dots,
+        changed_only,
+        test_hashes_file: args.test_hashes_file,
+        timings_baseline: args.timings_baseline,
+        regression_threshold: args.regression_threshold,
     })
     .expect("Could not serialize testify configuration.");
 
-    let mut command = Command::new("cargo");
+    let runner = runner_command(args.runner);
+    let mut command = Command::new(&runner[0]);
     command.env(testify::TEST_RUNNER_TOGGLE_ENV_VAR_NAME, "true");
-    command.arg("run");
+    command.args(&runner[1..]);
+
+    if let Some(package) = args.package {
+        command.args(["--package", &package]);
+    }
+
+    if let Some(bin) = args.bin {
+        command.args(["--bin", &bin]);
+    }
+
     command.args(args.cargo_args);
     command.env(testify::TEST_RUNNER_CONFIG, config);
 
-    if command
+    if args.backtrace {
+        command.env("RUST_BACKTRACE", "full");
+    }
+
+    if let Some(max_threads) = args.max_threads {
+        command.env(testify::RT_THREADS_ENV_VAR_NAME, max_threads.to_string());
+    }
+
+    // A custom runner may run the binary somewhere this temporary directory isn't, like in a
+    // container, so the marker is only checked with the default `cargo run`.
+    let run_marker = (runner == ["cargo", "run"])
+        .then(|| std::env::temp_dir().join(format!("testify-run-{}", std::process::id())));
+
+    if let Some(run_marker) = &run_marker {
+        let _ = std::fs::remove_file(run_marker);
+        command.env(testify::RUN_MARKER_ENV_VAR_NAME, run_marker);
+    }
+
+    // Ctrl-C reaches the tests' binary too, which stops the run and cleans up. Waiting for it keeps
+    // its output from being printed after the shell's prompt.
+    let _ = ctrlc::set_handler(|| {});
+
+    let success = command
         .spawn()
-        .expect("Failed to run cargo")
+        .unwrap_or_else(|e| panic!("Failed to run `{}`: {e}", runner.join(" ")))
         .wait()
-        .expect("Failed to wait for cargo to finish")
-        .success()
-    {
-        Ok(())
-    } else {
-        Err(())
+        .unwrap_or_else(|e| panic!("Failed to wait for `{}` to finish: {e}", runner.join(" ")))
+        .success();
+
+    let tests_ran = run_marker.as_ref().is_none_or(|run_marker| {
+        let ran = run_marker.exists();
+        let _ = std::fs::remove_file(run_marker);
+        ran
+    });
+
+    // A binary that fails may not have been built at all, so only a successful one is known to have
+    // skipped the tests.
+    if success && !tests_ran {
+        eprintln!(
+            "The tests were not run: the binary exited without calling `testify::run()`. Add `#[testify::main]` to its `main` function, or call `testify::run()` from it."
+        );
+        return Err(());
     }
+
+    if success { Ok(()) } else { Err(()) }
 }
@@ -16,9 +16,9 @@
 //! 
 //! To set up the tests runner, wrap your `main()` function with `#[testify::main]`. This'll expand
 //! to (roughly)
-//! ```
+//! ```ignore
 //! fn main() {
-//!     if std::env::var("DO_NOT_MANUALLY_SET_TESTIFY_ARE_TESTS_BEING_RUN").is_ok() {
+//!     if testify::is_test_run() {
 //!         testify::run();
 //!     } else {
 //!         /* YOUR CODE */
@@ -26,6 +26,10 @@
 //! }
 //! ```
 //! 
+//! If the binary exits successfully without running the tests, such as when `#[testify::main]` is
+//! missing and your own `main` runs instead, `cargo testify` fails with an error saying so instead
+//! of silently running nothing.
+//! 
 //! > *This means that the testing code will be built into your binary from now onwards.
 //! > Suggestions and PRs are welcome to solve this issue.*
 //! 
@@ -39,21 +43,98 @@
 //! enough to get you started in most cases. Testify's test macro supports a wider set of features
 //! than the default one.
 //! 
+//! Tests must be plain functions without arguments, which may be `async`. The macro rejects
+//! `unsafe`, `const` and `extern` functions with a compile error:
+//! 
+//! ```compile_fail
+//! #[testify::test]
+//! unsafe fn my_unsafe_test() {}
+//! ```
+//! 
+//! ```compile_fail
+//! #[testify::test]
+//! const fn my_const_test() {}
+//! ```
+//! 
+//! ```compile_fail
+//! #[testify::test]
+//! extern "C" fn my_extern_test() {}
+//! ```
+//! 
+//! So are functions with arguments, with an error naming the function and how many arguments it
+//! has:
+//! 
+//! ```compile_fail
+//! #[testify::test]
+//! fn my_test(user: &str) {}
+//! ```
+//! 
 //! ### Test Metadata
 //! 
 //! You can organize your tests better by passing some keyword arguments to the test macro (all
 //! optional):
-//! - `name`: A string literal, which allows you to rename the test function to something prettier
-//!     to be outputted in the console when running the tests.
-//! - `case`: A string literal, it allows you to specify different cases of the same unit being
-//!     tested.
-//! - `tags`: An array of string literals, it allows you to tag your tests for easier filtering
-//!     when running your tests with `cargo testify`, opposed to rust's default test suite with its
-//!     substring filtering.
+//! - `name`: A string, which allows you to rename the test function to something prettier to be
+//!   outputted in the console when running the tests. By default, tests are named after
+//!   their function's path relative to the crate's root, such as `auth::rejects_weak_passwords`,
+//!   or just the function's name for tests at the root.
+//! - `case`: A string, it allows you to specify different cases of the same unit being tested.
+//!   Cases can be nested by separating their levels with ` > `, as in `"Admin > Weak Password"`.
+//!   Each level is indented under the previous one, followed by how many of its cases passed.
+//! - `description`: A string literal with a longer explanation of what the test checks, for future
+//!   readers. It's shown under the test with `--verbose`.
+//! - `suite`: A string literal, it allows you to split your tests into suites (such as `unit`,
+//!   `integration` or `e2e`). Tests are grouped by suite first, and then by tags.
+//! - `tags`: An array of strings, it allows you to tag your tests for easier filtering when running
+//!   your tests with `cargo testify`, opposed to rust's default test suite with its substring
+//!   filtering.
+//! - `min_time` and `max_time`: String literals with a duration (such as `"10ms"` or `"1.5s"`),
+//!   which make a passing test fail if it took less than `min_time` or more than `max_time` to
+//!   run. Supported units are `ns`, `us`, `ms`, `s` and `m`. Keep in mind that timings are noisy,
+//!   especially when tests run in parallel, so leave some margin or run them with `-j 1`.
+//! - `timeout`: A string literal with a duration, after which the test fails without waiting for
+//!   it to finish (see [Timeouts](#timeouts)).
+//! - `priority`: An integer, `0` by default. When running with `cargo testify --order priority`,
+//!   tests with a higher priority run first, which combined with `--fail-fast` surfaces failures
+//!   in critical tests sooner. Tests with the same priority keep the default order.
 //! - `should_panic`: As the name says, passing this argument to the test macro will make the test
-//!     execution being expected to panic, and failing if it does not.
+//!   execution being expected to panic, and failing if it does not.
 //! - `should_fail`: Similar to `should_panic`, but for the return types of the test function. In
-//!     this case, `TestTermination.success()` will be expected to return `false`.
+//!   this case, `TestTermination.success()` will be expected to return `false`. It can also be
+//!   given a string literal describing the expected failure (`should_fail = "weak password"`),
+//!   which is shown when the test doesn't fail, and with `-v` when the failure has no message.
+//! - `fresh_runtime`: Runs an async test on a tokio runtime of its own (see
+//!   [Fresh Runtimes](#fresh-runtimes)).
+//! - `flavor`: `"current_thread"` runs an async test on a current-thread runtime of its own,
+//!   instead of the shared multi-thread one (see [Runtime Flavors](#runtime-flavors)).
+//!   `"multi_thread"` is the default.
+//! - `retries` and `retry_backoff`: How many times the test is retried if it fails, and how long to
+//!   wait in between (see [Retries](#retries)).
+//! - `no_captured_output`: Fails the test if it passes, but wrote with `testify::println!` or
+//!   `testify::eprintln!` (see [Test Output](#test-output)).
+//! - `depends_on`: The name of a test that must pass before this one runs, or an array of them
+//!   (see [Dependencies](#dependencies)).
+//! - `slow`: Marks the test as known to be slow, so that `--warn-slow` doesn't warn about it (see
+//!   [Slow Tests](#slow-tests)).
+//! 
+//! `name`, `case`, `tags` and `depends_on` take string literals, but also any expression
+//! evaluating to a `String` or a `&str`, such as a constant or a `format!` call. Literals are
+//! embedded as they are, while expressions are evaluated when the test is registered, on startup.
+//! Since registration happens outside of the test function, expressions can only use what's
+//! reachable from the module's scope (constants, statics and functions), and not the test's local
+//! variables:
+//! 
+//! ```ignore
+//! const MODULE: &str = "auth";
+//! 
+//! #[testify::test(
+//!     name = format!("{MODULE}: weak passwords"),
+//!     tags = [MODULE, concat!("sec", "urity")],
+//! )]
+//! fn rejects_weak_passwords() {}
+//! ```
+//! 
+//! `should_panic` and `should_fail` are also available on the registered `Test`s, so runners and
+//! observers can tell which tests are expected to fail.
 //! 
 //! #### Example
 //! 
@@ -71,6 +152,35 @@
 //! }
 //! ```
 //! 
+//! ### Dependencies
+//! 
+//! Tests are meant to be independent, but when one only makes sense after another, such as a test
+//! deleting what another created, `depends_on` makes it run after the tests it names. The tests
+//! are given by their name, their path or their ID, and a name without a case refers to all of the
+//! test's cases:
+//! 
+//! ```ignore
+//! #[testify::test(name = "Create User")]
+//! fn create_user() {}
+//! 
+//! #[testify::test(name = "Delete User", depends_on = "Create User")]
+//! fn delete_user() {}
+//! 
+//! #[testify::test(name = "Audit Log", depends_on = ["Create User", "Delete User"])]
+//! fn audit_log() {}
+//! ```
+//! 
+//! A test is moved right after the last of its dependencies, even if that takes it out of its
+//! group, and if any of them fails or is skipped, it's skipped as well. It's shown as skipped,
+//! listed in the reports with the `skipped` status and counted among the tests that didn't run, so
+//! `--no-skips` fails the run. When running in parallel, the tests that depend on each other run
+//! on the same thread, one after the other, and with `--shard` they're given to the same shard.
+//! 
+//! Dependencies only order the tests that run: if a dependency is filtered out, the test runs
+//! without it. Dependencies that don't match any test are reported as warnings, and dependencies
+//! forming a cycle, such as two tests depending on each other, make the run fail before any test
+//! runs, like a setup that panicked.
+//! 
 //! ### Async Support
 //! 
 //! Tests support async functions out of the box with the `async-tokio` feature. It's as easy as
@@ -83,39 +193,199 @@
 //! }
 //! ```
 //! 
+//! Async functions run through the `testify::AsyncRuntime` trait, which is implemented for tokio's
+//! runtime. To run them on another runtime, implement the trait for it and pass it to
+//! `testify::set_async_runtime()` before anything runs on the default one, such as from a non-async
+//! setup. Without the `async-tokio` feature there's no default runtime, so one must be set for
+//! async functions to run. Runtimes with their own timers can also override `AsyncRuntime::sleep`,
+//! which `wait_until_async` waits with, as tokio's runtime does.
+//! 
+//! ```ignore
+//! struct SmolRuntime;
+//! 
+//! impl testify::AsyncRuntime for SmolRuntime {
+//!     fn block_on_boxed(&self, future: testify::runtime::BoxedFuture<'_>) {
+//!         smol::block_on(future);
+//!     }
+//! }
+//! 
+//! #[testify::setup]
+//! fn setup() {
+//!     testify::set_async_runtime(SmolRuntime);
+//! }
+//! ```
+//! 
+//! #### Fresh Runtimes
+//! 
+//! Async tests share one runtime, so tasks and timers a test leaves behind keep running during the
+//! tests after it. To isolate a test, mark it with `fresh_runtime`: it runs on a new tokio runtime,
+//! which is shut down when the test ends, cancelling whatever the test spawned. Pass
+//! `--fresh-runtime` to `cargo testify` to do so for every async test.
+//! 
+//! ```ignore
+//! #[testify::test(fresh_runtime)]
+//! async fn reconnects() {
+//!     let client = Client::connect(URL).await.unwrap(); // Spawns a background task.
+//!     assert!(client.reconnect().await.is_ok());
+//! }
+//! ```
+//! 
+//! Building a runtime starts its worker threads, which takes longer than most small tests, so it's
+//! best kept for the tests that need it. Fresh runtimes are always tokio runtimes, built like the
+//! default one, even if another runtime was set with `set_async_runtime()`.
+//! 
+//! #### Runtime Flavors
+//! 
+//! Like `#[tokio::test(flavor = ...)]`, `flavor = "current_thread"` runs a test on a new
+//! current-thread tokio runtime instead of the shared multi-thread one, inside a `LocalSet`. The
+//! test's own future doesn't need to be `Send` with either flavor, since it's run with `block_on`,
+//! but the tasks it spawns with `tokio::spawn` do, and only current-thread tests can spawn `!Send`
+//! tasks with `tokio::task::spawn_local`. On the other hand, everything runs on the test's thread,
+//! so code that blocks it, or that needs another worker thread such as
+//! `tokio::task::block_in_place`, only works with the default `"multi_thread"` flavor. Like fresh
+//! runtimes, the runtime is shut down when the test ends.
+//! 
+//! ```ignore
+//! #[testify::test(flavor = "current_thread")]
+//! async fn shares_state_between_local_tasks() {
+//!     let state = Rc::new(RefCell::new(Vec::new()));
+//!     let task = tokio::task::spawn_local({
+//!         let state = state.clone();
+//!         async move { state.borrow_mut().push(1) }
+//!     });
+//! 
+//!     task.await.unwrap();
+//!     assert_eq!(*state.borrow(), [1]);
+//! }
+//! ```
+//! 
+//! ### Tests in `impl` Blocks
+//! 
+//! Related tests can be grouped as methods of a type by marking its `impl` block with
+//! `#[testify::tests]`. Every method marked with `#[testify::test]` is registered as a test, with
+//! the same arguments as free test functions. The type must implement `Default`: a new instance is
+//! created with `Default::default()` for every test that takes `&self`, `&mut self` or `self`, so
+//! tests don't share state. Methods without a receiver are called as associated functions. The
+//! attribute is also recognized as `#[test]`, after `use testify::test` or not, since `std`'s
+//! `#[test]` can't be used on methods. Other attributes named `test`, such as `#[tokio::test]`,
+//! are left as they are.
+//! 
+//! ```ignore
+//! #[derive(Default)]
+//! struct Cart {
+//!     items: Vec<String>,
+//! }
+//! 
+//! #[testify::tests]
+//! impl Cart {
+//!     #[testify::test(name = "Empty cart")]
+//!     fn empty(&self) {
+//!         assert!(self.items.is_empty());
+//!     }
+//! 
+//!     #[testify::test(name = "Add item")]
+//!     async fn add_item(&mut self) {
+//!         self.items.push("apple".to_string());
+//!         assert_eq!(self.items.len(), 1);
+//!     }
+//! }
+//! ```
+//! 
+//! Generic `impl` blocks aren't supported, and neither are methods with arguments other than
+//! `self`:
+//! 
+//! ```compile_fail
+//! #[derive(Default)]
+//! struct Cart;
+//! 
+//! #[testify::tests]
+//! impl Cart {
+//!     #[testify::test]
+//!     fn add_item(&mut self, item: &str) {}
+//! }
+//! ```
+//! 
 //! ### The `TestTermination` Trait
 //! 
-//! All your tests' return type must implement `TestTermination`. It's a simple trait that only has
-//! one method, `success() -> bool`, which returns whether the test has failed or not. There are
-//! some provided default implementations, but you're free to implement yours if the default
-//! options do not fit your use case.
+//! All your tests' return type must implement `TestTermination`. It's a simple trait that only
+//! requires one method, `success() -> bool`, which returns whether the test has failed or not.
+//! Optionally, `failure_message() -> Option<String>` can explain why the test failed, and it'll be
+//! displayed under the failing test. There are some provided default implementations, but you're
+//! free to implement yours if the default options do not fit your use case.
+//! 
+//! With `-vv`, the whole value returned by a failing test is printed as well, using
+//! `debug_value() -> Option<String>`. It isn't required, so the returned type doesn't need to
+//! implement `Debug`. The default implementations provide it for every type they wrap, except for
+//! the successful values inside `Ok` and `Some`, which are only printed if their own type
+//! provides it. For example, a test returning `Result<(), E>` doesn't print anything for `Ok(())`
+//! (which can't fail anyway), but prints `Err(...)` with `E`'s pretty `Debug` representation.
+//! 
+//! Passing tests can report something too, such as how many records they validated, with
+//! `success_detail() -> Option<String>`. It's displayed after the `Ok.` of the test, as in
+//! `Ok. (12ms) - validated 1500 records`. `Result` and `Option` report the detail of their
+//! successful value, and tuples the details of all their elements.
 //! 
 //! #### Default Implementations
 //! 
 //! The trait is implemented by default for:
 //! 
-//! - `Result<T: TestTermination, E>`: This'll fail in case of an error, otherwise run `.success()`
-//!     for the returned value and return it.
+//! - `Result<T: TestTermination, E: Debug + 'static>`: This'll fail in case of an error, otherwise
+//!   run `.success()` for the returned value and return it. The error is displayed with its
+//!   `Debug` representation, so it doesn't need to implement `Display`. Errors without a message,
+//!   such as `()`, are reported as such. `std::io::Error`s are displayed with their kind and
+//!   `Display` instead, such as `NotFound: No such file or directory (os error 2)`.
 //! - `Option<T: TestTermination>`: This'll fail if `None`, otherwise run `.success()` for the
-//!     returned value and return it.
+//!   returned value and return it.
 //! - `()`: This will always return true.
+//! - `bool`: This'll fail if `false`.
+//! - `std::process::ExitCode`: This'll fail if it's not `ExitCode::SUCCESS`.
+//! - Tuples of up to 4 `TestTermination` values: This'll fail if any of them fails, displaying the
+//!   message of the first failing one.
+//! 
+//! These compose, so a test can return `Result<Option<bool>, E>` or `Result<(ExitCode, bool), E>`
+//! and pass only if the value inside passes as well.
 //! 
 //! #### Example
 //! 
-//! ```
+//! ```ignore
 //! use testify::TestTermination;
 //! 
 //! // This is how the trait is implemented for this type internally.
-//! impl<T: TestTermination, E> TestTermination for Result<T, E> {
+//! impl<T: TestTermination, E: Debug> TestTermination for Result<T, E> {
 //!     fn success(&self) -> bool {
 //!         match self {
 //!             Ok(inner) => inner.success(),
 //!             Err(_) => false
 //!         }
 //!     }
+//! 
+//!     fn failure_message(&self) -> Option<String> {
+//!         match self {
+//!             Ok(inner) => inner.failure_message(),
+//!             Err(e) => Some(format!("{e:?}"))
+//!         }
+//!     }
+//! }
+//! ```
+//! 
+//! ## Benchmarks
+//! 
+//! For quick timing numbers, mark a function with `#[testify::bench]` instead. Its body runs
+//! `iterations` times (100 by default), and instead of passing, it's shown with the mean and the
+//! median time per iteration, leaving out the slowest and the fastest iterations. It takes the same
+//! arguments as `#[testify::test]` other than `should_panic`, `should_fail`, `min_time` and
+//! `max_time`, and it can't return a value. A benchmark that panics fails like a test would.
+//! 
+//! ```ignore
+//! #[testify::bench(name = "Parse config", iterations = 1000)]
+//! fn bench_parse_config() {
+//!     std::hint::black_box(parse_config(CONFIG));
 //! }
 //! ```
 //! 
+//! It's meant for rough measurements next to the tests. For statistically sound benchmarks, use a
+//! dedicated tool such as criterion.
+//! 
 //! ## The `#[testify::setup]` and `#[testify::cleanup]` Macros
 //! 
 //! These two macros allow you to set up the test environment before the execution of the tests,
@@ -123,7 +393,7 @@
 //! 
 //! ### Example
 //! 
-//! ```
+//! ```ignore
 //! #[testify::main]
 //! fn main() {}
 //! 
@@ -143,9 +413,317 @@
 //! }
 //! ```
 //! 
+//! The cleanup function runs even if the test run stops unexpectedly after the setup, such as when
+//! a panic escapes a test (for example, from the `Drop` implementation of its return value), or
+//! when the run is interrupted with Ctrl-C, which stops it before the next test. Pressing Ctrl-C
+//! a second time exits right away, skipping the cleanup. It can't run if the process is aborted
+//! or killed, though.
+//! 
 //! There's no need to have both a setup and a cleanup function either. You may use them
-//! individually. Both `setup` and `cleanup` functions support both sync and async (with the
-//! `async-tokio` feature enabled).
+//! individually. Both `setup` and `cleanup` functions support both sync and async.
+//! A function that isn't `async` but returns `impl Future<Output = ()>`, such as one that hands
+//! off to an async helper, is awaited too.
+//! 
+//! ```ignore
+//! #[testify::setup]
+//! fn setup() -> impl Future<Output = ()> {
+//!     db::start_test_instance()
+//! }
+//! ```
+//! 
+//! While iterating on a single test, the setup or cleanup may take longer than the test itself.
+//! Pass `--no-setup` or `--no-cleanup` to `cargo testify` to skip them, including the tag-scoped
+//! ones below. Testify warns you when it does, since tests that depend on the setup may fail.
+//! 
+//! If a setup panics, the setups after it and all the tests are skipped, since they'd fail for
+//! reasons unrelated to them, but the cleanup still runs. If a cleanup panics, the other cleanups
+//! still run. Either way, the panic message is shown and the run fails, without losing the results
+//! of the tests that already ran.
+//! 
+//! ### Cleaning Up Based on the Results
+//! 
+//! A cleanup can call `testify::last_report()` to tell how the tests went, such as to keep a test
+//! database around for inspection when a test failed. It returns the `TestReport` of the run once
+//! its tests finished running, and `None` before then. The report has:
+//! - `outcomes`: The tests that ran, with their results, durations and number of attempts, and the
+//!   ones skipped because a test they depend on didn't pass.
+//! - `failures()`, `successes()`, `flaky()` and `skipped_dependents()`: How many tests failed,
+//!   passed, passed only after being retried, and were skipped because of their dependencies.
+//! - `skipped`, `ignored` and `not_run`: How many tests were filtered out, ignored by
+//!   `.testifyignore`, or not run because the run stopped early or their dependencies failed.
+//! - `warnings` and `setup_error`: The warnings emitted and the setup's panic message, or the
+//!   cycle formed by the tests' dependencies, if any.
+//! 
+//! Since the report is taken before the cleanup runs, its `cleanup_error` is always `None`. The
+//! tag- and suite-scoped cleanups run while the tests are still running, so they can't use it.
+//! 
+//! ```ignore
+//! #[testify::cleanup]
+//! fn cleanup() {
+//!     if testify::last_report().is_some_and(|report| report.failures() > 0) {
+//!         println!("Keeping the test database for inspection.");
+//!     } else {
+//!         drop_test_database();
+//!     }
+//! }
+//! ```
+//! 
+//! ### Tag-Scoped Setup and Cleanup
+//! 
+//! Passing `tags` to `setup` or `cleanup` scopes them to the tests with any of those tags: the
+//! setup runs right before the first of those tests, and the cleanup right after the last one. If
+//! none of them is run, neither are they. There can be any number of tag-scoped functions, next to
+//! the global `setup` and `cleanup`.
+//! 
+//! ```ignore
+//! #[testify::setup(tags = ["db"])]
+//! async fn setup_db() {
+//!     start_test_db().await;
+//! }
+//! 
+//! #[testify::cleanup(tags = ["db"])]
+//! async fn cleanup_db() {
+//!     stop_test_db().await;
+//! }
+//! ```
+//! 
+//! When tests run in parallel, tag-scoped setups run before any test starts instead, since the
+//! tests with their tags may start at any time.
+//! 
+//! ### Suite-Scoped Setup and Cleanup
+//! 
+//! Passing `suite` instead scopes them to the tests of that suite, the same way: the setup runs
+//! right before the first test of the suite, and the cleanup right after the last one. A function
+//! can be scoped to `tags` or to a `suite`, but not both.
+//! 
+//! ```ignore
+//! #[testify::setup(suite = "integration")]
+//! async fn start_server() {
+//!     spawn_test_server().await;
+//! }
+//! ```
+//! 
+//! The global setups run first. Before a test, the setups of its suite run before the ones of its
+//! tags, and after it, the cleanups of its tags run before the ones of its suite. The global
+//! cleanups run last.
+//! 
+//! ### Ordering
+//! 
+//! There can be several setup and cleanup functions. Functions are registered when the binary
+//! starts, in an order that isn't specified, so set their `order` to run them in a given order:
+//! they run from the lowest `order` to the highest, which is 0 by default and may be negative. The
+//! same applies to cleanups, and to the tag-scoped functions that run at the same time. Functions
+//! with the same `order` run in an unspecified order.
+//! 
+//! ```ignore
+//! #[testify::setup(order = 1)]
+//! async fn start_db() {
+//!     start_test_db().await;
+//! }
+//! 
+//! #[testify::setup(order = 2)]
+//! async fn seed_db() {
+//!     insert_fixtures().await;
+//! }
+//! ```
+//! 
+//! ### Background Services
+//! 
+//! Tasks spawned from an async setup keep running on the runtime, but their handles would be lost
+//! when the setup returns. To start something that must stay alive for the whole run, such as a
+//! server the tests send requests to, spawn it with `testify::spawn_service` instead. The services are
+//! stopped after the cleanup, in the reverse order they were started, so the cleanup can still use
+//! them. They're also available as `testify::shutdown_services` to stop them earlier. Each service
+//! runs on a thread of its own, on the async runtime, and is stopped the next time it yields.
+//! 
+//! ```ignore
+//! #[testify::setup]
+//! async fn setup() {
+//!     let listener = tokio::net::TcpListener::bind("127.0.0.1:8080").await.unwrap();
+//!     testify::spawn_service(async move { serve(listener).await });
+//! }
+//! ```
+//! 
+//! ### Waiting for Readiness
+//! 
+//! A service started by a setup may take a while to be ready, and tests could otherwise race ahead
+//! of it. `testify::wait_until` calls a closure every 10ms until it returns `true`, returning a
+//! `WaitTimeout` error if it doesn't within the given timeout. `testify::wait_until_every` takes
+//! the interval in between calls as well. They're also available as `wait_until_async` and
+//! `wait_until_every_async` for closures returning a future, which wait without blocking the
+//! runtime.
+//! 
+//! ```ignore
+//! #[testify::setup]
+//! async fn setup() {
+//!     testify::spawn_service(serve());
+//!     testify::wait_until_async(|| async { ping().await.is_ok() }, Duration::from_secs(5))
+//!         .await
+//!         .expect("The server didn't start");
+//! }
+//! ```
+//! 
+//! ## Test Output
+//! 
+//! Testify doesn't capture what tests print with `println!`, since redirecting stdout needs
+//! unstable or platform-specific APIs, and it would mix the output of tests running in parallel.
+//! Instead, tests can opt in by printing with `testify::println!` and `testify::eprintln!`, which
+//! write to a buffer of the test running on the current thread. The buffer is only shown if the
+//! test fails, under its result, and it always belongs to the right test, even in parallel.
+//! 
+//! ```ignore
+//! #[testify::test]
+//! fn test_sync() {
+//!     let changes = sync();
+//!     testify::println!("Synced {} changes", changes.len());
+//!     assert!(changes.iter().all(Change::is_valid));
+//! }
+//! ```
+//! 
+//! The tradeoff is that only these macros are captured: `std::println!`, code in dependencies and
+//! tasks spawned on other threads still print straight to the terminal. Outside of a test, such as
+//! in a setup, the macros print to stdout and stderr like their `std` counterparts.
+//! 
+//! For strict tests, `#[testify::test(no_captured_output)]` fails the test if it writes anything
+//! with these macros, such as a debugging line left behind. As its name says, it only checks the
+//! captured output: a stray `std::println!` or `dbg!` writes to stdout or stderr directly and isn't
+//! caught, and neither is the output of a test that failed anyway.
+//! 
+//! ```ignore
+//! #[testify::test(no_captured_output)]
+//! fn test_parser() {
+//!     let tree = parse("1 + 2");
+//!     assert_eq!(tree.eval(), 3);
+//! }
+//! ```
+//! 
+//! ## Test Artifacts
+//! 
+//! Tests that produce debug artifacts, such as screenshots or dumps, can write them to the
+//! directory returned by `testify::artifact_dir()`. Each test case gets its own directory, named
+//! after its suite, name and case, which is created the first time it's requested. Characters that
+//! aren't letters, digits, `-` or `_` are percent-encoded in the names, so `Render page` becomes
+//! `Render%20page`, and tests without a suite are under a `%` directory. If a test fails, the path
+//! to its artifacts is printed under it.
+//! 
+//! ```ignore
+//! #[testify::test(name = "Render page")]
+//! fn test_render_page() {
+//!     let screenshot = render_page();
+//!     std::fs::write(testify::artifact_dir().join("page.png"), screenshot).unwrap();
+//! }
+//! ```
+//! 
+//! The directories are created inside the run's output directory, which you can set with
+//! `cargo testify --output-dir <dir>`. By default, a new directory is created for every run in the
+//! system's temporary directory. `artifact_dir()` must be called from the test's own thread.
+//! 
+//! ## Panic Hooks
+//! 
+//! Testify installs its own panic hook when the tests start running, replacing any hook set with
+//! `std::panic::set_hook`. To react to panics anyway, such as to log them, add your hook with
+//! `testify::chain_panic_hook()`. It'll be called for every panic, after testify's own hook, until
+//! it's removed by passing the handle `chain_panic_hook()` returns to
+//! `testify::unchain_panic_hook()`.
+//! 
+//! ```ignore
+//! #[testify::setup]
+//! fn setup() {
+//!     testify::chain_panic_hook(Box::new(|info| eprintln!("Panicked: {info}")));
+//! }
+//! ```
+//! 
+//! ## Assertions
+//! 
+//! Besides the standard `assert!` macros, `testify::assert_matches!` asserts that a value matches
+//! a pattern, like the unstable `std::assert_matches!` but on stable Rust. When it doesn't, the
+//! expected pattern and the actual value are shown under the failing test.
+//! 
+//! ```ignore
+//! testify::assert_matches!(parse("42"), Ok(Token::Number(n)) if n > 0);
+//! ```
+//! 
+//! ### Asserting Async Timeouts
+//! 
+//! With the `async-tokio` feature, `testify::assert_completes_within!` awaits a future and fails
+//! the test if it doesn't complete within a duration, evaluating to the future's output otherwise.
+//! It only times that future, unlike the `timeout` argument, which covers the whole test. It relies
+//! on tokio's timers, so it must be used inside an async test running on tokio, as they do on
+//! `ASYNC_RT` by default.
+//! 
+//! ```ignore
+//! #[testify::test]
+//! async fn caches_users() {
+//!     let user = testify::assert_completes_within!(Duration::from_millis(100), cache.get("user"));
+//!     assert_eq!(user.name, "Alice");
+//! }
+//! ```
+//! 
+//! ### Ending Tests Early
+//! 
+//! `testify::fail!` stops a test right away and fails it, with an optional message formatted like
+//! `format!`, and `testify::pass!()` stops it and passes it. They save threading a `Result` out of
+//! deeply nested code. They aren't panics, so they don't count as one for `should_panic`, and a
+//! test with `should_fail` passes when it calls `fail!`. They unwind out of the test, so they only
+//! work directly in a test's body or in the functions it calls, not in tasks it spawns, and not
+//! with `panic = "abort"`.
+//! 
+//! ```ignore
+//! #[testify::test]
+//! fn checks_every_user() {
+//!     for user in users() {
+//!         if user.email.is_empty() {
+//!             testify::fail!("{} has no email", user.name);
+//!         }
+//!     }
+//! }
+//! ```
+//! 
+//! ## Registering Tests at Runtime
+//! 
+//! Tests can also be registered at runtime with `register_test!`, from a name and a closure
+//! returning a `TestTermination`. This is useful to generate tests dynamically, such as one per
+//! file in a directory. Tests must be registered before they start running, so call it from a
+//! `#[testify::setup]` function.
+//! 
+//! ```ignore
+//! #[testify::setup]
+//! fn setup() {
+//!     for path in std::fs::read_dir("fixtures").unwrap() {
+//!         let path = path.unwrap().path();
+//! 
+//!         testify::register_test!(format!("Parse {}", path.display()), move || {
+//!             parse(&std::fs::read_to_string(&path).unwrap())
+//!         });
+//!     }
+//! }
+//! ```
+//! 
+//! Registering them in `main` before calling `testify::run()` works too, instead of using
+//! `#[testify::main]`:
+//! 
+//! ```ignore
+//! fn main() {
+//!     if testify::is_test_run() {
+//!         register_fixture_tests();
+//!         testify::run();
+//!     }
+//! }
+//! ```
+//! 
+//! ## Detecting Test Runs
+//! 
+//! `testify::is_test_run()` returns whether the binary was started by `cargo testify` to run the
+//! tests, the same check `#[testify::main]` does to decide whether to run them. Library code can use
+//! it to behave differently under test, such as using an in-memory backend instead of a real one.
+//! 
+//! ```ignore
+//! let storage: Box<dyn Storage> = if testify::is_test_run() {
+//!     Box::new(MemoryStorage::default())
+//! } else {
+//!     Box::new(DiskStorage::open("data")?)
+//! };
+//! ```
 //! 
 //! ## Using `cargo testify`
 //! 
@@ -153,7 +731,7 @@
 //! you to configure the way in which your tests are run. In case you haven't installed it yet, run
 //! `cargo install testify-rs` to set it up.
 //! 
-//! ```
+//! ```sh
 //! $ cargo testify --help
 //! ```
 //! 
@@ -163,32 +741,638 @@
 //! name. Testify goes a bit further by allowing you to use glob pattern matching to filter by
 //! name.
 //! 
-//! ```
+//! ```sh
 //! $ cargo testify hello*
 //! ```
 //! 
+//! Several patterns can be passed at once, in which case the tests matching any of them are run.
+//! 
+//! ```sh
+//! $ cargo testify "hello*" "*world"
+//! ```
+//! 
+//! To match a test by its exact name, such as one containing `*` or `[`, pass `--exact`. A
+//! specific case can be selected as `"<name> (<case>)"`, the way it's shown in the output.
+//! 
+//! ```sh
+//! $ cargo testify --exact "Hello world!" "Register User (Weak Password)"
+//! ```
+//! 
+//! Every test also has a path, made of the module it's defined in (relative to the crate's root),
+//! its name, and its case, as in `auth::Register User (Weak Password)`. It tells apart tests with
+//! the same name in different modules, and it's shown for every test with `-v` and for each
+//! failure in the recap, so it can be copied to rerun a single test. Like names, a path without
+//! its case selects all of the test's cases:
+//! 
+//! ```sh
+//! $ cargo testify --exact "auth::Register User (Weak Password)"
+//! ```
+//! 
+//! To filter with regular expressions instead, pass `--regex`. Unlike glob patterns, which have to
+//! match the whole name, a regular expression matches any part of it unless it's anchored with `^`
+//! and `$`. The syntax is the one of the [`regex`](https://docs.rs/regex) crate.
+//! 
+//! ```sh
+//! $ cargo testify --regex "^(Register|Delete) User$" "v[0-9]+"
+//! ```
+//! 
 //! ### Filtering by Tag
 //! 
 //! You can also filter by the tags you've set in your tests by passing the `--tag` argument to the
 //! `cargo testify` command.
 //! 
-//! ```
-//! // Both --tag and -t do the same
+//! ```sh
+//! # Both --tag and -t do the same
 //! $ cargo testify --tag auth -t api
 //! ```
 //! 
 //! You can also exclude tags by passing the `--exclude-tag` argument:
 //! 
-//! ```
-//! // -e for the shortcut
+//! ```sh
+//! # -e for the shortcut
 //! $ cargo testify --exclude-tag db
 //! ```
 //! 
+//! When tags get renamed, `--tag-alias <alias>=<tag>` makes the filters using the alias match the
+//! tests tagged with the other tag as well, so tests don't need to be renamed all at once. It can
+//! be passed several times. Aliases only expand the tags passed to `--tag` and `--exclude-tag`: the
+//! tags of the tests aren't changed, and filtering by the aliased tag doesn't match the alias.
+//! 
+//! ```sh
+//! # Runs the tests tagged either `auth` or `authentication`.
+//! $ cargo testify --tag-alias auth=authentication --tag auth
+//! ```
+//! 
+//! For CI jobs that compute which tests to run, the tags can also be set in the `TESTIFY_TAGS` and
+//! `TESTIFY_EXCLUDE_TAGS` environment variables, and the suites in `TESTIFY_SUITES`, as
+//! comma-separated lists. They're added to the ones passed on the command line instead of replacing
+//! them, so `TESTIFY_TAGS=api` with `--tag auth` only runs the tests tagged with both, the same as
+//! `--tag api --tag auth`. Unset the variables to filter with the command line alone.
+//! 
+//! ```sh
+//! $ TESTIFY_TAGS=api,auth TESTIFY_EXCLUDE_TAGS=slow cargo testify --tag smoke
+//! ```
+//! 
+//! Testify will warn you if a tag passed to `--tag` or `--exclude-tag` isn't used by any test,
+//! suggesting the closest existing tag in case it was a typo. You can also declare the tags used
+//! in your project with `register_tags!`, in which case tests using an undeclared tag will be
+//! warned about as well.
+//! 
+//! ```ignore
+//! testify::register_tags!(["api", "auth", "db"]);
+//! ```
+//! 
+//! To see which tags exist, pass `--list-tags`. It prints every tag (including the registered
+//! ones) with the number of tests using it, one per line as `<tag>\t<count>`, and exits without
+//! running any test.
+//! 
+//! ```sh
+//! $ cargo testify --list-tags
+//! ```
+//! 
+//! To see how many tests passed and failed for each tag after a run, pass `--tag-summary`. A test
+//! with several tags is counted under each of them. When running tests programmatically, the same
+//! counts are available from `TestReport::tag_summary`.
+//! 
+//! ```sh
+//! $ cargo testify --tag-summary
+//! ```
+//! 
+//! Similarly, `--by-file` prints how many tests passed and failed in each source file, which helps
+//! finding files with few tests. The counts are available from `TestReport::file_summary` as well.
+//! Tests registered with `register_test!` are counted under the file they were registered from.
+//! 
+//! ```sh
+//! $ cargo testify --by-file
+//! ```
+//! 
+//! ### Grouping
+//! 
+//! Tests are shown grouped by their set of tags, so a test tagged `["api", "slow"]` and one tagged
+//! `["api"]` end up in different groups. Pass `--group-by first-tag` to group them by their first
+//! tag instead, or `--group-by none` to show a flat list of tests. Tests are still grouped by
+//! suite either way.
+//! 
+//! ```sh
+//! $ cargo testify --group-by first-tag
+//! ```
+//! 
+//! ### Filtering by Suite
+//! 
+//! To only run the tests in a suite, pass the `--suite` (`-s`) argument. Passing it several times
+//! runs the tests in any of the given suites.
+//! 
+//! ```sh
+//! $ cargo testify --suite integration
+//! ```
+//! 
+//! ### Filtering Negative Tests
+//! 
+//! Tests marked with `should_panic` or `should_fail` check that something fails. Pass
+//! `--skip-negative` to leave them out of the run, or `--only-negative` to only run them.
+//! 
+//! ```sh
+//! $ cargo testify --skip-negative
+//! ```
+//! 
+//! ### Filter Files
+//! 
+//! To run a list of tests generated by another tool, such as a CI job splitting the tests between
+//! machines, pass `--filter-file <PATH>`. The file lists test names, paths or IDs (`<suite>/<name>
+//! (<case>)`, as shown by `--new-tests`), one per line, and only the tests matching any of them
+//! run. Empty lines and lines starting with `#` are skipped. It's combined with the other filters,
+//! so a test also has to match those to run.
+//! 
+//! ```sh
+//! $ cargo testify --filter-file tests-to-run.txt --tag api
+//! ```
+//! 
+//! ### Sharding
+//! 
+//! To split the tests between several machines, pass `--shard <SHARD>/<SHARDS>` to each of them,
+//! such as `--shard 1/4` to `--shard 4/4`. After filtering and sorting the tests, they're dealt
+//! round-robin to the shards, so each one gets about the same number of tests and a similar mix of
+//! suites and priorities. Tests that depend on each other are dealt together, so that they run in
+//! order on the same shard. The split only depends on the tests that pass the filters, so every
+//! machine has to run the same binary with the same filters. Adding or removing a test may move
+//! others to a different shard.
+//! 
+//! ```sh
+//! $ cargo testify --shard 2/4
+//! ```
+//! 
+//! ### Ignoring Modules
+//! 
+//! To never run the tests of some modules, such as experimental ones, list their paths in a
+//! `.testifyignore` file in the directory `cargo testify` is run from, one glob pattern per line.
+//! Lines starting with `#` are comments. A pattern matching a module also ignores its submodules.
+//! 
+//! ```text
+//! # Tests that are still being written.
+//! my_crate::experimental
+//! my_crate::*::wip
+//! ```
+//! 
+//! Tests are registered when the binary starts, so the ignored tests are still compiled and
+//! registered: they're only left out when the runner selects which tests to run, and are counted
+//! as ignored. To keep them out of the binary, put their module behind a `#[cfg(...)]` instead.
+//! 
 //! ### Fast Failing
 //! 
 //! If you only care about whether all tests pass or not, you can pass the `--fail-fast` argument.
 //! This'll stop testing on the first test that fails. You'll see a `Failed! Aborted.` next to the
-//! failing test, in case there's any.
+//! failing test, in case there's any. When tests run in parallel, the ones that already started
+//! are left to finish, but no other test is started.
+//! 
+//! To run the most important tests first, set their `priority` and pass `--order priority`:
+//! 
+//! ```sh
+//! $ cargo testify --order priority --fail-fast
+//! ```
+//! 
+//! ### Ensuring Every Test Runs
+//! 
+//! For release gating, pass `--no-skips` to fail the run unless every selected test ran. It covers:
+//! - Tests in modules ignored by `.testifyignore`.
+//! - Tests that weren't reached because `--fail-fast` stopped the run.
+//! - Tests skipped because a test they depend on didn't pass (see [Dependencies](#dependencies)).
+//! 
+//! Tests left out by the filters passed on the command line, such as `--tag`, `--suite`,
+//! `--skip-negative` or `--shard`, aren't covered, since they were left out on purpose.
+//! 
+//! ```sh
+//! $ cargo testify --no-skips
+//! ```
+//! 
+//! ### Empty Runs
+//! 
+//! If no tests are registered, such as in the middle of a refactor, the setup and cleanup are
+//! skipped, since they could only have side effects, and the run exits successfully. Pass
+//! `--error-on-empty` to make it fail instead:
+//! 
+//! ```sh
+//! $ cargo testify --error-on-empty
+//! ```
+//! 
+//! If the binary calls `register_test!` anywhere, the setup may register the tests, so it runs even
+//! when there are none yet (see [Registering Tests at Runtime](#registering-tests-at-runtime)).
+//! `--error-on-empty` still fails the run if it doesn't register any.
+//! 
+//! ### Timeouts
+//! 
+//! Pass `--timeout <DURATION>` to fail the tests that run for longer than a duration, such as
+//! `10s`, instead of hanging the whole run. Since some tests are slower by nature, a tag can have a
+//! timeout of its own with `--tag-timeout <TAG>=<DURATION>`, which can be passed several times. The
+//! timeout of a test is the first of:
+//! 
+//! 1. Its own `timeout`, as in `#[testify::test(timeout = "1m")]`.
+//! 2. The longest timeout of its tags, from `--tag-timeout`.
+//! 3. The `--timeout` of the run.
+//! 
+//! Tests without any of them run without a timeout.
+//! 
+//! ```sh
+//! $ cargo testify --timeout 10s --tag-timeout integration=30s --tag-timeout e2e=2m
+//! ```
+//! 
+//! Tests with a timeout run on a thread of their own, which the runner stops waiting for when the
+//! test times out. Threads can't be stopped from the outside, so the test keeps running in the
+//! background until it finishes or the tests' binary exits, which can affect the tests that run
+//! after it. With `--isolate`, the test's process is killed instead. Unlike `max_time`, which
+//! checks the duration of a test once it finished, a timeout doesn't wait for it to finish.
+//! 
+//! ### Slow Tests
+//! 
+//! Pass `--warn-slow <DURATION>` to emit a warning for every test that takes longer than the
+//! duration to run, to spot tests that got slow. Some tests, such as integration tests, are slow
+//! on purpose, so marking them with `#[testify::test(slow)]` exempts them from the warning. They're
+//! shown with `(slow)` next to their result, and their reports say they're slow too: the JSON
+//! report has a `slow` field, the JUnit report a `slow` property and the TAP report `slow: true`
+//! (see [Reports](#reports)). Benchmarks are never warned about, since they run many iterations.
+//! 
+//! ```sh
+//! $ cargo testify --warn-slow 500ms
+//! ```
+//! 
+//! ```ignore
+//! #[testify::test(slow)]
+//! fn migrates_the_whole_database() {}
+//! ```
+//! 
+//! Combined with `--fail-on-warnings`, it fails the run instead, keeping the fast tests fast.
+//! 
+//! ### Retries
+//! 
+//! Tests that fail because of something outside of your control, such as the network, can be run
+//! again with `--retries <n>`, which retries a failing test up to `n` times before considering it
+//! failed. Tests that only pass after being retried are flaky: they're marked as such next to
+//! their result, listed at the end of the run and counted in the summary, so the instability
+//! doesn't go unnoticed even if the run passes. Pass `--strict-flaky` to make them fail instead.
+//! 
+//! ```sh
+//! $ cargo testify --retries 2 --strict-flaky
+//! ```
+//! 
+//! A test can also set its own number of retries with `retries`, which overrides `--retries` for
+//! it, and how long to wait before retrying with `retry_backoff`, which gives a network service a
+//! moment to recover. There's no backoff by default, so tests are retried right away. The backoff
+//! grows exponentially: it doubles after every attempt, so `retry_backoff = "200ms"` waits 200ms
+//! before the second attempt, 400ms before the third, and so on. The wait isn't counted in the
+//! test's duration. Async tests wait on the async runtime, which keeps running the tasks they
+//! spawned in the meantime.
+//! 
+//! ```ignore
+//! #[testify::test(retries = 3, retry_backoff = "200ms")]
+//! async fn fetches_profile() {
+//!     client().get("/profile").await.unwrap();
+//! }
+//! ```
+//! 
+//! ### Stress Testing
+//! 
+//! To catch a failure that only shows up once in a while, pass `--repeat-until-failure` to run the
+//! tests over and over until an iteration fails, or `--repeat-until-failure=<n>` to give up after
+//! `n` iterations. The setups run before the first iteration and the cleanups after the last one,
+//! including the ones scoped to tags and suites, unless `--repeat-setup` is passed, which runs
+//! them all around every iteration. Runners set up by hand repeat the tests the same way, with
+//! `repeat_until_failure` and `repeat_setup` in their `TestifyConfig`.
+//! 
+//! ```sh
+//! $ cargo testify --repeat-until-failure=100 --tag flaky
+//! ```
+//! 
+//! ### Warnings
+//! 
+//! Testify warns about things that are likely mistakes but don't stop the tests from running.
+//! These are currently:
+//! 
+//! - A tag passed to `--tag` or `--exclude-tag` that isn't used by any test.
+//! - A tag used by a test that wasn't declared with `register_tags!`, if any tag was declared.
+//! - An invalid `RUST_TEST_THREADS` or `TESTIFY_RT_THREADS` value.
+//! - A setup or cleanup being skipped because of `--no-setup` or `--no-cleanup`.
+//! 
+//! The number of warnings is printed at the end of the run. To make the run fail if there were
+//! any, such as in CI, pass `--fail-on-warnings`.
+//! 
+//! ```sh
+//! $ cargo testify --fail-on-warnings
+//! ```
+//! 
+//! ### New Tests
+//! 
+//! Pass `--new-tests` to list the tests that weren't registered the previous time it was passed.
+//! The registered tests are stored in `.testify/known-tests.json`, relative to where `cargo
+//! testify` runs, or in the file passed to `--known-tests-file`. Nothing is listed the first time,
+//! when there's no file yet. Tests are identified as `<suite>/<name> (<case>)`, so renaming a test
+//! makes it count as new.
+//! 
+//! ```sh
+//! $ cargo testify --new-tests
+//! ```
+//! 
+//! ### Changed Tests
+//! 
+//! While iterating on a few tests, pass `--changed-tests-only` to only run the tests that are new
+//! or whose definition changed since they last passed with it. Every test carries a hash of its
+//! source, computed by the test macro from the function and the macro's arguments, and the hashes
+//! of the tests that pass are stored in `.testify/test-hashes.json`, or in the file passed to
+//! `--test-hashes-file`. The first time, when there's no file yet, every test runs. A test that
+//! fails keeps its previous hash, so it keeps running until it passes.
+//! 
+//! ```sh
+//! $ cargo testify --changed-tests-only
+//! ```
+//! 
+//! Only the test's own source is hashed, so it's not a replacement for a full run:
+//! - Changes to the functions, types and fixtures a test calls aren't detected, and neither are
+//!   changes to the setups and cleanups.
+//! - Changes to whitespace, comments and doc comments don't count, since the source is hashed as
+//!   tokens, without its doc attributes.
+//! - Tests registered at runtime with `register_test!` have no hash, so they always run.
+//! - Renaming a test, or changing its suite or case, makes it a new test.
+//! 
+//! ### Rerunning by Status
+//! 
+//! Pass `--save-last-run` to store the final status of the tests that run in
+//! `.testify/last-run.json`, relative to where `cargo testify` runs, or in the file passed to
+//! `--last-run-file`. Only the tests that ran are updated, so the others keep the status they had.
+//! Then, pass `--rerun-status` to only run the tests that had a status in the stored runs, which is
+//! one of:
+//! - `passed`: The test passed on its first attempt.
+//! - `failed`: The test failed, even after being retried.
+//! - `skipped`: The test hasn't run since the statuses were first stored, because it was filtered
+//!   out, ignored, or the run stopped early.
+//! - `flaky`: The test passed only after being retried.
+//! 
+//! It's combined with the other filters, and tests are matched by their IDs, like with
+//! `--new-tests`. `cargo testify` exits with an error if there's no previous run, and a warning is
+//! shown if some of the tests aren't registered anymore, which means the file is stale.
+//! 
+//! ```sh
+//! $ cargo testify --rerun-status failed
+//! ```
+//! 
+//! Runs with `--rerun-status` store the statuses as well, so rerunning the failed tests twice only
+//! runs the ones that failed again the first time.
+//! 
+//! ### Timing Regressions
+//! 
+//! Pass `--save-timings <PATH>` to write how long each passed test took to a file, and
+//! `--timings-baseline <PATH>` in a later run to compare against it. Tests that are more than
+//! `--regression-threshold` slower than in the baseline (20% by default) are listed after the
+//! results and fail the run. Tests missing from the baseline are skipped, and so are slowdowns under
+//! a millisecond, which are mostly noise. Both flags can point to the same file, since the baseline
+//! is read before the new timings are written.
+//! 
+//! ```sh
+//! $ cargo testify --save-timings .testify/timings.json
+//! $ cargo testify --timings-baseline .testify/timings.json --regression-threshold 50%
+//! ```
+//! 
+//! To see where the time goes, pass `--fold-timings <PATH>` to write the durations of the tests
+//! as folded stacks, which flamegraph tools such as `inferno-flamegraph` read. Each line is a test
+//! case, formatted as `<suite>;<tags>;<name>[;<case>...] <microseconds>`, where tests without a
+//! suite are under `(no suite)`, and tests without tags under `(no tags)`. The tags are the ones of
+//! the test's group, so they follow `--group-by`, and the levels of nested cases are frames of
+//! their own.
+//! 
+//! ```sh
+//! $ cargo testify --fold-timings timings.folded
+//! $ inferno-flamegraph timings.folded > timings.svg
+//! ```
+//! 
+//! ### Metrics
+//! 
+//! Pass `--metrics <PATH>` to write the results of the run to a file in the Prometheus text
+//! format, such as for a CI dashboard scraping it through the node exporter's textfile collector.
+//! Every metric is a gauge:
+//! - `testify_tests_total`, `testify_tests_failed` and `testify_tests_skipped`: The number of tests
+//!   that ran, failed and didn't run.
+//! - `testify_duration_seconds`: The time spent running the tests, added up.
+//! - `testify_tag_tests_passed` and `testify_tag_tests_failed`: The number of tests that passed and
+//!   failed with each tag, labeled with `tag`.
+//! 
+//! ```sh
+//! $ cargo testify --metrics metrics.prom
+//! ```
+//! 
+//! The same metrics are returned as a string by `testify::metrics::format`, for custom runners.
+//! 
+//! ### Reports
+//! 
+//! Pass `--report <KIND>:<PATH>` to also write a report of the run to a file, while the output is
+//! still shown in the console. It can be passed several times, such as to keep a JUnit report for
+//! CI alongside a copy of the output:
+//! - `human`: The output, as shown in the console but without colors.
+//! - `junit`: JUnit XML, with a `testsuite` for each suite, which most CI services can show.
+//! - `json`: A JSON object with the result of every test that ran, and the counts of the run.
+//! - `tap`: The Test Anything Protocol, version 13.
+//! 
+//! The JUnit and TAP reports also list the tests that didn't run, such as the ignored ones or the
+//! ones `--fail-fast` didn't reach, as skipped with the reason why.
+//! 
+//! Both the JUnit and JSON reports give each test's path, as matched by
+//! [`--exact`](#filtering-by-name), and its `description`, along with the counts of every tag as
+//! `--tag-summary` prints them and of every file as `--by-file` does. In JUnit, they're properties
+//! of the test cases and suites. In JSON, each test has a `path` and a `description`, and the
+//! summary has a `tags` and a `files` object, keyed by tag and by file.
+//! 
+//! ```sh
+//! $ cargo testify --report junit:target/junit.xml --report human:target/testify.log
+//! ```
+//! 
+//! JSON reports are indented to be easy to read. Pass `--json-compact` to write them on a single
+//! line instead, which is easier for tools that read one JSON object per line (`--json-pretty`
+//! switches back, such as to override an alias). Both end with a newline. Since there's no
+//! streaming JSON output that's read while the tests run, only the report written at the end, the
+//! default is the indented form rather than a compact one.
+//! 
+//! The reports are written when the run finishes, and a report that can't be written is reported
+//! as a warning. Each reporter is an observer, which receives the results of the whole run through
+//! `Observer::on_run_finished`, and the formats are available as `testify::reports::junit`, `json`
+//! and `tap`, for custom runners.
+//! 
+//! ### Verbose Output
+//! 
+//! Passing `--verbose` (`-v`) shows more details about each test. For example, tests marked with
+//! `should_fail` or `should_panic` show the failure they were expected to produce, and tests with a
+//! `description` show it under their result, which documents what the test is checking.
+//! 
+//! ```sh
+//! $ cargo testify -v
+//!    1. Register User... Ok. (2ms) (expected failure: "The password was too weak.")
+//! ```
+//! 
+//! Passing it twice (`-vv`) also prints the whole value returned by each failing test, such as
+//! `Some(false)` or the full `Err(...)`, which is useful when the failure message alone isn't
+//! enough to tell what went wrong.
+//! 
+//! To only get those details for the tests that fail, pass `--verbose-failures` instead: failing
+//! tests are shown as with `-vv`, while passing tests keep to one line. What the tests print with
+//! `testify::println!` is shown for failing tests either way. `--backtrace` implies
+//! `--verbose-failures`, but not the other way around, since capturing backtraces is slow.
+//! 
+//! ```sh
+//! $ cargo testify --verbose-failures
+//! ```
+//! 
+//! ### Compact Output
+//! 
+//! For large suites, pass `--dots` to print a single character for each test instead of a line,
+//! in the same order as the regular output. The failing tests are still listed with all their
+//! details in the recap at the end. The legend is printed above the characters:
+//! 
+//! - `.`: The test passed.
+//! - `F`: The test failed, or didn't fail or panic when it was expected to.
+//! - `P`: The test panicked.
+//! - `S`: The test was skipped, because it's ignored by `.testifyignore` or the run stopped early.
+//! 
+//! ```sh
+//! $ cargo testify --dots
+//! 2. Running 120 tests (0 skipped)...
+//!    Legend: . passed, F failed, P panicked, S skipped
+//!    .........F.........P..........
+//! ```
+//! 
+//! ### ASCII Output
+//! 
+//! For terminals that can't display emoji, pass `--ascii` to print the output without them (or any
+//! other non-ASCII symbol). When running tests programmatically, the symbols and indentation can
+//! be customized through the `theme` field of `TestifyConfig`.
+//! 
+//! ```sh
+//! $ cargo testify --ascii
+//! ```
+//! 
+//! ### Duration Precision
+//! 
+//! Durations are rounded to their unit, such as `1ms`, or to hundredths of a second once they
+//! reach a second, which can hide the differences between fine-grained timings. Pass
+//! `--duration-precision` with the number of decimals to show instead, such as `1.234ms` with
+//! `3`. It applies to every duration in the output, including the benchmarks' statistics. When
+//! running tests programmatically, set the `duration_precision` field of the theme.
+//! 
+//! ```sh
+//! $ cargo testify --duration-precision 3
+//! ```
+//! 
+//! ### Color Schemes
+//! 
+//! Passed and failed tests are shown in green and red by default, which some colorblind people
+//! can't tell apart. Pass `--color-scheme colorblind` to show them in blue and orange instead, and
+//! to mark them with `✓` and `✗` (or `+` and `x` with `--ascii`) so that the color isn't the only
+//! difference. When running tests programmatically, `ColorScheme::theme` builds the theme of a
+//! scheme, and the colors and symbols can be changed one by one through `OutputTheme`.
+//! 
+//! ```sh
+//! $ cargo testify --color-scheme colorblind
+//! ```
+//! 
+//! ### Backtraces
+//! 
+//! Pass `--backtrace` to print the backtrace of every test that panics, indented under its panic
+//! message, along with the other details `--verbose-failures` shows about failing tests. It also
+//! sets `RUST_BACKTRACE=full` for your project. How readable the backtraces are
+//! depends on the build profile: release builds without debug symbols may show few or no function
+//! names.
+//! 
+//! ```sh
+//! $ cargo testify --backtrace
+//! ```
+//! 
+//! ### Isolation
+//! 
+//! Testify catches panics by unwinding, which isn't possible if your binary is built with
+//! `panic = "abort"`: the first panicking test aborts the whole run, after printing which test it
+//! was. The same happens when a test crashes, such as with a segfault in unsafe code.
+//! 
+//! Pass `--isolate` to run each test in its own process instead, so an abort or a crash only fails
+//! that test. Panics are reported as usual, even with `panic = "abort"`, although tests marked with
+//! `should_panic` can't pass when panics abort. Starting a process for every test makes the run
+//! slower, and its startup is counted in the tests' durations.
+//! 
+//! The setups and cleanups only run in the main process, so isolated tests don't see the state
+//! they set up in memory, only outside of it (files, databases, servers, etc.). For the same
+//! reason, tests registered at runtime can't be isolated, and fail if `--isolate` is passed.
+//! 
+//! ```sh
+//! $ cargo testify --isolate
+//! ```
+//! 
+//! ### Parallel Execution
+//! 
+//! Tests are run in parallel, although their results are always printed in the same order. The
+//! number of tests run at the same time is taken from the first of:
+//! 
+//! 1. The `--jobs` (`-j`) argument.
+//! 2. The `RUST_TEST_THREADS` environment variable, same as with `cargo test`.
+//! 3. The number of CPUs available.
+//! 
+//! Setting either of them to `1` runs the tests one after the other in the current thread, in a
+//! deterministic order.
+//! 
+//! ```sh
+//! $ cargo testify -j 4
+//! $ RUST_TEST_THREADS=1 cargo testify
+//! ```
+//! 
+//! When the tests of a file share state, such as a fixture set up by the first of them, pass
+//! `--parallel-by file` to run the tests of each file one after the other, in order and on the
+//! same thread, while different files still run in parallel. It sits between the default
+//! (`--parallel-by case`), where any test can run at the same time as any other, and `-j 1`, where
+//! nothing runs in parallel.
+//! 
+//! ```sh
+//! $ cargo testify --parallel-by file
+//! ```
+//! 
+//! Async tests are driven by the thread running them, but the tasks they spawn run on the worker
+//! threads of a shared tokio runtime, which has one worker per CPU by default. When running many
+//! async tests in parallel, up to `jobs + workers` threads may be busy at once, so you may want to
+//! bound the runtime with `--max-threads` (or the `TESTIFY_RT_THREADS` environment variable).
+//! 
+//! ```sh
+//! $ cargo testify -j 8 --max-threads 2
+//! ```
+//! 
+//! ### GitHub Actions
+//! 
+//! When running on GitHub Actions (or when passing `--format github`), testify prints an `error`
+//! annotation for every failing test, pointing at the test's source, so that failures show up
+//! inline in the pull request. Pass `--format human` to disable them. Annotations are titled with
+//! the test's name and the reason it failed, such as `Register User [not_panicked]`. The recap of
+//! the failures at the end of the run is left out, since the annotations already list them.
+//! 
+//! The reasons are given by `TestStatus::reason()`, which returns a stable identifier for every
+//! status: `passed`, `panicked`, `not_panicked` (expected to panic), `failed`, `not_failed`
+//! (expected to fail) and `skipped` (a test it depends on didn't pass). Tools reading testify's
+//! results should match on them, along with the result's message, rather than on the wording of
+//! the output.
+//! 
+//! ```sh
+//! $ cargo testify --format github
+//! ```
+//! 
+//! ### Shell Completions
+//! 
+//! `cargo testify completions <shell>` prints a completion script for `bash`, `zsh` or `fish` to
+//! stdout. The script adds to the completion of `cargo` instead of replacing it, so it's installed
+//! next to it:
+//! - bash: cargo's completion completes `cargo testify` as `cargo-testify`, which needs
+//!   bash-completion 2.12 or later.
+//! - zsh: cargo's completion calls the `_cargo-testify` function, which is loaded from a directory
+//!   in `$fpath`.
+//! - fish: the completions are added to the ones of `cargo`, from a file that fish loads on
+//!   startup.
+//! 
+//! ```sh
+//! $ cargo testify completions bash > ~/.local/share/bash-completion/completions/cargo-testify
+//! $ cargo testify completions zsh > ~/.zfunc/_cargo-testify
+//! $ cargo testify completions fish > ~/.config/fish/conf.d/cargo-testify.fish
+//! ```
+//! 
+//! Since `completions` is a subcommand, filtering the tests by a name that's exactly
+//! `completions` needs a glob that matches it instead, such as `[c]ompletions`.
 //! 
 //! ### Passing Arguments to `cargo run`
 //! 
@@ -196,14 +1380,106 @@
 //! to run in your project's binary. Any arguments passed after `--` when running `cargo testify`
 //! will be passed to cargo. For example:
 //! 
-//! ```
-//! // To run `cargo run` in release mode
+//! ```sh
+//! # To run `cargo run` in release mode
 //! $ cargo testify -- --release
 //! ```
+//! 
+//! In a workspace, or in a package with several binaries, select the binary with
+//! `#[testify::main]` with `--package` (`-p`) and `--bin`. They're forwarded to `cargo run` as is,
+//! so they can't also be passed after `--`.
+//! 
+//! ```sh
+//! $ cargo testify -p example --bin example
+//! ```
+//! 
+//! ### Custom Runners
+//! 
+//! To run the tests with something other than `cargo run`, such as `cross` to cross-compile them
+//! or a binary that was already built, pass the command to `--runner` or set it in the
+//! `TESTIFY_RUNNER` environment variable. It's split on whitespace, so its arguments can't contain
+//! spaces. testify's environment variables are still set, and `--package`, `--bin` and the
+//! arguments after `--` are passed to the command after its own arguments.
+//! 
+//! ```sh
+//! $ cargo testify --runner "cross run --target aarch64-unknown-linux-gnu"
+//! $ TESTIFY_RUNNER=./target/release/my-app cargo testify
+//! ```
+//! 
+//! ## Running Tests Programmatically
+//! 
+//! `testify::run()`, which `#[testify::main]` calls, runs the registered tests with the
+//! configuration passed by `cargo testify`. If you'd rather build the set of tests yourself, for
+//! example to write tooling around testify, use `testify::Runner` instead. It prints the same
+//! output, but returns a `TestReport` with the outcome of every test instead of exiting.
+//! 
+//! ```ignore
+//! use testify::{Runner, runner::TestifyConfig, test::Test};
+//! 
+//! let report = Runner::new()
+//!     .with_tests(vec![Test { /* ... */ }])
+//!     .with_config(TestifyConfig { fail_fast: true, ..Default::default() })
+//!     .run();
+//! 
+//! println!("{} tests failed", report.failures());
+//! ```
+//! 
+//! A test can start a run of its own too, such as to check how a failure is reported. Runs aren't
+//! reentrant, though: a run replaces the process's panic hook until it finishes, and sets the
+//! report returned by `testify::last_report()`. Runs started from tests that run in parallel have
+//! to take turns, for example by holding a shared `Mutex` while they run.
+//! 
+//! To look at the structure of the suite without running it, such as to show it as a tree in an
+//! editor, call `testify::plan()`. It returns the tests `run()` would run, grouped and sorted the
+//! same way, with the metadata of every case. The returned types implement serde's `Serialize`.
+//! 
+//! ```ignore
+//! let plan = testify::plan();
+//! println!("{}", serde_json::to_string_pretty(&plan).unwrap());
+//! ```
+//! 
+//! To follow the run as it happens, such as to show it in a live UI or to find out which test is
+//! hanging, implement `testify::observer::Observer` and add it with `Runner::with_observer`. It's
+//! notified right before each test starts, when its result is shown, and once the run finishes.
+//! 
+//! ```ignore
+//! struct Progress;
+//! 
+//! impl testify::observer::Observer for Progress {
+//!     fn on_test_started(&self, test: &Test) {
+//!         eprintln!("Running {}...", test.name);
+//!     }
+//! }
+//! 
+//! let report = Runner::new().with_observer(Progress).run();
+//! ```
+//! 
+//! The runner's output goes to stdout, unless another destination is passed to
+//! `Runner::with_output`, which takes anything implementing `std::io::Write`. To capture it in
+//! memory, such as to check how a run is shown, pass a `testify::OutputBuffer` and read it back
+//! with `OutputBuffer::contents` after the run. What the tests themselves print isn't redirected.
+//! The output is colored if stdout is a terminal, which can be turned off by setting the
+//! `NO_COLOR` environment variable.
+//! 
+//! ```ignore
+//! let output = testify::OutputBuffer::default();
+//! let report = Runner::new().with_tests(tests).with_output(output.clone()).run();
+//! 
+//! assert!(output.contents().contains("Finished running tests."));
+//! ```
 
 #[doc(hidden)]
 pub use testify_core::*;
 
+pub use testify_core::macros;
+
+pub use testify_core::{
+    AsyncRuntime, ChainedPanicHook, OutputBuffer, PlannedCase, PlannedGroup, PlannedTest, Runner,
+    TagCounts, TestReport, WaitTimeout, artifact_dir, async_runtime, chain_panic_hook,
+    is_test_run, last_report, plan, set_async_runtime, shutdown_services, spawn_service,
+    unchain_panic_hook, wait_until, wait_until_async, wait_until_every, wait_until_every_async,
+};
+
 pub use testify_macros::*;
 
 #[doc(hidden)]